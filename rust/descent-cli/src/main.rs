@@ -1,4 +1,5 @@
-//! descent-rs CLI: `generate` (parser generation) plus the
+//! descent-rs CLI: `generate` (parser generation through the emit::Registry
+//! backends — `backends` lists them), `sample`, `fuzz-target`, plus the
 //! differential-testing probe subcommands (tokens/ast/context).
 //!
 //! Front-end: udon-core reader by default; `--oracle` selects the
@@ -23,28 +24,69 @@ fn main() -> ExitCode {
             dump_context(path, trace, frontend)
         }
         (Some("classify"), Some(path)) => classify(path, frontend),
-        (Some("generate"), Some(path)) => {
-            let trace = args.iter().skip(3).any(|s| s == "--trace" || s == "true");
-            if let Some(bi) = args.iter().position(|s| s == "--backend") {
-                if args.get(bi + 1).map(|s| s.as_str()) == Some("pushdown") {
-                    return generate_pushdown(path, &args, frontend);
-                }
+        (Some("validate"), Some(path)) => validate(path, frontend),
+        (Some("lint"), Some(path)) => lint(path, frontend),
+        (Some("manifest"), Some(flag)) if flag == "--diff" => match (args.get(3), args.get(4)) {
+            (Some(old), Some(path)) => manifest_diff(old, path, frontend),
+            _ => {
+                eprintln!("usage: descent-rs manifest --diff <old.json> <file.desc> [--oracle]");
+                ExitCode::from(2)
             }
-            generate(path, trace, frontend)
+        },
+        (Some("manifest"), Some(path)) => manifest(path, frontend),
+        (Some("sample"), Some(path)) => sample(path, &args, frontend),
+        (Some("fuzz-target"), Some(path)) => {
+            let out = args.iter().position(|s| s == "--out").and_then(|i| args.get(i + 1));
+            fuzz_target(path, out.map_or("fuzz", |s| s.as_str()), frontend)
         }
+        (Some("generate"), Some(path)) => generate(path, &args[3..], frontend),
+        (Some("backends"), _) => backends(),
         _ => {
             eprintln!("usage: descent-rs <tokens|ast|context> <file.desc> [trace] [--oracle]");
-            eprintln!("       descent-rs generate <file.desc> [--trace] [--oracle]");
-            eprintln!("       descent-rs generate <file.desc> --backend pushdown [--event-path <rust::path>]");
+            eprintln!("       descent-rs <classify|validate|lint|manifest> <file.desc> [--oracle]");
+            eprintln!("       descent-rs manifest --diff <old.json> <file.desc> [--oracle]");
+            eprintln!("       descent-rs generate <file.desc> [--backend <name>] [--out <dir>] [backend options] [--oracle]");
+            eprintln!("       descent-rs backends");
+            eprintln!("       descent-rs sample <file.desc> [--count N] [--seed S] [--max-len N] [--out <dir>] [--oracle]");
+            eprintln!("       descent-rs fuzz-target <file.desc> [--out <dir>] [--oracle]");
             ExitCode::from(2)
         }
     }
 }
 
-/// Generate Rust parser source to stdout (mirrors Ruby
-/// `Descent.generate(file, target: :rust, trace:)` plus the regenerate
-/// driver's blank-run collapse — see emit::rust::engine::post_process).
-fn generate(path: &str, trace: bool, frontend: Frontend) -> ExitCode {
+/// Generate a parser with the `--backend` (default `rust`) from the
+/// emit::Registry, its options parsed against the backend's schema. A
+/// single output file goes to stdout unless `--out` is given; several are
+/// written under `--out` (default `.`), printing each path.
+fn generate(path: &str, args: &[String], frontend: Frontend) -> ExitCode {
+    let registry = descent_core::emit::Registry::builtin();
+    let flag = |name: &str| args.iter().position(|s| s == name).and_then(|i| args.get(i + 1));
+    let backend = match registry.lookup(flag("--backend").map_or("rust", |s| s.as_str())) {
+        Ok(b) => b,
+        Err(e) => {
+            eprint!("{}", e.render(""));
+            return ExitCode::from(2);
+        }
+    };
+    // Driver options are ours; the rest belong to the backend.
+    let mut backend_args = Vec::new();
+    let mut it = args.iter();
+    while let Some(a) = it.next() {
+        match a.as_str() {
+            "--backend" | "--out" => {
+                it.next();
+            }
+            "--oracle" => {}
+            _ => backend_args.push(a.clone()),
+        }
+    }
+    let opts = match descent_core::emit::BackendOptions::from_args(backend.name(), backend.options(), &backend_args) {
+        Ok(o) => o,
+        Err(e) => {
+            eprint!("{}", e.render(""));
+            return ExitCode::from(2);
+        }
+    };
     let content = match std::fs::read_to_string(path) {
         Ok(c) => c,
         Err(e) => {
@@ -55,25 +97,106 @@ fn generate(path: &str, trace: bool, frontend: Frontend) -> ExitCode {
     let ir = match descent_core::build_ir_with(&content, path, frontend) {
         Ok(ir) => ir,
         Err(e) => {
-            eprintln!("{e}");
+            eprint!("{}", e.render(&content));
+            return ExitCode::FAILURE;
+        }
+    };
+    let files = match backend.generate(&ir, &opts) {
+        Ok(f) => f,
+        Err(e) => {
+            eprint!("{}", e.render(&content));
             return ExitCode::FAILURE;
         }
     };
-    let opts = descent_core::emit::rust::Options { trace, ..Default::default() };
-    match descent_core::emit::rust::generate(&ir, &opts) {
-        Ok(code) => {
-            print!("{code}");
-            ExitCode::SUCCESS
+    let out = flag("--out");
+    if let ([file], None) = (files.as_slice(), out) {
+        print!("{}", file.contents);
+        return ExitCode::SUCCESS;
+    }
+    let out = out.map_or(".", |s| s.as_str());
+    for file in files {
+        let dest = std::path::Path::new(out).join(&file.path);
+        if let Err(e) = dest.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|_| std::fs::write(&dest, file.contents)) {
+            eprintln!("{}: {e}", dest.display());
+            return ExitCode::FAILURE;
         }
+        println!("{}", dest.display());
+    }
+    ExitCode::SUCCESS
+}
+
+/// The registered backends with their options (what `generate --backend`
+/// accepts).
+fn backends() -> ExitCode {
+    for backend in descent_core::emit::Registry::builtin().iter() {
+        println!("{:<10} {}", backend.name(), backend.description());
+        for opt in backend.options() {
+            let usage = format!("--{}{}", opt.name, opt.value.map(|v| format!(" {v}")).unwrap_or_default());
+            println!("    {usage:<26} {}", opt.help);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Random inputs walked from the grammar (descent_core::sample): one per
+/// line, escaped, or one file each under `--out` (a fuzz corpus). The
+/// case-row coverage report goes to stderr.
+fn sample(path: &str, args: &[String], frontend: Frontend) -> ExitCode {
+    let flag = |name: &str, default: u64| -> Result<u64, String> {
+        match args.iter().position(|s| s == name).map(|i| args.get(i + 1)) {
+            None => Ok(default),
+            Some(v) => v.and_then(|v| v.parse().ok()).ok_or(format!("{name} takes a number")),
+        }
+    };
+    let (count, seed, max_len) = match (flag("--count", 16), flag("--seed", 0), flag("--max-len", 256)) {
+        (Ok(c), Ok(s), Ok(m)) => (c, s, m),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            eprintln!("{e}");
+            return ExitCode::from(2);
+        }
+    };
+    let out = args.iter().position(|s| s == "--out").and_then(|i| args.get(i + 1));
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
         Err(e) => {
-            eprintln!("template error: {e:#}");
-            ExitCode::FAILURE
+            eprintln!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let ir = match descent_core::build_ir_with(&content, path, frontend) {
+        Ok(ir) => ir,
+        Err(e) => {
+            eprint!("{}", e.render(&content));
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut sampler = descent_core::sample::Sampler::new(&ir, seed).max_len(max_len as usize);
+    if let Some(dir) = out {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            eprintln!("{dir}: {e}");
+            return ExitCode::FAILURE;
+        }
+    }
+    for i in 0..count {
+        let bytes = sampler.sample();
+        match out {
+            Some(dir) => {
+                let dest = std::path::Path::new(dir).join(format!("sample-{seed}-{i:04}"));
+                if let Err(e) = std::fs::write(&dest, &bytes) {
+                    eprintln!("{}: {e}", dest.display());
+                    return ExitCode::FAILURE;
+                }
+            }
+            None => println!("{}", bytes.escape_ascii()),
         }
     }
+    eprint!("{}", sampler.coverage().report());
+    ExitCode::SUCCESS
 }
 
-/// Generate the pushdown (explicit-stack, resumable) parser to stdout.
-fn generate_pushdown(path: &str, args: &[String], frontend: Frontend) -> ExitCode {
+/// Write the differential fuzz crate (emit::fuzz) under `out`, printing
+/// each file written. Run it with `cargo fuzz run differential` from `out`.
+fn fuzz_target(path: &str, out: &str, frontend: Frontend) -> ExitCode {
     let content = match std::fs::read_to_string(path) {
         Ok(c) => c,
         Err(e) => {
@@ -84,17 +207,25 @@ fn generate_pushdown(path: &str, args: &[String], frontend: Frontend) -> ExitCod
     let ir = match descent_core::build_ir_with(&content, path, frontend) {
         Ok(ir) => ir,
         Err(e) => {
-            eprintln!("{e}");
+            eprint!("{}", e.render(&content));
+            return ExitCode::FAILURE;
+        }
+    };
+    let files = match descent_core::emit::fuzz::generate(&ir) {
+        Ok(f) => f,
+        Err(e) => {
+            eprint!("{}", e.render(&content));
             return ExitCode::FAILURE;
         }
     };
-    let mut opts = descent_core::emit::rust_pushdown::PdOptions::default();
-    if let Some(pi) = args.iter().position(|s| s == "--event-path") {
-        if let Some(p) = args.get(pi + 1) {
-            opts.event_path = p.clone();
+    for (rel, text) in files {
+        let dest = std::path::Path::new(out).join(rel);
+        if let Err(e) = dest.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|_| std::fs::write(&dest, text)) {
+            eprintln!("{}: {e}", dest.display());
+            return ExitCode::FAILURE;
         }
+        println!("{}", dest.display());
     }
-    print!("{}", descent_core::emit::rust_pushdown::generate(&ir, &opts));
     ExitCode::SUCCESS
 }
 
@@ -110,7 +241,7 @@ fn classify(path: &str, frontend: Frontend) -> ExitCode {
     let ir = match descent_core::build_ir_with(&content, path, frontend) {
         Ok(ir) => ir,
         Err(e) => {
-            eprintln!("{e}");
+            eprint!("{}", e.render(&content));
             return ExitCode::FAILURE;
         }
     };
@@ -118,6 +249,126 @@ fn classify(path: &str, frontend: Frontend) -> ExitCode {
     ExitCode::SUCCESS
 }
 
+/// Validate the grammar's IR and print every error and warning (Ruby:
+/// Validator#report). Exits non-zero when there are errors.
+fn validate(path: &str, frontend: Frontend) -> ExitCode {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let (ir, warnings) = match descent_core::build_unvalidated(&content, path, frontend) {
+        Ok(built) => built,
+        Err(e) => {
+            eprint!("{}", e.render(&content));
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut validator = descent_core::Validator::new(&ir);
+    validator.issues = warnings;
+    let validator = validator.validate();
+    print!("{}", validator.report(path, &content));
+    if validator.is_valid() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Print the parser manifest (emit::manifest) as JSON: the vocabulary the
+/// generated parser can emit, for diffing against a spec.
+fn manifest(path: &str, frontend: Frontend) -> ExitCode {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let ir = match descent_core::build_ir_with(&content, path, frontend) {
+        Ok(ir) => ir,
+        Err(e) => {
+            eprint!("{}", e.render(&content));
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&descent_core::emit::manifest::generate(&ir)).unwrap());
+    ExitCode::SUCCESS
+}
+
+/// Diff a saved manifest against the grammar's current one and print the
+/// changes. Exits non-zero when vocabulary was removed (consumers matching on
+/// it break); additions and reclassifications are reported but pass.
+fn manifest_diff(old_path: &str, path: &str, frontend: Frontend) -> ExitCode {
+    let old = match std::fs::read_to_string(old_path).map_err(|e| e.to_string()).and_then(|s| {
+        serde_json::from_str::<serde_json::Value>(&s).map_err(|e| e.to_string())
+    }) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{old_path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let ir = match descent_core::build_ir_with(&content, path, frontend) {
+        Ok(ir) => ir,
+        Err(e) => {
+            eprint!("{}", e.render(&content));
+            return ExitCode::FAILURE;
+        }
+    };
+    let diff = descent_core::emit::manifest::diff(&old, &descent_core::emit::manifest::generate(&ir));
+    print!("{}", diff.report());
+    if diff.has_removals() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Run the advisory lints (descent_core::lint) and print each finding.
+/// Exits non-zero when any lint fires, so CI can gate on a clean grammar.
+fn lint(path: &str, frontend: Frontend) -> ExitCode {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let machine = match descent_core::parse_with(&content, path, frontend) {
+        Ok(m) => m,
+        Err(e) => {
+            eprint!("{}", e.render(&content));
+            return ExitCode::FAILURE;
+        }
+    };
+    let ir = match descent_core::build_ir_from(&machine, path) {
+        Ok(ir) => ir,
+        Err(e) => {
+            eprint!("{}", e.render(&content));
+            return ExitCode::FAILURE;
+        }
+    };
+    let lints = descent_core::lint::lint(&machine, &ir);
+    for d in &lints {
+        println!("{}", d.clone().in_file(path).render(&content));
+    }
+    if lints.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
 /// Dump the Rust-emitter template context (differential vs
 /// rust/tools/dump_context.rb on the Ruby side).
 fn dump_context(path: &str, trace: bool, frontend: Frontend) -> ExitCode {
@@ -131,11 +382,12 @@ fn dump_context(path: &str, trace: bool, frontend: Frontend) -> ExitCode {
     let ir = match descent_core::build_ir_with(&content, path, frontend) {
         Ok(ir) => ir,
         Err(e) => {
-            eprintln!("{e}");
+            eprint!("{}", e.render(&content));
             return ExitCode::FAILURE;
         }
     };
-    let opts = descent_core::emit::rust::Options { trace, ..Default::default() };
+    // Ruby's generator defaults to `streaming: true`; match its context.
+    let opts = descent_core::emit::rust::Options { trace, streaming: true, ..Default::default() };
     let ctx = descent_core::emit::rust::build_context(&ir, &opts);
     println!("{}", serde_json::to_string_pretty(&ctx).unwrap());
     ExitCode::SUCCESS
@@ -149,10 +401,10 @@ fn dump(path: &str, ast: bool, frontend: Frontend) -> ExitCode {
             return ExitCode::FAILURE;
         }
     };
-    let tokens = match descent_core::tokenize(&content, path, frontend) {
+    let (tokens, _) = match descent_core::tokenize(&content, path, frontend) {
         Ok(t) => t,
         Err(e) => {
-            eprintln!("LexerError: {e}");
+            eprint!("{}", e.render(&content));
             return ExitCode::FAILURE;
         }
     };
@@ -160,7 +412,7 @@ fn dump(path: &str, ast: bool, frontend: Frontend) -> ExitCode {
         match descent_core::Parser::new(tokens).parse() {
             Ok(m) => descent_core::dump::machine_to_json(&m),
             Err(e) => {
-                eprintln!("ParseError: {e}");
+                eprint!("{}", e.in_file(path).render(&content));
                 return ExitCode::FAILURE;
             }
         }
@@ -9,6 +9,18 @@ pub struct Machine {
     pub consts: Vec<ConstDecl>,
     pub functions: Vec<Function>,
     pub keywords: Vec<Keywords>,
+    pub templates: Vec<Template>,
+    pub terminators: Vec<TerminatorSet>,
+    /// Grammar-wide `|allow[lint_id]` directives (before the first function).
+    pub allows: Vec<Allow>,
+}
+
+/// `|allow[lint_id]`: silence one `lint` check, grammar-wide when it sits
+/// in the header, or for the enclosing function when it sits inside one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Allow {
+    pub lint: String,
+    pub lineno: usize,
 }
 
 /// Named integer constant: `|const[NAME] <int>`. NAME is SCREAMING_CASE and
@@ -22,6 +34,49 @@ pub struct ConstDecl {
     pub lineno: usize,
 }
 
+/// `|template[name] :T ...`: case rows shared by many states, spliced in
+/// wherever a state says `|use[name(Type)]`, with each param replaced by
+/// its argument in event-type positions. Expanded away before IR building,
+/// so the IR never sees a template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pub name: String,
+    pub params: Vec<String>,
+    pub cases: Vec<Case>,
+    pub eof_handler: Option<EOFHandler>,
+    pub lineno: usize,
+}
+
+/// `|use[name(Arg, ...)]` row: splice template `name` into the state's
+/// cases at index `at` (the rows written above it stay above it).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateUse {
+    pub name: String,
+    pub args: Vec<String>,
+    pub at: usize,
+    pub lineno: usize,
+}
+
+/// `|terminators[name] eof '\n' ' ' :bracket`: the bytes (quoted chars,
+/// `<class>`es, `:param`s) and/or `eof` that end a value. A state's
+/// `|ends[name -> Type]` expands to a `| Type(USE_MARK) |return` row per
+/// member before IR building.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminatorSet {
+    pub name: String,
+    pub members: Vec<String>,
+    pub lineno: usize,
+}
+
+/// `|ends[set -> Type]` row: the state's value ends, as `Type`, on any
+/// member of terminator set `set`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateEnds {
+    pub set: String,
+    pub event_type: String,
+    pub lineno: usize,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct TypeDecl {
     pub name: String,
@@ -38,6 +93,13 @@ pub struct Function {
     pub states: Vec<State>,
     pub eof_handler: Option<EOFHandler>,
     pub entry_actions: Vec<Command>,
+    /// Function-scoped `|allow[lint_id]` directives.
+    pub allows: Vec<Allow>,
+    /// `|unclosed Name`: declared delimited construct; EOF while open warns
+    /// `Unclosed<Name>`.
+    pub unclosed: Option<String>,
+    /// `|mixed`: positional and delimited exits are intentional here.
+    pub mixed: bool,
     pub lineno: usize,
 }
 
@@ -46,6 +108,13 @@ pub struct State {
     pub name: String,
     pub cases: Vec<Case>,
     pub eof_handler: Option<EOFHandler>,
+    /// Actions on the `|state[:name] ...` line, run on every entry into the
+    /// state (not on `|>>` self-loops).
+    pub entry_actions: Vec<Command>,
+    /// Template splices, in row order.
+    pub uses: Vec<TemplateUse>,
+    /// `|ends[...]` rows, in row order.
+    pub ends: Vec<StateEnds>,
     pub lineno: usize,
 }
 
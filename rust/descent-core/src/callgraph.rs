@@ -0,0 +1,284 @@
+//! Call-graph analysis: recursion that can re-enter a function without
+//! consuming input.
+//!
+//! The call stack IS the element stack, so a function that reaches a call
+//! back to itself (directly or through others) before any byte is consumed
+//! recurses forever on the same input: a stack overflow in the recursive
+//! backend, unbounded frame growth in the pushdown one.
+//!
+//! An edge `f -> g` exists when some path from `f`'s entry reaches `/g`
+//! with nothing consumed yet. Paths start at the entry actions, then the
+//! first state, and follow `|>> :state` transitions taken before consuming.
+//! Only `->` / `->[...]` count as consuming, plus a call to a function that
+//! itself consumes on every path to its return (computed to a fixed point,
+//! starting from "consumes nothing" so the answer only errs toward
+//! reporting). A case without a transition stays in its state, already
+//! visited; `|eof` handlers run with no input left and are not followed.
+//! `KEYWORDS(map)` is a call to the map's fallback.
+
+use crate::ir::{Command, Function, ParserIR};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// `caller` reaches `/callee` at `lineno` without having consumed input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallEdge {
+    pub caller: String,
+    pub callee: String,
+    pub lineno: usize,
+}
+
+/// A closed path of non-advancing calls: each edge's callee is the next
+/// edge's caller, and the last callee is the first caller.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cycle {
+    pub edges: Vec<CallEdge>,
+}
+
+impl Cycle {
+    /// `/a -> /b -> /a`
+    pub fn path(&self) -> String {
+        let mut names: Vec<String> = self.edges.iter().map(|e| format!("/{}", e.caller)).collect();
+        names.extend(self.edges.first().map(|e| format!("/{}", e.caller)));
+        names.join(" -> ")
+    }
+}
+
+/// What running a command list from a given point can do.
+#[derive(Default)]
+struct Flow {
+    consumed: bool,
+    /// Every path left the list (return or transition).
+    terminated: bool,
+    returns_unconsumed: bool,
+    /// States entered by a transition before consuming.
+    targets: Vec<String>,
+    calls: Vec<(String, usize)>,
+}
+
+struct Scan<'a> {
+    ir: &'a ParserIR,
+    consumes: &'a HashMap<&'a str, bool>,
+}
+
+impl Scan<'_> {
+    fn callee<'c>(&self, cmd: &'c Command) -> Option<&'c str> {
+        match cmd.ctype.as_str() {
+            "call" if cmd.args.get("is_error").and_then(|v| v.as_bool()) != Some(true) => cmd.arg_str("name"),
+            "assign" => {
+                let expr = cmd.arg_str("expr")?.trim().strip_prefix('/')?;
+                expr.split('(').next().filter(|n| !n.is_empty() && n.chars().all(|c| c.is_alphanumeric() || c == '_'))
+            }
+            _ => None,
+        }
+    }
+
+    fn run(&self, cmds: &[Command], consumed: bool, lineno: usize, flow: &mut Flow) {
+        flow.consumed = consumed;
+        for cmd in cmds {
+            match cmd.ctype.as_str() {
+                "advance" | "advance_to" => flow.consumed = true,
+                "return" => {
+                    flow.returns_unconsumed |= !flow.consumed;
+                    flow.terminated = true;
+                    return;
+                }
+                "transition" => {
+                    let target = cmd.arg_str("value").unwrap_or("");
+                    if !flow.consumed && !target.is_empty() {
+                        flow.targets.push(crate::validate::normalize_state_name(target));
+                    }
+                    flow.terminated = true;
+                    return;
+                }
+                "keywords_lookup" => {
+                    let map = cmd.arg_str("name").unwrap_or("");
+                    let fallback = self.ir.keywords.iter().find(|k| k.name == map).and_then(|k| k.fallback_func.as_ref());
+                    if let Some(f) = fallback.filter(|_| !flow.consumed) {
+                        flow.calls.push((f.clone(), lineno));
+                    }
+                }
+                "conditional" => {
+                    let clauses = cmd.clauses.as_deref().unwrap_or_default();
+                    let has_else = clauses.iter().any(|c| c.condition.is_none());
+                    let (mut all_consumed, mut all_terminated) = (has_else, has_else);
+                    for clause in clauses {
+                        let mut inner = Flow::default();
+                        self.run(&clause.commands, flow.consumed, lineno, &mut inner);
+                        all_consumed &= inner.consumed || inner.terminated;
+                        all_terminated &= inner.terminated;
+                        flow.returns_unconsumed |= inner.returns_unconsumed;
+                        flow.targets.extend(inner.targets);
+                        flow.calls.extend(inner.calls);
+                    }
+                    if all_terminated {
+                        flow.terminated = true;
+                        return;
+                    }
+                    flow.consumed |= all_consumed;
+                }
+                _ => {
+                    if let Some(callee) = self.callee(cmd) {
+                        if !flow.consumed {
+                            flow.calls.push((callee.to_string(), lineno));
+                        }
+                        flow.consumed |= self.consumes.get(callee).copied().unwrap_or(false);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The calls `func` can reach before consuming, and whether it can
+    /// return without consuming.
+    fn function(&self, func: &Function) -> (Vec<(String, usize)>, bool) {
+        let mut entry = Flow::default();
+        self.run(&func.entry_actions, false, func.lineno, &mut entry);
+        let mut calls = entry.calls;
+        if entry.consumed || entry.terminated {
+            return (calls, entry.returns_unconsumed);
+        }
+        let mut returns_unconsumed = false;
+        let mut seen = HashSet::new();
+        let mut queue: VecDeque<&str> = func.states.first().map(|s| s.name.as_str()).into_iter().collect();
+        while let Some(name) = queue.pop_front() {
+            if !seen.insert(name) {
+                continue;
+            }
+            let Some(state) = func.states.iter().find(|s| s.name == name) else {
+                continue;
+            };
+            for kase in &state.cases {
+                let mut flow = Flow::default();
+                self.run(&kase.commands, false, kase.lineno, &mut flow);
+                calls.extend(flow.calls);
+                returns_unconsumed |= flow.returns_unconsumed;
+                for t in &flow.targets {
+                    if let Some(s) = func.states.iter().find(|s| &s.name == t) {
+                        queue.push_back(&s.name);
+                    }
+                }
+            }
+        }
+        (calls, returns_unconsumed)
+    }
+}
+
+/// Every non-advancing call edge in the grammar, callers in grammar order.
+pub fn edges(ir: &ParserIR) -> Vec<CallEdge> {
+    let mut consumes: HashMap<&str, bool> = ir.functions.iter().map(|f| (f.name.as_str(), false)).collect();
+    loop {
+        let scan = Scan { ir, consumes: &consumes };
+        let next: HashMap<&str, bool> = ir.functions.iter().map(|f| (f.name.as_str(), !scan.function(f).1)).collect();
+        if next == consumes {
+            break;
+        }
+        consumes = next;
+    }
+    let scan = Scan { ir, consumes: &consumes };
+    let defined: HashSet<&str> = ir.functions.iter().map(|f| f.name.as_str()).collect();
+    let mut out: Vec<CallEdge> = Vec::new();
+    for func in &ir.functions {
+        for (callee, lineno) in scan.function(func).0 {
+            let edge = CallEdge { caller: func.name.clone(), callee, lineno };
+            if defined.contains(edge.callee.as_str()) && !out.contains(&edge) {
+                out.push(edge);
+            }
+        }
+    }
+    out
+}
+
+/// One shortest cycle per function that can re-enter itself without
+/// consuming; functions already on a reported cycle start no new one.
+pub fn non_advancing_cycles(ir: &ParserIR) -> Vec<Cycle> {
+    let edges = edges(ir);
+    let mut reported: HashSet<String> = HashSet::new();
+    let mut cycles = Vec::new();
+    for func in &ir.functions {
+        let start = func.name.as_str();
+        if reported.contains(start) {
+            continue;
+        }
+        // BFS from `start`; `via[n]` is the edge that first reached `n`.
+        let mut via: HashMap<&str, &CallEdge> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        let mut closing = None;
+        'search: while let Some(n) = queue.pop_front() {
+            for e in edges.iter().filter(|e| e.caller == n) {
+                if e.callee == start {
+                    closing = Some(e);
+                    break 'search;
+                }
+                if !via.contains_key(e.callee.as_str()) {
+                    via.insert(&e.callee, e);
+                    queue.push_back(&e.callee);
+                }
+            }
+        }
+        let Some(last) = closing else {
+            continue;
+        };
+        let mut path = vec![last.clone()];
+        let mut n = last.caller.as_str();
+        while n != start {
+            let e = via[n];
+            path.push(e.clone());
+            n = &e.caller;
+        }
+        path.reverse();
+        reported.extend(path.iter().map(|e| e.caller.clone()));
+        cycles.push(Cycle { edges: path });
+    }
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycles(desc: &str) -> Vec<String> {
+        let m = crate::parse_with(desc, "t.desc", crate::Frontend::OracleLexer).unwrap();
+        let ir = crate::IRBuilder::new(&m).build().unwrap();
+        non_advancing_cycles(&ir).iter().map(Cycle::path).collect()
+    }
+
+    #[test]
+    fn finds_cycles_that_skip_consuming() {
+        let desc = "\
+|parser t
+|entry-point /doc
+|function[doc]
+  |state[:main]
+    |c['(']   | /list  |>>
+    |default  | ->     |>>
+|function[list]
+  |state[:main]
+    |c[')']   | ->     |return
+    |default  | /item  |>>
+|function[item]
+  |state[:main]
+    |c['(']   | /list  |>>
+    |default  | ->     |return
+";
+        assert_eq!(cycles(desc), vec!["/list -> /item -> /list"]);
+    }
+
+    #[test]
+    fn consuming_before_the_call_breaks_the_cycle() {
+        let desc = "\
+|parser t
+|entry-point /doc
+|function[doc]
+  |state[:main]
+    |c['(']   | -> | /doc  |>>
+    |default  | ->         |return
+|function[wrap]
+  |state[:main]
+    |default  | /eat | /wrap  |return
+|function[eat]
+  |state[:main]
+    |default  | ->  |return
+";
+        assert!(cycles(desc).is_empty());
+    }
+}
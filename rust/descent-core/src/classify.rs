@@ -54,6 +54,17 @@ pub enum Kind {
 }
 
 impl Kind {
+    /// Lowercase name, as the manifest records it.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Positional => "positional",
+            Kind::Delimited => "delimited",
+            Kind::Mixed => "mixed",
+            Kind::Inert => "inert",
+            Kind::Unresolved => "unresolved",
+        }
+    }
+
     fn label(self) -> &'static str {
         match self {
             Kind::Positional => "POSITIONAL",
@@ -77,6 +88,11 @@ pub struct FnClass {
     semantic_closes: Vec<String>,
     fn_level_delimfail: bool,
     notes: Vec<String>,
+    /// `.desc` rows of the Geometric / DelimFailure exits (a state `|eof`
+    /// reports its state's row, the function `|eof` the function's) — what a
+    /// **Mixed** verdict points at.
+    pub geometric_rows: Vec<usize>,
+    pub delim_failure_rows: Vec<usize>,
 }
 
 fn has_return(cmds: &[Command]) -> bool {
@@ -198,6 +214,8 @@ fn classify_fn_direct(func: &Function) -> FnClass {
     let mut delim_failures = vec![];
     let mut semantic_closes = vec![];
     let mut fn_level_delimfail = false;
+    let mut geometric_rows = vec![];
+    let mut delim_failure_rows = vec![];
 
     // function-level eof handler
     if let Some(h) = &func.eof_handler {
@@ -206,6 +224,7 @@ fn classify_fn_direct(func: &Function) -> FnClass {
                 Some((sev, code)) if sev == "warning" && is_closer_failure(&code) => {
                     fn_level_delimfail = true;
                     delim_failures.push(format!("fn-eof warning({code})"));
+                    delim_failure_rows.push(func.lineno);
                 }
                 Some((sev, code)) if sev == "error" => {
                     semantic_closes.push(format!("fn-eof error({code})"))
@@ -220,7 +239,8 @@ fn classify_fn_direct(func: &Function) -> FnClass {
             if has_return(h) {
                 match anomaly(h) {
                     Some((sev, code)) if sev == "warning" && is_closer_failure(&code) => {
-                        delim_failures.push(format!("eof warning({code})@{}", st.name))
+                        delim_failures.push(format!("eof warning({code})@{}", st.name));
+                        delim_failure_rows.push(st.lineno);
                     }
                     Some((sev, code)) if sev == "error" => {
                         semantic_closes.push(format!("eof error({code})@{}", st.name))
@@ -236,9 +256,15 @@ fn classify_fn_direct(func: &Function) -> FnClass {
             let detail = format!("{}@{} [{}]", trig(case), st.name, case.lineno);
             match tag_edge(case) {
                 Tag::Closer => closers.push(detail),
-                Tag::Geometric => geometrics.push(detail),
+                Tag::Geometric => {
+                    geometrics.push(detail);
+                    geometric_rows.push(case.lineno);
+                }
                 Tag::Delegation => delegations.extend(callees(&case.commands)),
-                Tag::DelimFailure => delim_failures.push(detail),
+                Tag::DelimFailure => {
+                    delim_failures.push(detail);
+                    delim_failure_rows.push(case.lineno);
+                }
                 Tag::SemanticClose => semantic_closes.push(detail),
             }
         }
@@ -250,7 +276,10 @@ fn classify_fn_direct(func: &Function) -> FnClass {
     let has_closer = !closers.is_empty();
     let has_delimfail = !delim_failures.is_empty();
 
-    let kind = if fn_level_delimfail {
+    // `|unclosed Name` is a declaration, not an inference: delimited, whatever
+    // the exits look like. (At this point only declared codes are set; the
+    // build post-pass derives the rest from this classification.)
+    let kind = if fn_level_delimfail || func.delimited_code.is_some() {
         Kind::Delimited
     } else if has_delimfail && has_geometric {
         Kind::Mixed
@@ -275,6 +304,8 @@ fn classify_fn_direct(func: &Function) -> FnClass {
         semantic_closes,
         fn_level_delimfail,
         notes: vec![],
+        geometric_rows,
+        delim_failure_rows,
     }
 }
 
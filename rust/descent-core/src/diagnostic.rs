@@ -0,0 +1,282 @@
+//! Structured diagnostics shared by every pipeline stage (reader/lexer,
+//! parser, ir_builder, validate): severity, a stable code, a primary span,
+//! secondary labels and help text, plus a rustc-style renderer that prints
+//! the offending `.desc` rows.
+//!
+//! Codes are grouped by the stage that raises them:
+//!   E00xx/W00xx lexer/reader · E01xx parser · E02xx ir_builder · E03xx/W03xx validate
+//!   · E04xx emit (backend selection and options, generation failures)
+//!   · L00xx lint (advisory; `lint::LINTS` maps each code to its name).
+//!
+//! Tokens carry line numbers but no columns, so a span usually names its
+//! line plus a `needle` — the text to underline, located when rendering
+//! (falling back to the whole row). Stages below the lexer don't know the
+//! source file; `lib.rs` stamps it on at the boundary (`in_file`).
+
+use std::fmt::{self, Write as _};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A location in a `.desc` file. `line` and `column` are 1-based; `column`
+/// and `len` count chars.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Span {
+    pub file: String,
+    pub line: usize,
+    pub column: Option<usize>,
+    pub len: Option<usize>,
+    /// Text to underline when `column` is unknown.
+    pub needle: Option<String>,
+}
+
+impl Span {
+    pub fn line(line: usize) -> Self {
+        Span { line, ..Default::default() }
+    }
+
+    pub fn column(mut self, column: usize, len: usize) -> Self {
+        self.column = Some(column);
+        self.len = Some(len);
+        self
+    }
+
+    pub fn find(mut self, needle: impl Into<String>) -> Self {
+        let n = needle.into();
+        if !n.is_empty() {
+            self.needle = Some(n);
+        }
+        self
+    }
+
+    /// (column, len) on `text`, the span's source line: explicit column,
+    /// else the needle's first occurrence, else the trimmed row.
+    fn resolve(&self, text: &str) -> (usize, usize) {
+        if let Some(c) = self.column {
+            return (c, self.len.unwrap_or(1).max(1));
+        }
+        if let Some(n) = &self.needle {
+            if let Some(b) = text.find(n.as_str()) {
+                return (text[..b].chars().count() + 1, n.chars().count().max(1));
+            }
+        }
+        let indent = text.chars().take_while(|c| c.is_whitespace()).count();
+        (indent + 1, text.trim().chars().count().max(1))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// The primary span is boxed to keep `Result<_, Diagnostic>` small on the
+/// happy path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Option<Box<Span>>,
+    pub labels: Vec<Label>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            span: None,
+            labels: Vec::new(),
+            help: None,
+        }
+    }
+
+    pub fn warning(code: &'static str, message: impl Into<String>) -> Self {
+        Diagnostic { severity: Severity::Warning, ..Diagnostic::error(code, message) }
+    }
+
+    pub fn at(mut self, span: Span) -> Self {
+        self.span = Some(Box::new(span));
+        self
+    }
+
+    pub fn label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label { span, message: message.into() });
+        self
+    }
+
+    pub fn help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.span.as_ref().map(|s| s.line)
+    }
+
+    /// Fill in `file` on every span that doesn't have one yet.
+    pub fn in_file(mut self, file: &str) -> Self {
+        let spans = self.span.iter_mut().map(|s| &mut **s).chain(self.labels.iter_mut().map(|l| &mut l.span));
+        for s in spans.filter(|s| s.file.is_empty()) {
+            s.file = file.to_string();
+        }
+        self
+    }
+
+    /// rustc-style rendering against the `.desc` text the spans point into:
+    ///
+    /// ```text
+    /// error[E0304]: call to undefined function '/nope'
+    ///  --> t.desc:7:19
+    ///   |
+    /// 7 |     |c['a']     | /nope      |>>
+    ///   |                   ^^^^^ not a function of this grammar
+    ///   = help: ...
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let lines: Vec<&str> = source.lines().collect();
+        let mut out = String::new();
+        let _ = writeln!(out, "{}[{}]: {}", self.severity.as_str(), self.code, self.message);
+
+        // Primary first, then labels in line order.
+        let mut marks: Vec<(&Span, char, &str)> = Vec::new();
+        if let Some(s) = &self.span {
+            marks.push((&**s, '^', ""));
+        }
+        let mut labels: Vec<&Label> = self.labels.iter().collect();
+        labels.sort_by_key(|l| l.span.line);
+        marks.extend(labels.iter().map(|l| (&l.span, '-', l.message.as_str())));
+
+        let width = marks.iter().map(|(s, ..)| s.line.to_string().len()).max().unwrap_or(1);
+        let pad = " ".repeat(width);
+        if let Some(s) = &self.span {
+            let text = lines.get(s.line.wrapping_sub(1)).copied().unwrap_or("");
+            let (col, _) = s.resolve(text);
+            let _ = writeln!(out, "{pad}--> {}:{}:{}", s.file, s.line, col);
+        }
+        if !marks.is_empty() {
+            let _ = writeln!(out, "{pad} |");
+        }
+        let mut prev_file = self.span.as_ref().map(|s| s.file.as_str());
+        for (span, ch, msg) in &marks {
+            if prev_file != Some(span.file.as_str()) {
+                let _ = writeln!(out, "{pad}::: {}:{}", span.file, span.line);
+                prev_file = Some(span.file.as_str());
+            }
+            let Some(text) = lines.get(span.line.wrapping_sub(1)) else {
+                continue;
+            };
+            let text = text.replace('\t', " ");
+            let (col, len) = span.resolve(&text);
+            let _ = writeln!(out, "{:>width$} | {}", span.line, text.trim_end());
+            let under = format!("{}{}", " ".repeat(col - 1), ch.to_string().repeat(len));
+            let line = if msg.is_empty() { under } else { format!("{under} {msg}") };
+            let _ = writeln!(out, "{pad} | {}", line.trim_end());
+        }
+        if let Some(h) = &self.help {
+            let _ = writeln!(out, "{pad} = help: {h}");
+        }
+        out
+    }
+}
+
+/// One-line form: `file:line: error[E0101]: message` (location omitted when
+/// unknown).
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(s) = &self.span {
+            if !s.file.is_empty() {
+                write!(f, "{}:", s.file)?;
+            }
+            write!(f, "{}: ", s.line)?;
+        }
+        write!(f, "{}[{}]: {}", self.severity.as_str(), self.code, self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Every diagnostic a stage produced — what the `lib.rs` pipeline returns.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Diagnostics {
+    pub fn render(&self, source: &str) -> String {
+        self.0.iter().map(|d| d.render(source)).collect::<Vec<_>>().join("\n")
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter().filter(|d| d.is_error())
+    }
+
+    pub fn in_file(self, file: &str) -> Self {
+        Diagnostics(self.0.into_iter().map(|d| d.in_file(file)).collect())
+    }
+}
+
+impl From<Diagnostic> for Diagnostics {
+    fn from(d: Diagnostic) -> Self {
+        Diagnostics(vec![d])
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, d) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{d}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_primary_label_and_help() {
+        let src = "|parser t\n|function[main]\n  |state[:s]\n    |c['a']  | /nope  |>>\n";
+        let d = Diagnostic::error("E0304", "call to undefined function '/nope'")
+            .at(Span::line(4).find("/nope"))
+            .label(Span::line(2), "in this function")
+            .help("define |function[nope]")
+            .in_file("t.desc");
+        assert_eq!(
+            d.render(src),
+            "error[E0304]: call to undefined function '/nope'\n\
+             \x20--> t.desc:4:16\n\
+             \x20 |\n\
+             4 |     |c['a']  | /nope  |>>\n\
+             \x20 |                ^^^^^\n\
+             2 | |function[main]\n\
+             \x20 | --------------- in this function\n\
+             \x20 = help: define |function[nope]\n"
+        );
+        assert_eq!(d.to_string(), "t.desc:4: error[E0304]: call to undefined function '/nope'");
+    }
+}
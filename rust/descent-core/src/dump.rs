@@ -33,6 +33,32 @@ pub fn machine_to_json(m: &Machine) -> Value {
                 .collect(),
         );
     }
+    // Likewise rs-only: templates, terminator sets and the states' `|use` /
+    // `|ends` rows.
+    if !m.templates.is_empty() {
+        obj["templates"] = Value::Array(
+            m.templates
+                .iter()
+                .map(|t| {
+                    json!({
+                        "name": t.name,
+                        "params": t.params,
+                        "cases": t.cases.iter().map(case_to_json).collect::<Vec<_>>(),
+                        "eof_handler": t.eof_handler.as_ref().map(eof_to_json),
+                        "lineno": t.lineno,
+                    })
+                })
+                .collect(),
+        );
+    }
+    if !m.terminators.is_empty() {
+        obj["terminators"] = Value::Array(
+            m.terminators
+                .iter()
+                .map(|t| json!({ "name": t.name, "members": t.members, "lineno": t.lineno }))
+                .collect(),
+        );
+    }
     obj
 }
 
@@ -53,12 +79,32 @@ fn function_to_json(f: &Function) -> Value {
 }
 
 fn state_to_json(s: &State) -> Value {
-    json!({
+    let mut obj = json!({
         "name": s.name,
         "cases": s.cases.iter().map(case_to_json).collect::<Vec<_>>(),
         "eof_handler": s.eof_handler.as_ref().map(eof_to_json),
         "lineno": s.lineno,
-    })
+    });
+    if !s.entry_actions.is_empty() {
+        obj["entry_actions"] = s.entry_actions.iter().map(command_to_json).collect();
+    }
+    if !s.ends.is_empty() {
+        obj["ends"] = Value::Array(
+            s.ends
+                .iter()
+                .map(|e| json!({ "set": e.set, "type": e.event_type, "lineno": e.lineno }))
+                .collect(),
+        );
+    }
+    if !s.uses.is_empty() {
+        obj["uses"] = Value::Array(
+            s.uses
+                .iter()
+                .map(|u| json!({ "name": u.name, "args": u.args, "at": u.at, "lineno": u.lineno }))
+                .collect(),
+        );
+    }
+    obj
 }
 
 fn case_to_json(c: &Case) -> Value {
@@ -0,0 +1,359 @@
+//! Backend trait and registry: every target behind one interface.
+//!
+//! A `Backend` has a name (what `--backend` selects), an options schema,
+//! and `generate(&ParserIR, &BackendOptions) -> Result<Vec<OutputFile>,
+//! Diagnostic>`. Options arrive as a string map checked against the schema
+//! (`BackendOptions::from_args`), so the CLI needs no per-target parsing;
+//! each built-in converts the map to its own typed options (`Options`,
+//! `PdOptions`, `COptions`) and calls its module's `generate`, which stays
+//! the typed entry point for library use.
+//!
+//! `Registry::builtin()` holds rust, pushdown and c. Out-of-tree emitters
+//! implement `Backend` and `register` into a registry of their own driver.
+
+use super::{c, rust, rust_pushdown};
+use crate::diagnostic::Diagnostic;
+use crate::ir::ParserIR;
+use std::collections::BTreeMap;
+
+/// One generated file: a path relative to the output directory, and its
+/// contents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputFile {
+    pub path: String,
+    pub contents: String,
+}
+
+impl OutputFile {
+    pub fn new(path: impl Into<String>, contents: impl Into<String>) -> Self {
+        OutputFile { path: path.into(), contents: contents.into() }
+    }
+}
+
+/// One entry of a backend's options schema. `--<name>` on the command
+/// line; a flag takes no value, anything else takes the next argument.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OptionSpec {
+    pub name: &'static str,
+    /// Placeholder for the value in help text (`"<rust::path>"`); `None`
+    /// for a flag.
+    pub value: Option<&'static str>,
+    pub help: &'static str,
+}
+
+impl OptionSpec {
+    pub const fn flag(name: &'static str, help: &'static str) -> Self {
+        OptionSpec { name, value: None, help }
+    }
+
+    pub const fn value(name: &'static str, placeholder: &'static str, help: &'static str) -> Self {
+        OptionSpec { name, value: Some(placeholder), help }
+    }
+
+    pub fn is_flag(&self) -> bool {
+        self.value.is_none()
+    }
+}
+
+/// Option values by name: flags are present or not, valued options carry
+/// their string. Unset options take the backend's default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BackendOptions {
+    values: BTreeMap<String, String>,
+}
+
+impl BackendOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.values.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// `--name [value]` arguments against `schema`. Arguments that aren't
+    /// `--` options are skipped (the caller's positionals); an option the
+    /// schema doesn't list is E0402, a valued one at the end E0403.
+    pub fn from_args(backend: &str, schema: &[OptionSpec], args: &[String]) -> Result<Self, Diagnostic> {
+        let mut opts = BackendOptions::new();
+        let mut it = args.iter();
+        while let Some(arg) = it.next() {
+            let Some(name) = arg.strip_prefix("--") else { continue };
+            let Some(spec) = schema.iter().find(|s| s.name == name) else {
+                let known: Vec<String> = schema.iter().map(|s| format!("--{}", s.name)).collect();
+                let help = if known.is_empty() {
+                    format!("backend '{backend}' takes no options")
+                } else {
+                    format!("backend '{backend}' takes {}", known.join(", "))
+                };
+                return Err(Diagnostic::error("E0402", format!("unknown option '{arg}' for backend '{backend}'")).help(help));
+            };
+            if spec.is_flag() {
+                opts = opts.set(name, "true");
+            } else {
+                let Some(v) = it.next() else {
+                    return Err(Diagnostic::error("E0403", format!("option '{arg}' needs a value {}", spec.value.unwrap_or(""))));
+                };
+                opts = opts.set(name, v.clone());
+            }
+        }
+        Ok(opts)
+    }
+}
+
+/// A code generation target.
+pub trait Backend: Send + Sync {
+    /// What `--backend` selects.
+    fn name(&self) -> &str;
+
+    /// One line for `descent-rs backends`.
+    fn description(&self) -> &str;
+
+    /// The options `generate` reads.
+    fn options(&self) -> &[OptionSpec] {
+        &[]
+    }
+
+    fn generate(&self, ir: &ParserIR, opts: &BackendOptions) -> Result<Vec<OutputFile>, Diagnostic>;
+}
+
+/// Backends by name, in registration order.
+pub struct Registry {
+    backends: Vec<Box<dyn Backend>>,
+}
+
+impl Registry {
+    /// An empty registry (also `Registry::default()`); `builtin` for the
+    /// in-tree targets.
+    pub fn new() -> Self {
+        Registry { backends: Vec::new() }
+    }
+
+    /// The in-tree targets: rust (the default), pushdown, c.
+    pub fn builtin() -> Self {
+        let mut r = Registry::new();
+        r.register(Box::new(RustBackend));
+        r.register(Box::new(PushdownBackend));
+        r.register(Box::new(CBackend));
+        r
+    }
+
+    /// Add a backend; one with the same name is replaced in place.
+    pub fn register(&mut self, backend: Box<dyn Backend>) {
+        match self.backends.iter().position(|b| b.name() == backend.name()) {
+            Some(i) => self.backends[i] = backend,
+            None => self.backends.push(backend),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Backend> {
+        self.backends.iter().find(|b| b.name() == name).map(|b| &**b)
+    }
+
+    /// `get`, or E0401 naming the registered backends.
+    pub fn lookup(&self, name: &str) -> Result<&dyn Backend, Diagnostic> {
+        self.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.iter().map(|b| b.name()).collect();
+            Diagnostic::error("E0401", format!("unknown backend '{name}'"))
+                .help(format!("registered backends: {}", names.join(", ")))
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Backend> {
+        self.backends.iter().map(|b| &**b)
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry::new()
+    }
+}
+
+// ============================================================================
+// Built-in backends
+// ============================================================================
+
+/// `emit::rust`: the recursive parser (with `--streaming`, its nested
+/// pushdown module and `StreamingParser`).
+struct RustBackend;
+
+impl Backend for RustBackend {
+    fn name(&self) -> &str {
+        "rust"
+    }
+
+    fn description(&self) -> &str {
+        "recursive descent Rust parser, one .rs file (the default)"
+    }
+
+    fn options(&self) -> &[OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[
+            OptionSpec::flag("trace", "eprintln! a line per case taken"),
+            OptionSpec::flag("streaming", "also emit StreamingParser and events(), over a nested pushdown module"),
+            OptionSpec::flag("cancellable", "callbacks return ControlFlow<()>; Break stops the parse"),
+        ];
+        OPTIONS
+    }
+
+    fn generate(&self, ir: &ParserIR, opts: &BackendOptions) -> Result<Vec<OutputFile>, Diagnostic> {
+        let o = rust::Options {
+            trace: opts.flag("trace"),
+            streaming: opts.flag("streaming"),
+            cancellable: opts.flag("cancellable"),
+        };
+        rust::generate(ir, &o).map(|code| vec![OutputFile::new("parser.rs", code)])
+    }
+}
+
+/// `emit::rust_pushdown`: the explicit-stack, resumable parser.
+struct PushdownBackend;
+
+impl Backend for PushdownBackend {
+    fn name(&self) -> &str {
+        "pushdown"
+    }
+
+    fn description(&self) -> &str {
+        "explicit-stack resumable Rust parser (PushdownParser), one .rs file"
+    }
+
+    fn options(&self) -> &[OptionSpec] {
+        const OPTIONS: &[OptionSpec] = &[
+            OptionSpec::value("event-path", "<rust::path>", "module owning the shared event types (default crate::parser)"),
+            OptionSpec::flag("trace", "eprintln! a line per transition"),
+            OptionSpec::flag("async", "also emit EventStream, the async adapter"),
+            OptionSpec::flag("cancellable", "callbacks return ControlFlow<()>; Break stops the parser"),
+        ];
+        OPTIONS
+    }
+
+    fn generate(&self, ir: &ParserIR, opts: &BackendOptions) -> Result<Vec<OutputFile>, Diagnostic> {
+        let mut o = rust_pushdown::PdOptions {
+            trace: opts.flag("trace"),
+            async_adapter: opts.flag("async"),
+            cancellable: opts.flag("cancellable"),
+            ..Default::default()
+        };
+        if let Some(path) = opts.value("event-path") {
+            o.event_path = path.to_string();
+        }
+        Ok(vec![OutputFile::new("pushdown.rs", rust_pushdown::generate(ir, &o)?)])
+    }
+}
+
+/// `emit::c`: a `.h` + `.c` pair.
+struct CBackend;
+
+impl Backend for CBackend {
+    fn name(&self) -> &str {
+        "c"
+    }
+
+    fn description(&self) -> &str {
+        "callback-based C99 parser, a .h + .c pair"
+    }
+
+    fn options(&self) -> &[OptionSpec] {
+        const OPTIONS: &[OptionSpec] =
+            &[OptionSpec::value("prefix", "<name>", "prefix for public names and file names (default parser)")];
+        OPTIONS
+    }
+
+    fn generate(&self, ir: &ParserIR, opts: &BackendOptions) -> Result<Vec<OutputFile>, Diagnostic> {
+        let mut o = c::COptions::default();
+        if let Some(prefix) = opts.value("prefix") {
+            if !crate::lexer::re(r"^[A-Za-z_][A-Za-z0-9_]*$").is_match(prefix) {
+                return Err(Diagnostic::error("E0405", format!("C prefix '{prefix}' is not a C identifier")));
+            }
+            o.prefix = prefix.to_string();
+        }
+        Ok(c::generate(ir, &o)?.into_iter().map(|(path, text)| OutputFile::new(path, text)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn options_are_checked_against_the_schema() {
+        let reg = Registry::builtin();
+        let pd = reg.lookup("pushdown").unwrap();
+        let opts = BackendOptions::from_args("pushdown", pd.options(), &args("g.desc --trace --event-path super")).unwrap();
+        assert!(opts.flag("trace") && !opts.flag("async"));
+        assert_eq!(opts.value("event-path"), Some("super"));
+
+        let err = BackendOptions::from_args("rust", reg.lookup("rust").unwrap().options(), &args("--async")).unwrap_err();
+        assert_eq!(err.code, "E0402");
+        let err = BackendOptions::from_args("c", reg.lookup("c").unwrap().options(), &args("--prefix")).unwrap_err();
+        assert_eq!(err.code, "E0403");
+        assert_eq!(reg.lookup("cobol").err().map(|e| e.code), Some("E0401"));
+    }
+
+    #[test]
+    fn registry_generates_and_accepts_out_of_tree_backends() {
+        struct Names;
+        impl Backend for Names {
+            fn name(&self) -> &str {
+                "names"
+            }
+            fn description(&self) -> &str {
+                "function names, one per line"
+            }
+            fn generate(&self, ir: &ParserIR, _: &BackendOptions) -> Result<Vec<OutputFile>, Diagnostic> {
+                let names: Vec<&str> = ir.functions.iter().map(|f| f.name.as_str()).collect();
+                Ok(vec![OutputFile::new("names.txt", names.join("\n"))])
+            }
+        }
+        let src = "|parser tiny\n|type[Text] CONTENT\n|entry-point /doc\n|function[doc:Text]\n  |state[:main]\n    |c['\\n'] |return\n    |default | -> |>>\n";
+        let ir = crate::build_ir_with(src, "tiny.desc", crate::Frontend::OracleLexer).unwrap();
+        let mut reg = Registry::builtin();
+        reg.register(Box::new(Names));
+        let names: Vec<&str> = reg.iter().map(|b| b.name()).collect();
+        assert_eq!(names, ["rust", "pushdown", "c", "names"]);
+        let files = reg.lookup("names").unwrap().generate(&ir, &BackendOptions::new()).unwrap();
+        assert_eq!(files, [OutputFile::new("names.txt", "doc")]);
+        let c = reg.lookup("c").unwrap().generate(&ir, &BackendOptions::new().set("prefix", "tiny")).unwrap();
+        assert_eq!(c.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(), ["tiny.h", "tiny.c"]);
+        assert_eq!(Registry::default().iter().count(), 0);
+    }
+
+    #[test]
+    fn streaming_is_opt_in() {
+        // `+=` of a call result has no pushdown continuation, so only the
+        // streaming build fails.
+        let src = "|parser g\n|entry-point /doc\n|function[doc] | x = 0\n  |state[:main]\n    |c['a'] | x += /num |>>\n    |default | -> |return\n|function[num]\n  |state[:main]\n    |default |return 1\n";
+        let ir = crate::build_ir_with(src, "g.desc", crate::Frontend::OracleLexer).unwrap();
+        let rust = Registry::builtin();
+        let rust = rust.lookup("rust").unwrap();
+        let plain = rust.generate(&ir, &BackendOptions::new()).unwrap();
+        assert!(!plain[0].contents.contains("pub mod pushdown"));
+        assert!(!plain[0].contents.contains("pub struct StreamingParser"));
+        let err = rust.generate(&ir, &BackendOptions::new().set("streaming", "true")).unwrap_err();
+        assert_eq!(err.code, "E0406");
+    }
+
+    #[test]
+    fn unsupported_grammars_are_diagnostics() {
+        // `+=` of a call result has no pushdown continuation.
+        let src = "|parser g\n|entry-point /doc\n|function[doc] | x = 0\n  |state[:main]\n    |c['a'] | x += /num |>>\n    |default | -> |return\n|function[num]\n  |state[:main]\n    |default |return 1\n";
+        let ir = crate::build_ir_with(src, "g.desc", crate::Frontend::OracleLexer).unwrap();
+        let err = Registry::builtin().lookup("pushdown").unwrap().generate(&ir, &BackendOptions::new()).unwrap_err();
+        assert_eq!(err.code, "E0406");
+        assert!(err.message.contains("'x += /num' in function 'doc'"), "{}", err.message);
+    }
+}
@@ -0,0 +1,1505 @@
+//! C target (`--backend c`): a `.h` + `.c` pair from the neutral IR.
+//!
+//! The header declares the event kind enum, the error-code enum, the event
+//! struct, the callback typedef and `<prefix>_parse(const uint8_t *, size_t,
+//! cb, void *)`; the source is a callback-based recursive descent parser
+//! with the same shape and semantics as the recursive Rust backend — one C
+//! function per grammar function, the C call stack as the element stack,
+//! and each grammar state a `goto` label. Events are delivered in the same
+//! order with the same spans, and `<prefix>_format_event` renders them
+//! exactly like the Rust `Event::format_line`, which is what
+//! `rust/tools/diff_c.sh` diffs over the fixture corpus and
+//! `descent-tests/tests/c_backend.rs` over fixture samples.
+//!
+//! Target mapping:
+//! - SCAN: one needle is `memchr`; more go through a 256-bit needle set
+//!   built per call (portable, no SIMD intrinsics), with the same column
+//!   bookkeeping as the Rust `scan_toN`.
+//! - `|keywords`: a perfect hash found at generation time — seeded FNV-1a
+//!   over a power-of-two table with no collisions between the map's
+//!   keywords — so a lookup is one hash, one length check, one `memcmp`.
+//! - Content is borrowed from the input; PREPEND-combined content lives in
+//!   the parser's prepend buffer. Either way it is valid only during the
+//!   callback that receives it.
+//! - Grammar params and locals become `v_<name>`, so they can't collide
+//!   with C keywords or the runtime's own names (no reserved list needed).
+//!
+//! Not carried over: trace output and cancellation (`--trace`,
+//! `--cancellable`), and the pushdown machine — the C parser is one-shot.
+
+use crate::charclass;
+use crate::diagnostic::Diagnostic;
+use crate::ir::*;
+use crate::ir_builder::tokenize_call_args;
+use crate::lexer::re;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+use super::rust::engine::pascalcase;
+
+/// Options for C generation.
+#[derive(Debug, Clone)]
+pub struct COptions {
+    /// Prefix for every public name (`<prefix>_parse`, `<prefix>_event`,
+    /// `<PREFIX>_EVENT_*`) and for the file names (`<prefix>.h` / `.c`).
+    pub prefix: String,
+}
+
+impl Default for COptions {
+    fn default() -> Self {
+        COptions { prefix: "parser".to_string() }
+    }
+}
+
+/// The generated pair as (file name, contents): the header, then the
+/// source. E0406 for a construct the C parser cannot express.
+pub fn generate(ir: &ParserIR, opts: &COptions) -> Result<Vec<(String, String)>, Diagnostic> {
+    let mut g = Gen::new(ir, opts);
+    let header = g.header();
+    let source = g.source();
+    if let Some(d) = g.unsupported {
+        return Err(d);
+    }
+    Ok(vec![(format!("{}.h", opts.prefix), header), (format!("{}.c", opts.prefix), source)])
+}
+
+// ============================================================================
+// Names and literals
+// ============================================================================
+
+/// `TextStart` -> `TEXT_START`, `UnclosedBracket` -> `UNCLOSED_BRACKET`.
+fn screaming(s: &str) -> String {
+    let mut out = String::new();
+    let mut prev: Option<char> = None;
+    for c in s.chars() {
+        if c == '-' || c == ' ' {
+            out.push('_');
+        } else {
+            if c.is_ascii_uppercase() && prev.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit()) {
+                out.push('_');
+            }
+            out.push(c.to_ascii_uppercase());
+        }
+        prev = Some(c);
+    }
+    out
+}
+
+/// A DSL char as a C byte expression: a char literal where one reads
+/// cleanly, else hex (a bare `'\xff'` would be a negative `char`).
+fn c_byte(ch: char) -> String {
+    match ch {
+        '\n' => "'\\n'".to_string(),
+        '\t' => "'\\t'".to_string(),
+        '\r' => "'\\r'".to_string(),
+        '\0' => "'\\0'".to_string(),
+        '\\' => "'\\\\'".to_string(),
+        '\'' => "'\\''".to_string(),
+        c if c == ' ' || c.is_ascii_graphic() => format!("'{c}'"),
+        c => format!("0x{:02x}", c as u32),
+    }
+}
+
+fn c_byte_str(c: &str) -> String {
+    c.chars().next().map(c_byte).unwrap_or_else(|| "0".to_string())
+}
+
+/// Body of a C string literal holding exactly these bytes (one per char,
+/// as the Rust backend's byte strings do). Octal escapes: unlike `\x`,
+/// they can't swallow a following hex digit.
+fn c_str(s: &str) -> String {
+    let mut out = String::new();
+    for ch in s.chars() {
+        match ch {
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '?' => out.push_str("\\?"), // no trigraphs
+            c if c == ' ' || c.is_ascii_graphic() => out.push(c),
+            c => {
+                let _ = write!(out, "\\{:03o}", (c as u32) & 0xFF);
+            }
+        }
+    }
+    out
+}
+
+/// `DP_BYTES("...")`: a static byte string.
+fn c_bytes(s: &str) -> String {
+    format!("DP_BYTES(\"{}\")", c_str(s))
+}
+
+/// Byte values of the DSL escape aliases.
+fn c_escape_alias(alias: &str) -> Option<&'static str> {
+    Some(match alias {
+        "<P>" => "'|'",
+        "<R>" => "']'",
+        "<L>" => "'['",
+        "<RB>" => "'}'",
+        "<LB>" => "'{'",
+        "<RP>" => "')'",
+        "<LP>" => "'('",
+        "<BS>" => "'\\\\'",
+        "<SQ>" => "'\\''",
+        "<DQ>" => "'\"'",
+        "<NL>" => "'\\n'",
+        "<WS>" => "' '",
+        "<>" => "0",
+        _ => return None,
+    })
+}
+
+// ============================================================================
+// Expressions
+// ============================================================================
+
+/// Render a DSL expression as C: calls become `parse_<fn>(p, ...)` with
+/// args typed by the callee's params, variables `v_<name>`, COL / LINE /
+/// PREV the runtime's accessors, char literals and escape aliases bytes.
+/// Calls and char literals are rendered first and parked behind word
+/// tokens so the variable pass can't rewrite inside them.
+fn c_expr(dsl: &str, vars: &BTreeSet<String>, ir: &ParserIR) -> String {
+    let mut parked: Vec<String> = Vec::new();
+    let park = |parked: &mut Vec<String>, text: String| {
+        parked.push(text);
+        format!("DPPARK{}KRAPPD", parked.len() - 1)
+    };
+    let s = re(r"/(\w+)(?:\(([^)]*)\))?")
+        .replace_all(dsl, |caps: &regex::Captures| {
+            let name = &caps[1];
+            let callee = ir.functions.iter().find(|f| f.name == name);
+            let args = caps.get(2).map_or("", |m| m.as_str());
+            let call = format!("parse_{name}(p{})", c_call_args(args, vars, callee, ir));
+            park(&mut parked, call)
+        })
+        .into_owned();
+    let s = re(r"'(\\.|.)'")
+        .replace_all(&s, |caps: &regex::Captures| {
+            let lit = match &caps[1] {
+                "\\n" | "\\t" | "\\r" | "\\0" | "\\\\" | "\\'" => format!("'{}'", &caps[1]),
+                one if one.chars().count() == 1 => c_byte_str(one),
+                other => format!("'{other}'"),
+            };
+            park(&mut parked, lit)
+        })
+        .into_owned();
+    let mut s = re(r"(?i):([a-z_]\w*)").replace_all(&s, "v_$1").into_owned();
+    for v in vars {
+        s = re(&format!(r"\b{}\b", regex::escape(v))).replace_all(&s, format!("v_{v}")).into_owned();
+    }
+    let s = re(r"\bCOL\b").replace_all(&s, "dp_col(p)");
+    let s = re(r"\bLINE\b").replace_all(&s, "(int32_t)p->line");
+    let s = re(r"\bPREV\b").replace_all(&s, "dp_prev(p)");
+    let s = re(r"<[A-Z]*>").replace_all(&s, |caps: &regex::Captures| {
+        c_escape_alias(&caps[0]).map(str::to_string).unwrap_or_else(|| caps[0].to_string())
+    });
+    re(r"DPPARK(\d+)KRAPPD")
+        .replace_all(&s, |caps: &regex::Captures| parked[caps[1].parse::<usize>().unwrap()].clone())
+        .into_owned()
+}
+
+/// Call args converted by the CALLEE's param types, as the recursive
+/// backend's `transform_args_for_target` does: bytes params take byte
+/// strings (a numeric arg is the empty sentinel), byte params the first
+/// byte of the parsed class, i32 params the expression. Leading ", ".
+fn c_call_args(args: &str, vars: &BTreeSet<String>, callee: Option<&Function>, ir: &ParserIR) -> String {
+    if args.trim().is_empty() {
+        return String::new();
+    }
+    let mut out = String::new();
+    for (i, raw) in tokenize_call_args(args.trim()).iter().enumerate() {
+        let a = raw.trim();
+        let pty = callee.and_then(|c| c.params.get(i)).and_then(|p| callee.and_then(|c| c.param_type(p)));
+        let numeric = re(r"^-?\d+$").is_match(a);
+        let rendered = match pty {
+            Some(ParamType::Bytes) if numeric => c_bytes(""),
+            Some(ParamType::Byte) if numeric => a.to_string(),
+            Some(ParamType::Bytes) => {
+                let parsed = charclass::parse(a);
+                match parsed.param_ref {
+                    Some(pr) => format!("v_{pr}"),
+                    None => c_bytes(parsed.bytes.as_deref().unwrap_or("")),
+                }
+            }
+            Some(ParamType::Byte) => {
+                let parsed = charclass::parse(a);
+                match parsed.param_ref {
+                    Some(pr) => format!("v_{pr}"),
+                    None => {
+                        let bytes = parsed.bytes.unwrap_or_default();
+                        bytes
+                            .chars()
+                            .next()
+                            .or_else(|| parsed.chars.first().and_then(|s| s.chars().next()))
+                            .map(c_byte)
+                            .unwrap_or_else(|| "0".to_string())
+                    }
+                }
+            }
+            _ => c_expr(a, vars, ir),
+        };
+        let _ = write!(out, ", {rendered}");
+    }
+    out
+}
+
+// ============================================================================
+// Keyword perfect hash
+// ============================================================================
+
+/// Seeded 32-bit FNV-1a; the generated `dp_hash` computes the same.
+fn fnv1a(seed: u32, bytes: &[u8]) -> u32 {
+    let mut h = 2_166_136_261u32 ^ seed;
+    for &b in bytes {
+        h ^= b as u32;
+        h = h.wrapping_mul(16_777_619);
+    }
+    h
+}
+
+/// A DSL keyword as the bytes the parser compares (one byte per char).
+fn keyword_bytes(k: &str) -> Vec<u8> {
+    k.chars().map(|c| c as u32 as u8).collect()
+}
+
+/// `(seed, table size)` with no two keywords in one slot. Starts at twice
+/// the key count (rounded up to a power of two) and grows if a run of
+/// seeds finds nothing.
+fn perfect_hash(keys: &[Vec<u8>]) -> (u32, usize) {
+    let mut size = (keys.len() * 2).max(1).next_power_of_two();
+    loop {
+        for seed in 0..1000u32 {
+            let mut used = vec![false; size];
+            let ok = keys.iter().all(|k| {
+                let slot = fnv1a(seed, k) as usize & (size - 1);
+                !std::mem::replace(&mut used[slot], true)
+            });
+            if ok {
+                return (seed, size);
+            }
+        }
+        size *= 2;
+    }
+}
+
+// ============================================================================
+// Generator
+// ============================================================================
+
+struct Gen<'i> {
+    ir: &'i ParserIR,
+    /// Public prefix, lower (`parser`) and upper (`PARSER`).
+    p: String,
+    up: String,
+    /// Labels the current function jumps to (unreferenced ones are not
+    /// emitted: `-Wunused-label`).
+    gotos: BTreeSet<String>,
+    /// The first construct the C parser cannot express (E0406).
+    unsupported: Option<Diagnostic>,
+}
+
+/// The function being rendered.
+struct FnCtx<'i> {
+    func: &'i Function,
+    kind: &'i str, // "bracket" | "content" | "internal" | ""
+    vars: BTreeSet<String>,
+}
+
+impl<'i> Gen<'i> {
+    fn new(ir: &'i ParserIR, opts: &COptions) -> Self {
+        Gen { ir, p: opts.prefix.clone(), up: opts.prefix.to_uppercase(), gotos: BTreeSet::new(), unsupported: None }
+    }
+
+    fn type_kind(&self, name: &Option<String>) -> &'i str {
+        name.as_deref()
+            .and_then(|n| self.ir.types.iter().find(|t| t.name == n))
+            .map(|t| t.kind.as_str())
+            .unwrap_or("")
+    }
+
+    fn event(&self, name: &str) -> String {
+        format!("{}_EVENT_{}", self.up, screaming(name))
+    }
+
+    fn error_code(&self, code: &str) -> String {
+        format!("{}_ERROR_{}", self.up, screaming(&pascalcase(code)))
+    }
+
+    /// Event kinds in declaration order: (enumerator, `format_line` name).
+    fn event_kinds(&self) -> Vec<(String, String)> {
+        let mut kinds = Vec::new();
+        for t in &self.ir.types {
+            match t.kind.as_str() {
+                "bracket" => {
+                    for suffix in ["Start", "End"] {
+                        let name = format!("{}{suffix}", t.name);
+                        kinds.push((self.event(&name), name));
+                    }
+                }
+                "content" => kinds.push((self.event(&t.name), t.name.clone())),
+                _ => {}
+            }
+        }
+        kinds.push((self.event("Error"), "Error".to_string()));
+        kinds
+    }
+
+    /// Error codes, deduplicated in the recursive backend's order: the
+    /// built-ins, `Unclosed<Type>` per delimited function, then `/error`
+    /// codes. (`LimitExceeded` is the streaming parser's; C has none.)
+    fn error_codes(&self) -> Vec<String> {
+        let mut codes: Vec<String> = vec!["UnexpectedEof".to_string(), "UnexpectedChar".to_string()];
+        for f in &self.ir.functions {
+            if f.expects_char.is_some() {
+                codes.push(format!("Unclosed{}", f.return_type.as_deref().unwrap_or("")));
+            }
+        }
+        codes.extend(self.ir.custom_error_codes.iter().map(|c| pascalcase(c)));
+        let mut seen = BTreeSet::new();
+        codes.retain(|c| seen.insert(c.clone()));
+        codes
+    }
+
+    fn header(&self) -> String {
+        let (p, up) = (&self.p, &self.up);
+        let mut kinds = String::new();
+        for (e, _) in self.event_kinds() {
+            let _ = writeln!(kinds, "    {e},");
+        }
+        let mut codes = String::new();
+        for c in self.error_codes() {
+            let _ = writeln!(codes, "    {up}_ERROR_{},", screaming(&c));
+        }
+        HEADER
+            .replace("__GRAMMAR__", self.ir.name.as_deref().unwrap_or("parser"))
+            .replace("__KINDS__", &kinds)
+            .replace("__CODES__", &codes)
+            .replace("__p__", p)
+            .replace("__P__", up)
+    }
+
+    fn source(&mut self) -> String {
+        let mut out = SOURCE_HEAD
+            .replace("__GRAMMAR__", self.ir.name.as_deref().unwrap_or("parser"))
+            .replace("__p__", &self.p);
+
+        // Saved slots become start/end fields on the parser.
+        let mut slots = BTreeSet::new();
+        for f in &self.ir.functions {
+            collect_saved_slots(&f.entry_actions, &mut slots);
+            collect_saved_slots(f.eof_handler.as_deref().unwrap_or_default(), &mut slots);
+            for s in &f.states {
+                collect_saved_slots(s.eof_handler.as_deref().unwrap_or_default(), &mut slots);
+                for c in &s.cases {
+                    collect_saved_slots(&c.commands, &mut slots);
+                }
+            }
+        }
+        let mut fields = String::new();
+        for slot in &slots {
+            let _ = writeln!(fields, "    size_t saved_{slot}_start, saved_{slot}_end;");
+        }
+        out.push_str(&RUNTIME.replace("__SLOTS__", &fields).replace("__p__", &self.p).replace("__P__", &self.up));
+
+        // Prototypes: keyword fallbacks and calls can go either way.
+        out.push_str("\n/* ========== Generated parse functions ========== */\n\n");
+        for f in &self.ir.functions {
+            let _ = writeln!(out, "static DP_UNUSED {};", self.signature(f));
+        }
+        for kw in &self.ir.keywords {
+            self.render_keywords(&mut out, kw);
+        }
+        for f in &self.ir.functions {
+            let body = self.render_function(f);
+            out.push_str(&body);
+        }
+        self.render_api(&mut out);
+        out
+    }
+
+    fn signature(&self, f: &Function) -> String {
+        let ret = if self.type_kind(&f.return_type) == "internal" { "int32_t" } else { "void" };
+        let mut params = String::from("dp_parser *p");
+        for (name, ty) in &f.param_types {
+            let cty = match ty {
+                ParamType::I32 => "int32_t",
+                ParamType::Byte => "uint8_t",
+                ParamType::Bytes => "dp_bytes",
+            };
+            let _ = write!(params, ", {cty} v_{name}");
+        }
+        format!("{ret} parse_{}({params})", f.name)
+    }
+
+    // ---- |keywords: perfect-hash table + lookup (+ fallback) ----
+    fn render_keywords(&self, out: &mut String, kw: &Keywords) {
+        let mut seen = BTreeSet::new();
+        let entries: Vec<(Vec<u8>, &str, &str)> = kw
+            .mappings
+            .iter()
+            .filter(|m| seen.insert(m.keyword.clone()))
+            .map(|m| (keyword_bytes(&m.keyword), m.keyword.as_str(), m.event_type.as_str()))
+            .collect();
+        let keys: Vec<Vec<u8>> = entries.iter().map(|e| e.0.clone()).collect();
+        let (seed, size) = perfect_hash(&keys);
+        let name = &kw.name;
+        let _ = writeln!(
+            out,
+            "\n/* |keywords[{name}]: perfect hash (FNV-1a, seed {seed}) over {size} slots. */\nstatic const dp_keyword dp_kw_{name}_table[{size}] = {{"
+        );
+        let mut slots: Vec<(usize, String)> = entries
+            .iter()
+            .map(|(bytes, text, ev)| {
+                let slot = fnv1a(seed, bytes) as usize & (size - 1);
+                (slot, format!("    [{slot}] = {{ \"{}\", {}, {} }},", c_str(text), bytes.len(), self.event(ev)))
+            })
+            .collect();
+        slots.sort();
+        for (_, line) in slots {
+            let _ = writeln!(out, "{line}");
+        }
+        let _ = writeln!(
+            out,
+            "}};\n\n/* Look up the capture in {name}: emit and return 1 on a match, else 0. */\nstatic DP_UNUSED int dp_kw_{name}(dp_parser *p)\n{{\n    return dp_keyword_lookup(p, dp_kw_{name}_table, {seed}u, {mask}u);\n}}",
+            mask = size - 1
+        );
+        let _ = writeln!(out, "\nstatic DP_UNUSED void dp_kw_{name}_or_fallback(dp_parser *p)\n{{");
+        match &kw.fallback_func {
+            Some(fb) => {
+                let callee = self.ir.functions.iter().find(|f| &f.name == fb);
+                let args = c_call_args(kw.fallback_args.as_deref().unwrap_or(""), &BTreeSet::new(), callee, self.ir);
+                let _ = writeln!(out, "    if (!dp_kw_{name}(p)) {{\n        parse_{fb}(p{args});\n    }}");
+            }
+            None => {
+                let _ = writeln!(out, "    (void)dp_kw_{name}(p); /* no fallback: a miss is a no-op */");
+            }
+        }
+        let _ = writeln!(out, "}}");
+    }
+
+    // ---- one grammar function ----
+    fn render_function(&mut self, func: &'i Function) -> String {
+        let mut vars: BTreeSet<String> = func.params.iter().cloned().collect();
+        vars.extend(func.locals.iter().cloned());
+        let cx = FnCtx { func, kind: self.type_kind(&func.return_type), vars };
+        self.gotos.clear();
+
+        let mut b = String::new();
+        let ret = func.return_type.as_deref().map(|t| format!(" -> {t}")).unwrap_or_default();
+        let _ = writeln!(b, "\n/* Parse {}{ret} */\nstatic DP_UNUSED {}\n{{", func.name, self.signature(func));
+        for name in &func.params {
+            let _ = writeln!(b, "    (void)v_{name};");
+        }
+        match cx.kind {
+            "bracket" => {
+                let t = func.return_type.as_deref().unwrap();
+                let _ = writeln!(b, "    dp_emit_mark_span(p, {}, p->pos, p->pos);", self.event(&format!("{t}Start")));
+            }
+            "content" => {
+                let _ = writeln!(b, "    dp_mark(p);");
+            }
+            _ => {}
+        }
+        // Locals take their initializer from the entry-action assignment
+        // (last one wins), which then doesn't run again.
+        let mut inits: Vec<(&str, &str)> = Vec::new();
+        for cmd in &func.entry_actions {
+            if cmd.ctype == "assign" {
+                if let (Some(v), Some(e)) = (cmd.arg_str("var"), cmd.arg_str("expr")) {
+                    inits.retain(|(x, _)| *x != v);
+                    inits.push((v, e));
+                }
+            }
+        }
+        for local in &func.locals {
+            let init = inits
+                .iter()
+                .find(|(v, _)| v == local)
+                .map(|(_, e)| c_expr(e, &cx.vars, self.ir))
+                .unwrap_or_else(|| "0".to_string());
+            let _ = writeln!(b, "    int32_t v_{local} = {init};\n    (void)v_{local};");
+        }
+        for cmd in &func.entry_actions {
+            let is_init = cmd.ctype == "assign" && cmd.arg_str("var").is_some_and(|v| inits.iter().any(|(x, _)| *x == v));
+            if is_init || (cmd.ctype == "mark" && cx.kind == "content") {
+                continue;
+            }
+            self.render_cmd(&mut b, cmd, &cx, None, 4);
+        }
+
+        if func.states.is_empty() {
+            match cx.kind {
+                "content" => {
+                    let t = func.return_type.as_deref().unwrap();
+                    let _ = writeln!(b, "    dp_emit_capture(p, {});", self.event(t));
+                }
+                "bracket" => {
+                    let t = func.return_type.as_deref().unwrap();
+                    let _ = writeln!(b, "    dp_emit_mark_span(p, {}, p->pos, p->pos);", self.event(&format!("{t}End")));
+                }
+                "internal" => {
+                    let _ = writeln!(b, "    return 0;");
+                }
+                _ => {}
+            }
+            b.push_str("}\n");
+            return b;
+        }
+
+        let single = func.states.len() == 1;
+        let mut bodies = Vec::new();
+        for state in &func.states {
+            bodies.push((state.name.clone(), self.render_state(state, &cx, single)));
+        }
+        for (i, (name, body)) in bodies.iter().enumerate() {
+            if self.gotos.contains(name) {
+                let _ = writeln!(b, "st_{name}:");
+            } else if i > 0 {
+                // Unreachable by construction (every state body ends in a
+                // jump), kept so the state still reads in the source.
+                let _ = writeln!(b, "    /* state {name}: never entered */");
+            }
+            b.push_str(body);
+        }
+        b.push_str("}\n");
+        b
+    }
+
+    fn jump(&mut self, b: &mut String, state: &str, ind: usize) {
+        self.gotos.insert(state.to_string());
+        let _ = writeln!(b, "{:ind$}goto st_{state};", "");
+    }
+
+    fn ret(&self, cx: &FnCtx<'_>) -> &'static str {
+        if cx.kind == "internal" {
+            "return 0;"
+        } else {
+            "return;"
+        }
+    }
+
+    // ---- a grammar state: EOF handling + case dispatch, then loop ----
+    fn render_state(&mut self, state: &State, cx: &FnCtx<'i>, single: bool) -> String {
+        let mut b = String::new();
+        let name = state.name.as_str();
+        let _ = writeln!(b, "    {{");
+        if state.scannable() {
+            let mut needles: Vec<String> = state.scan_chars.iter().flatten().map(|c| c_byte_str(c)).collect();
+            needles.extend(state.scan_params.iter().map(|p| format!("v_{p}")));
+            let _ = writeln!(
+                b,
+                "        int c = dp_scan(p, (const uint8_t[]){{ {} }}, {});",
+                needles.join(", "),
+                needles.len()
+            );
+            let mut first = true;
+            for case in state.cases.iter().filter(|c| !c.is_default()) {
+                self.render_arm(&mut b, case, state, cx, &mut first);
+            }
+            if state.newline_injected {
+                let _ = writeln!(b, "        {} (c == '\\n') {{\n            dp_advance(p);\n        }}", if first { "if" } else { "else if" });
+                first = false;
+            }
+            let _ = writeln!(b, "        {} (c < 0) {{", if first { "if" } else { "else if" });
+            self.render_eof(&mut b, state, cx, 12);
+            let _ = writeln!(b, "        }}");
+        } else if !single && state.is_unconditional {
+            let cmds = state.cases[0].commands.clone();
+            self.render_seq(&mut b, &cmds, cx, Some(name), 8);
+        } else {
+            let byte_independent = !state.cases.is_empty()
+                && state.eof_handler.is_none()
+                && state.cases.iter().all(|c| c.chars.is_none() && c.special_class.is_none() && c.param_ref.is_none());
+            if !byte_independent {
+                let _ = writeln!(b, "        if (dp_eof(p)) {{");
+                self.render_eof(&mut b, state, cx, 12);
+                let _ = writeln!(b, "        }}");
+            }
+            if state.cases.len() == 1 && state.cases[0].is_default() {
+                let cmds = state.cases[0].commands.clone();
+                self.render_seq(&mut b, &cmds, cx, Some(name), 8);
+            } else {
+                if !byte_independent {
+                    let _ = writeln!(b, "        int c = dp_peek(p);");
+                }
+                let mut first = true;
+                let mut saw_default = false;
+                for case in &state.cases {
+                    self.render_arm(&mut b, case, state, cx, &mut first);
+                    if case.is_default() {
+                        saw_default = true;
+                        break; // later arms are unreachable, as in a Rust `match`
+                    }
+                }
+                if !state.has_default && !saw_default {
+                    let _ = writeln!(b, "        else {{\n            {}\n        }}", self.ret(cx));
+                }
+            }
+        }
+        let _ = writeln!(b, "    }}");
+        self.jump(&mut b, name, 4);
+        // Arms are written as separate blocks; join the chain K&R-style.
+        b.replace("        }\n        else", "        } else")
+    }
+
+    /// One match arm as an `if` / `else if` / `else` link.
+    fn render_arm(&mut self, b: &mut String, case: &Case, state: &State, cx: &FnCtx<'i>, first: &mut bool) {
+        let test = if case.is_default() {
+            None
+        } else if let Some(cond) = &case.condition {
+            Some(c_expr(cond, &cx.vars, self.ir))
+        } else if let Some(pr) = &case.param_ref {
+            Some(format!("c >= 0 && c == v_{pr}"))
+        } else if let Some(class) = &case.special_class {
+            let extra: String = case.chars.iter().flatten().map(|ch| format!(" || c == {}", c_byte_str(ch))).collect();
+            Some(format!("c >= 0 && (dp_is_{class}((uint8_t)c){extra})"))
+        } else if let Some(chars) = &case.chars {
+            let tests: Vec<String> = chars.iter().map(|ch| format!("c == {}", c_byte_str(ch))).collect();
+            Some(tests.join(" || "))
+        } else {
+            Some("c >= 0".to_string())
+        };
+        let head = match (test, *first) {
+            (Some(t), true) => format!("if ({t}) {{"),
+            (Some(t), false) => format!("else if ({t}) {{"),
+            (None, true) => "{".to_string(),
+            (None, false) => "else {".to_string(),
+        };
+        *first = false;
+        let _ = writeln!(b, "        {head}");
+        let cmds = case.commands.clone();
+        self.render_seq(b, &cmds, cx, Some(&state.name), 12);
+        let _ = writeln!(b, "        }}");
+    }
+
+    /// EOF: explicit `|eof` (state, then function); else EOF ≡ newline (the
+    /// state's returning `\n` arms, or its fall-through `default` for a
+    /// lookahead state); else the type-default with the delimited
+    /// force-unwind — the recursive backend's None arm, verbatim.
+    fn render_eof(&mut self, b: &mut String, state: &State, cx: &FnCtx<'i>, ind: usize) {
+        let handler = state
+            .eof_handler
+            .as_ref()
+            .filter(|h| !h.is_empty())
+            .or(cx.func.eof_handler.as_ref().filter(|h| !h.is_empty()));
+        if let Some(cmds) = handler {
+            let cmds = cmds.clone();
+            self.render_seq(b, &cmds, cx, Some(&state.name), ind);
+            return;
+        }
+        if state.eof_run_newline() || state.eof_run_default() {
+            let newline = state.eof_run_newline();
+            let cmds: Vec<Command> = state
+                .cases
+                .iter()
+                .filter(|c| if newline { c.is_newline_return() } else { c.is_default() })
+                .flat_map(|c| c.commands.clone())
+                .collect();
+            self.render_seq(b, &cmds, cx, Some(&state.name), ind);
+            return;
+        }
+        let t = cx.func.return_type.as_deref().unwrap_or("");
+        if cx.kind == "content" {
+            let _ = writeln!(b, "{:ind$}dp_emit_capture(p, {});", "", self.event(t));
+        }
+        if let Some(code) = cx.func.delimited_code.as_deref() {
+            let _ = writeln!(b, "{:ind$}dp_emit_bytes(p, {}, {});", "", self.event("Warning"), c_bytes(code));
+            if cx.kind == "bracket" {
+                let _ = writeln!(b, "{:ind$}dp_emit_mark_span(p, {}, p->pos, p->pos);", "", self.event(&format!("{t}End")));
+            }
+        } else if cx.func.expects_char.is_some() {
+            let _ = writeln!(b, "{:ind$}dp_error(p, {});", "", self.error_code(&format!("Unclosed{t}")));
+        } else if cx.kind == "bracket" {
+            let _ = writeln!(b, "{:ind$}dp_emit_mark_span(p, {}, p->pos, p->pos);", "", self.event(&format!("{t}End")));
+        }
+        let _ = writeln!(b, "{:ind$}{}", "", self.ret(cx));
+    }
+
+    fn render_seq(&mut self, b: &mut String, cmds: &[Command], cx: &FnCtx<'i>, state: Option<&str>, ind: usize) {
+        for cmd in cmds {
+            self.render_cmd(b, cmd, cx, state, ind);
+        }
+    }
+
+    /// One command — `_command.j2`, in C. `state` is the grammar state the
+    /// command runs in (the target of a bare `|>>`).
+    fn render_cmd(&mut self, b: &mut String, cmd: &Command, cx: &FnCtx<'i>, state: Option<&str>, ind: usize) {
+        let arg = |k: &str| cmd.arg_str(k).unwrap_or("");
+        match cmd.ctype.as_str() {
+            "advance" => {
+                let _ = writeln!(b, "{:ind$}dp_advance(p);", "");
+            }
+            "advance_to" => {
+                let needles: Vec<String> = arg("value").chars().map(c_byte).collect();
+                let _ = writeln!(
+                    b,
+                    "{:ind$}(void)dp_scan(p, (const uint8_t[]){{ {} }}, {});",
+                    "",
+                    needles.join(", "),
+                    needles.len()
+                );
+            }
+            "mark" => {
+                let _ = writeln!(b, "{:ind$}dp_mark(p);", "");
+            }
+            "term" => {
+                let off = cmd.args.get("offset").and_then(|v| v.as_i64()).unwrap_or(0);
+                let _ = writeln!(b, "{:ind$}dp_set_term(p, {off});", "");
+            }
+            "prepend" => {
+                let _ = writeln!(b, "{:ind$}dp_prepend(p, {});", "", c_bytes(arg("literal")));
+            }
+            "prepend_param" => {
+                let _ = writeln!(b, "{:ind$}dp_prepend(p, v_{});", "", arg("param_ref"));
+            }
+            "return" => self.render_return(b, cmd, cx, ind),
+            "transition" => {
+                let target = arg("value").replace(':', "");
+                let target = if target.is_empty() { state.unwrap_or_default().to_string() } else { target };
+                self.jump(b, &target, ind);
+            }
+            "call" => {
+                if cmd.args.get("is_error").and_then(|v| v.as_bool()).unwrap_or(false) {
+                    let code = cmd.arg_str("call_args").filter(|s| !s.is_empty()).unwrap_or("UnexpectedChar");
+                    let _ = writeln!(b, "{:ind$}dp_error(p, {});", "", self.error_code(code));
+                    return;
+                }
+                let name = arg("name");
+                let callee = self.ir.functions.iter().find(|f| f.name == name);
+                let args = c_call_args(arg("call_args"), &cx.vars, callee, self.ir);
+                let discard = if self.type_kind(&callee.and_then(|c| c.return_type.clone())) == "internal" { "(void)" } else { "" };
+                let _ = writeln!(b, "{:ind$}{discard}parse_{name}(p{args});", "");
+            }
+            "assign" | "add_assign" | "sub_assign" => {
+                let op = match cmd.ctype.as_str() {
+                    "add_assign" => "+=",
+                    "sub_assign" => "-=",
+                    _ => "=",
+                };
+                if !arg("var").is_empty() && !arg("expr").is_empty() {
+                    let _ = writeln!(b, "{:ind$}v_{} {op} {};", "", arg("var"), c_expr(arg("expr"), &cx.vars, self.ir));
+                }
+            }
+            "emit" => {
+                let _ = writeln!(b, "{:ind$}dp_emit_capture(p, {});", "", self.event(arg("value")));
+            }
+            "inline_emit_bare" => {
+                let _ = writeln!(b, "{:ind$}dp_emit_bytes(p, {}, {});", "", self.event(arg("type")), c_bytes(""));
+            }
+            "inline_emit_mark" => {
+                let _ = writeln!(b, "{:ind$}dp_emit_capture(p, {});", "", self.event(arg("type")));
+            }
+            "keywords_try" => {
+                let _ = writeln!(b, "{:ind$}v_{} = dp_kw_{}(p);", "", arg("var"), arg("name"));
+            }
+            "save" => {
+                let slot = arg("slot");
+                let _ = writeln!(
+                    b,
+                    "{:ind$}p->saved_{slot}_start = p->mark_pos;\n{:ind$}p->saved_{slot}_end = p->term_pos != DP_NO_TERM ? p->term_pos : p->pos;",
+                    "", ""
+                );
+            }
+            "inline_emit_saved" => {
+                let slot = arg("slot");
+                let _ = writeln!(
+                    b,
+                    "{:ind$}dp_emit_range(p, {}, p->saved_{slot}_start, p->saved_{slot}_end);",
+                    "",
+                    self.event(arg("type"))
+                );
+            }
+            "inline_emit_param" => {
+                let _ = writeln!(b, "{:ind$}dp_emit_bytes(p, {}, v_{});", "", self.event(arg("type")), arg("param_ref"));
+            }
+            "inline_emit_literal" => {
+                // Verbatim, quotes stripped; escapes mean what they mean in
+                // the Rust backend's byte string.
+                let raw = arg("literal");
+                let lit = if raw.starts_with('\'') { raw.trim_matches('\'') } else { raw };
+                let _ = writeln!(b, "{:ind$}dp_emit_bytes(p, {}, DP_BYTES(\"{lit}\"));", "", self.event(arg("type")));
+            }
+            "error" => {
+                let code = if arg("value").is_empty() { "unexpected_char" } else { arg("value") };
+                let _ = writeln!(b, "{:ind$}dp_error(p, {});", "", self.error_code(code));
+            }
+            "keywords_lookup" => {
+                let _ = writeln!(b, "{:ind$}dp_kw_{}_or_fallback(p);", "", arg("name"));
+            }
+            "conditional" => {
+                let clauses = cmd.clauses.clone().unwrap_or_default();
+                for (i, clause) in clauses.iter().enumerate() {
+                    let head = match (&clause.condition, i) {
+                        (Some(c), 0) => format!("if ({}) {{", c_expr(c, &cx.vars, self.ir)),
+                        (Some(c), _) => format!("}} else if ({}) {{", c_expr(c, &cx.vars, self.ir)),
+                        (None, _) => "} else {".to_string(),
+                    };
+                    let _ = writeln!(b, "{:ind$}{head}", "");
+                    self.render_seq(b, &clause.commands, cx, state, ind + 4);
+                }
+                let _ = writeln!(b, "{:ind$}}}", "");
+            }
+            other => {
+                let message = format!("C backend: command '{other}' in function '{}' is not supported", cx.func.name);
+                self.unsupported.get_or_insert_with(|| Diagnostic::error("E0406", message));
+            }
+        }
+    }
+
+    fn render_return(&mut self, b: &mut String, cmd: &Command, cx: &FnCtx<'i>, ind: usize) {
+        if let Some(rv) = cmd.arg_str("return_value") {
+            let _ = writeln!(b, "{:ind$}return {};", "", c_expr(rv, &cx.vars, self.ir));
+            return;
+        }
+        let t = cx.func.return_type.as_deref().unwrap_or("");
+        if let Some(et) = cmd.arg_str("emit_type") {
+            match cmd.arg_str("emit_mode") {
+                Some("mark") => {
+                    let _ = writeln!(b, "{:ind$}dp_emit_capture(p, {});", "", self.event(et));
+                }
+                Some("literal") => {
+                    let lit = cmd.arg_str("literal").unwrap_or("");
+                    let _ = writeln!(b, "{:ind$}dp_emit_bytes(p, {}, DP_BYTES(\"{lit}\"));", "", self.event(et));
+                }
+                _ => {
+                    let _ = writeln!(b, "{:ind$}dp_emit_bytes(p, {}, {});", "", self.event(et), c_bytes(""));
+                }
+            }
+        } else {
+            let suppress = cmd.args.get("suppress_auto_emit").and_then(|v| v.as_bool()).unwrap_or(false);
+            match cx.kind {
+                "content" if !suppress => {
+                    let _ = writeln!(b, "{:ind$}dp_emit_capture(p, {});", "", self.event(t));
+                }
+                "bracket" => {
+                    let _ = writeln!(b, "{:ind$}dp_emit_mark_span(p, {}, p->pos, p->pos);", "", self.event(&format!("{t}End")));
+                }
+                _ => {}
+            }
+        }
+        let _ = writeln!(b, "{:ind$}{}", "", self.ret(cx));
+    }
+
+    // ---- public API: names, formatting, the entry point ----
+    fn render_api(&self, out: &mut String) {
+        let (p, up) = (&self.p, &self.up);
+        let _ = writeln!(out, "\n/* ========== Public API ========== */\n\nconst char *{p}_event_name({p}_event_kind kind)\n{{\n    switch (kind) {{");
+        for (e, name) in self.event_kinds() {
+            let _ = writeln!(out, "    case {e}: return \"{name}\";");
+        }
+        let _ = writeln!(out, "    }}\n    return \"?\";\n}}\n\nconst char *{p}_error_name({p}_error_code code)\n{{\n    switch (code) {{");
+        for c in self.error_codes() {
+            let _ = writeln!(out, "    case {up}_ERROR_{}: return \"{c}\";", screaming(&c));
+        }
+        let _ = writeln!(out, "    }}\n    return \"?\";\n}}");
+        let ep = self.ir.entry_point.as_deref().unwrap_or_default().replace('/', "");
+        let discard = self
+            .ir
+            .functions
+            .iter()
+            .find(|f| f.name == ep)
+            .filter(|f| self.type_kind(&f.return_type) == "internal")
+            .map_or("", |_| "(void)");
+        out.push_str(
+            &API.replace("__ENTRY__", &format!("{discard}parse_{ep}(&p);"))
+                .replace("__p__", p)
+                .replace("__P__", up),
+        );
+    }
+}
+
+/// SAVE / USE_SAVED slot names, recursing into conditional clauses.
+fn collect_saved_slots(cmds: &[Command], slots: &mut BTreeSet<String>) {
+    for cmd in cmds {
+        if cmd.ctype == "save" || cmd.ctype == "inline_emit_saved" {
+            if let Some(slot) = cmd.arg_str("slot") {
+                slots.insert(slot.to_string());
+            }
+        }
+        for clause in cmd.clauses.iter().flatten() {
+            collect_saved_slots(&clause.commands, slots);
+        }
+    }
+}
+
+// ============================================================================
+// Static text
+// ============================================================================
+
+const HEADER: &str = r#"/* Generated by descent (`--backend c`) from __GRAMMAR__.desc - DO NOT EDIT
+ *
+ * Callback-based recursive descent parser. `__p___parse` calls `cb` once per
+ * event, in document order. Content points into the input, or into the
+ * parser's own buffer for PREPEND-combined content: either way it is valid
+ * only during the callback that receives it - copy what must outlive it.
+ */
+
+#ifndef __P___H
+#define __P___H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum {
+__KINDS__} __p___event_kind;
+
+typedef enum {
+__CODES__} __p___error_code;
+
+typedef struct {
+    __p___event_kind kind;
+    /* Captured bytes for content events; NULL for Start/End/Error. */
+    const uint8_t *content;
+    size_t content_len;
+    /* Byte span [start, end) in the input. */
+    size_t start;
+    size_t end;
+    /* __P___EVENT_ERROR only. */
+    __p___error_code code;
+} __p___event;
+
+typedef void (*__p___callback)(const __p___event *event, void *user);
+
+/* Parse `input`, calling `cb(event, user)` for each event. Returns 0, or -1
+ * if the prepend buffer could not be allocated (events after the failure
+ * lose their prepended bytes). */
+int __p___parse(const uint8_t *input, size_t len, __p___callback cb, void *user);
+
+/* The event's name as in the Rust backend's `Event` (e.g. "TextStart"). */
+const char *__p___event_name(__p___event_kind kind);
+
+/* The error code's name as in the Rust backend's `ParseErrorCode`. */
+const char *__p___error_name(__p___error_code code);
+
+/* Render `event` like the Rust backend's `Event::format_line` into `buf`
+ * (NUL-terminated, truncated to `cap`); returns the full length, as
+ * snprintf does. */
+size_t __p___format_event(const __p___event *event, char *buf, size_t cap);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* __P___H */
+"#;
+
+const SOURCE_HEAD: &str = r#"/* Generated by descent (`--backend c`) from __GRAMMAR__.desc - DO NOT EDIT
+ *
+ * Callback-based recursive descent parser: the C call stack is the element
+ * stack, each grammar state a label. Event-for-event equivalent to the Rust
+ * backend's `Parser`.
+ */
+
+#include "__p__.h"
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+"#;
+
+const RUNTIME: &str = r#"
+#if defined(__GNUC__)
+#define DP_UNUSED __attribute__((unused))
+#else
+#define DP_UNUSED
+#endif
+
+/* term_pos before any TERM since the last MARK. */
+#define DP_NO_TERM SIZE_MAX
+
+typedef struct {
+    const uint8_t *ptr;
+    size_t len;
+} dp_bytes;
+
+#define DP_BYTES(s) ((dp_bytes){ (const uint8_t *)(s), sizeof(s) - 1 })
+
+typedef struct {
+    const uint8_t *input;
+    size_t len;
+    size_t pos;
+    size_t mark_pos;
+    size_t term_pos;
+    /* PREPEND bytes, consumed by the next capture. */
+    uint8_t *prepend_buf;
+    size_t prepend_len;
+    size_t prepend_cap;
+    /* Prepend bytes consumed by the last capture: its span extends back
+     * over them (PREPEND restores just-consumed source bytes). */
+    size_t term_prepend_len;
+    uint32_t line;
+    uint32_t column;
+__SLOTS__    __p___callback cb;
+    void *user;
+    int oom;
+} dp_parser;
+
+typedef struct {
+    const char *kw;
+    size_t len;
+    __p___event_kind kind;
+} dp_keyword;
+
+/* ========== Runtime ========== */
+
+static DP_UNUSED int dp_peek(const dp_parser *p)
+{
+    return p->pos < p->len ? p->input[p->pos] : -1;
+}
+
+static DP_UNUSED int dp_eof(const dp_parser *p)
+{
+    return p->pos >= p->len;
+}
+
+static DP_UNUSED void dp_advance(dp_parser *p)
+{
+    if (p->pos < p->len) {
+        uint8_t b = p->input[p->pos];
+        if (b == '\n') {
+            p->line++;
+            p->column = 1;
+        } else if ((b & 0xC0) != 0x80) {
+            /* Columns count characters: UTF-8 continuation bytes don't. */
+            p->column++;
+        }
+        p->pos++;
+    }
+}
+
+static DP_UNUSED void dp_mark(dp_parser *p)
+{
+    p->mark_pos = p->pos;
+    p->term_pos = DP_NO_TERM;
+    p->term_prepend_len = 0;
+    /* prepend_buf survives: PREPEND may precede a callee's MARK. */
+}
+
+static DP_UNUSED void dp_set_term(dp_parser *p, int32_t offset)
+{
+    int64_t at = (int64_t)p->pos + offset;
+    if (at < 0) {
+        at = 0;
+    }
+    if (at > (int64_t)p->len) {
+        at = (int64_t)p->len;
+    }
+    p->term_pos = (size_t)at;
+}
+
+static DP_UNUSED void dp_prepend(dp_parser *p, dp_bytes bytes)
+{
+    if (bytes.len == 0) {
+        return;
+    }
+    if (p->prepend_len + bytes.len > p->prepend_cap) {
+        size_t cap = p->prepend_cap ? p->prepend_cap * 2 : 64;
+        uint8_t *grown;
+        while (cap < p->prepend_len + bytes.len) {
+            cap *= 2;
+        }
+        grown = (uint8_t *)realloc(p->prepend_buf, cap);
+        if (grown == NULL) {
+            p->oom = 1;
+            return;
+        }
+        p->prepend_buf = grown;
+        p->prepend_cap = cap;
+    }
+    memcpy(p->prepend_buf + p->prepend_len, bytes.ptr, bytes.len);
+    p->prepend_len += bytes.len;
+}
+
+static DP_UNUSED size_t dp_capture_end(const dp_parser *p)
+{
+    return p->term_pos != DP_NO_TERM ? p->term_pos : p->pos;
+}
+
+/* MARK..TERM (or ..pos), behind any prepended bytes, which it consumes. */
+static DP_UNUSED dp_bytes dp_term(dp_parser *p)
+{
+    size_t end = dp_capture_end(p);
+    dp_bytes slice;
+    slice.ptr = p->input + p->mark_pos;
+    slice.len = end > p->mark_pos ? end - p->mark_pos : 0;
+    p->term_prepend_len = p->prepend_len;
+    if (p->prepend_len == 0) {
+        return slice;
+    }
+    dp_prepend(p, slice);
+    slice.ptr = p->prepend_buf;
+    slice.len = p->prepend_len;
+    p->prepend_len = 0;
+    return slice;
+}
+
+static DP_UNUSED void dp_span_from_mark(const dp_parser *p, size_t *start, size_t *end)
+{
+    size_t plen = p->prepend_len ? p->prepend_len : p->term_prepend_len;
+    *start = p->mark_pos > plen ? p->mark_pos - plen : 0;
+    *end = dp_capture_end(p);
+}
+
+static DP_UNUSED int32_t dp_col(const dp_parser *p)
+{
+    return (int32_t)p->column;
+}
+
+/* Previous byte (0 at start of input). */
+static DP_UNUSED uint8_t dp_prev(const dp_parser *p)
+{
+    return p->pos > 0 ? p->input[p->pos - 1] : 0;
+}
+
+static DP_UNUSED int dp_is_letter(uint8_t b)
+{
+    return (b >= 'a' && b <= 'z') || (b >= 'A' && b <= 'Z');
+}
+
+static DP_UNUSED int dp_is_digit(uint8_t b)
+{
+    return b >= '0' && b <= '9';
+}
+
+static DP_UNUSED int dp_is_label_cont(uint8_t b)
+{
+    return dp_is_letter(b) || dp_is_digit(b) || b == '_' || b == '-';
+}
+
+static DP_UNUSED int dp_is_hex_digit(uint8_t b)
+{
+    return dp_is_digit(b) || (b >= 'a' && b <= 'f') || (b >= 'A' && b <= 'F');
+}
+
+static DP_UNUSED int dp_is_ws(uint8_t b)
+{
+    return b == ' ' || b == '\t';
+}
+
+static DP_UNUSED int dp_is_nl(uint8_t b)
+{
+    return b == '\n';
+}
+
+/* Unicode identifier classes at the byte level, as the Rust backend has
+ * them: ASCII exactly, non-ASCII by UTF-8 lead / continuation byte. */
+static DP_UNUSED int dp_is_xid_start(uint8_t b)
+{
+    return b < 0x80 ? dp_is_letter(b) : (b >= 0xC2 && b <= 0xF4);
+}
+
+static DP_UNUSED int dp_is_xid_cont(uint8_t b)
+{
+    return b < 0x80 ? (dp_is_letter(b) || dp_is_digit(b) || b == '_') : 1;
+}
+
+static DP_UNUSED int dp_is_xlbl_start(uint8_t b)
+{
+    return dp_is_xid_start(b);
+}
+
+static DP_UNUSED int dp_is_xlbl_cont(uint8_t b)
+{
+    return b == '-' || dp_is_xid_cont(b);
+}
+
+static DP_UNUSED uint32_t dp_char_count(const uint8_t *s, size_t n)
+{
+    uint32_t count = 0;
+    size_t i;
+    for (i = 0; i < n; i++) {
+        count += (s[i] & 0xC0) != 0x80;
+    }
+    return count;
+}
+
+/* SCAN: skip to the first of `n` needles ('\n' among them whenever the
+ * state's cases leave room, for line tracking). Returns the byte found, or
+ * -1 with pos at the end of input. One needle is memchr; more test a
+ * 256-bit set. */
+static DP_UNUSED int dp_scan(dp_parser *p, const uint8_t *needles, size_t n)
+{
+    const uint8_t *at = p->input + p->pos;
+    size_t rest = p->len - p->pos;
+    size_t off = rest;
+    if (n == 1) {
+        const uint8_t *hit = rest ? (const uint8_t *)memchr(at, needles[0], rest) : NULL;
+        if (hit != NULL) {
+            off = (size_t)(hit - at);
+        }
+    } else {
+        uint32_t set[8] = { 0 };
+        size_t i;
+        for (i = 0; i < n; i++) {
+            set[needles[i] >> 5] |= 1u << (needles[i] & 31);
+        }
+        for (i = 0; i < rest; i++) {
+            if (set[at[i] >> 5] & (1u << (at[i] & 31))) {
+                off = i;
+                break;
+            }
+        }
+    }
+    p->column += dp_char_count(at, off);
+    p->pos += off;
+    return p->pos < p->len ? p->input[p->pos] : -1;
+}
+
+/* ========== Event delivery ========== */
+
+static DP_UNUSED void dp_deliver(dp_parser *p, __p___event_kind kind, const uint8_t *content, size_t len, size_t start,
+                                 size_t end, __p___error_code code)
+{
+    __p___event ev;
+    ev.kind = kind;
+    ev.content = content;
+    ev.content_len = len;
+    ev.start = start;
+    ev.end = end;
+    ev.code = code;
+    p->cb(&ev, p->user);
+}
+
+/* Start/End: no content. */
+static DP_UNUSED void dp_emit_mark_span(dp_parser *p, __p___event_kind kind, size_t start, size_t end)
+{
+    dp_deliver(p, kind, NULL, 0, start, end, (__p___error_code)0);
+}
+
+static DP_UNUSED void dp_emit_content(dp_parser *p, __p___event_kind kind, const uint8_t *content, size_t len,
+                                      size_t start, size_t end)
+{
+    dp_deliver(p, kind, content ? content : (const uint8_t *)"", len, start, end, (__p___error_code)0);
+}
+
+/* Type(USE_MARK) / emit(Type) / a CONTENT return: the capture. */
+static DP_UNUSED void dp_emit_capture(dp_parser *p, __p___event_kind kind)
+{
+    dp_bytes content = dp_term(p);
+    size_t start, end;
+    dp_span_from_mark(p, &start, &end);
+    dp_emit_content(p, kind, content.ptr, content.len, start, end);
+}
+
+/* Literal / param / bare payloads, at an empty span at pos. */
+static DP_UNUSED void dp_emit_bytes(dp_parser *p, __p___event_kind kind, dp_bytes bytes)
+{
+    dp_emit_content(p, kind, bytes.ptr, bytes.len, p->pos, p->pos);
+}
+
+/* Type(USE_SAVED(slot)): content and span from the slot. */
+static DP_UNUSED void dp_emit_range(dp_parser *p, __p___event_kind kind, size_t start, size_t end)
+{
+    dp_emit_content(p, kind, p->input + start, end > start ? end - start : 0, start, end);
+}
+
+static DP_UNUSED void dp_error(dp_parser *p, __p___error_code code)
+{
+    dp_deliver(p, __P___EVENT_ERROR, NULL, 0, p->pos, p->pos, code);
+}
+
+/* ========== Keywords ========== */
+
+static DP_UNUSED uint32_t dp_hash(uint32_t seed, const uint8_t *s, size_t n)
+{
+    uint32_t h = 2166136261u ^ seed;
+    size_t i;
+    for (i = 0; i < n; i++) {
+        h ^= s[i];
+        h *= 16777619u;
+    }
+    return h;
+}
+
+static DP_UNUSED int dp_keyword_lookup(dp_parser *p, const dp_keyword *table, uint32_t seed, uint32_t mask)
+{
+    dp_bytes content = dp_term(p);
+    const dp_keyword *k = &table[dp_hash(seed, content.ptr, content.len) & mask];
+    size_t start, end;
+    if (k->kw == NULL || k->len != content.len || (k->len && memcmp(k->kw, content.ptr, k->len) != 0)) {
+        return 0;
+    }
+    dp_span_from_mark(p, &start, &end);
+    dp_emit_content(p, k->kind, content.ptr, content.len, start, end);
+    return 1;
+}
+"#;
+
+const API: &str = r#"
+typedef struct {
+    char *buf;
+    size_t cap;
+    size_t len;
+} dp_writer;
+
+static void dp_put(dp_writer *w, const char *s, size_t n)
+{
+    size_t i;
+    for (i = 0; i < n; i++, w->len++) {
+        if (w->len + 1 < w->cap) {
+            w->buf[w->len] = s[i];
+        }
+    }
+}
+
+static void dp_puts(dp_writer *w, const char *s)
+{
+    dp_put(w, s, strlen(s));
+}
+
+/* Decode one UTF-8 scalar at s[0..n) (Rust's `str::from_utf8` rules);
+ * returns its length, or 0 if invalid. */
+static size_t dp_utf8(const uint8_t *s, size_t n, uint32_t *cp)
+{
+    size_t len, i;
+    uint32_t min;
+    if (s[0] < 0x80) {
+        *cp = s[0];
+        return 1;
+    } else if (s[0] >= 0xC2 && s[0] <= 0xDF) {
+        len = 2, min = 0x80, *cp = s[0] & 0x1F;
+    } else if (s[0] >= 0xE0 && s[0] <= 0xEF) {
+        len = 3, min = 0x800, *cp = s[0] & 0x0F;
+    } else if (s[0] >= 0xF0 && s[0] <= 0xF4) {
+        len = 4, min = 0x10000, *cp = s[0] & 0x07;
+    } else {
+        return 0;
+    }
+    if (n < len) {
+        return 0;
+    }
+    for (i = 1; i < len; i++) {
+        if ((s[i] & 0xC0) != 0x80) {
+            return 0;
+        }
+        *cp = (*cp << 6) | (s[i] & 0x3F);
+    }
+    if (*cp < min || *cp > 0x10FFFF || (*cp >= 0xD800 && *cp <= 0xDFFF)) {
+        return 0;
+    }
+    return len;
+}
+
+/* Rust's `{:?}` of the content as a str, or "<invalid utf8>". Non-ASCII
+ * escaping covers the C1 controls and U+00AD; other code points print
+ * as themselves. */
+static void dp_put_debug(dp_writer *w, const uint8_t *s, size_t n)
+{
+    size_t i, len;
+    uint32_t cp;
+    char esc[16];
+    for (i = 0; i < n; i += len) {
+        if ((len = dp_utf8(s + i, n - i, &cp)) == 0) {
+            dp_puts(w, "\"<invalid utf8>\"");
+            return;
+        }
+    }
+    dp_puts(w, "\"");
+    for (i = 0; i < n; i += len) {
+        len = dp_utf8(s + i, n - i, &cp);
+        switch (cp) {
+        case 0: dp_puts(w, "\\0"); break;
+        case '\t': dp_puts(w, "\\t"); break;
+        case '\n': dp_puts(w, "\\n"); break;
+        case '\r': dp_puts(w, "\\r"); break;
+        case '"': dp_puts(w, "\\\""); break;
+        case '\\': dp_puts(w, "\\\\"); break;
+        default:
+            if (cp < 0x20 || cp == 0x7F || (cp >= 0x80 && cp < 0xA0) || cp == 0xAD) {
+                snprintf(esc, sizeof esc, "\\u{%lx}", (unsigned long)cp);
+                dp_puts(w, esc);
+            } else {
+                dp_put(w, (const char *)s + i, len);
+            }
+        }
+    }
+    dp_puts(w, "\"");
+}
+
+size_t __p___format_event(const __p___event *event, char *buf, size_t cap)
+{
+    dp_writer w;
+    char span[64];
+    w.buf = buf;
+    w.cap = cap;
+    w.len = 0;
+    dp_puts(&w, __p___event_name(event->kind));
+    if (event->kind == __P___EVENT_ERROR) {
+        dp_puts(&w, " ");
+        dp_puts(&w, __p___error_name(event->code));
+    } else if (event->content != NULL) {
+        dp_puts(&w, " ");
+        dp_put_debug(&w, event->content, event->content_len);
+    }
+    snprintf(span, sizeof span, " @ %lu..%lu", (unsigned long)event->start, (unsigned long)event->end);
+    dp_puts(&w, span);
+    if (cap > 0) {
+        w.buf[w.len < cap ? w.len : cap - 1] = '\0';
+    }
+    return w.len;
+}
+
+int __p___parse(const uint8_t *input, size_t len, __p___callback cb, void *user)
+{
+    dp_parser p;
+    memset(&p, 0, sizeof p);
+    p.input = input ? input : (const uint8_t *)"";
+    p.len = input ? len : 0;
+    p.line = 1;
+    p.column = 1;
+    p.cb = cb;
+    p.user = user;
+    __ENTRY__
+    free(p.prepend_buf);
+    return p.oom ? -1 : 0;
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TINY: &str = "|parser tiny\n|type[Word] CONTENT\n|type[Kw] CONTENT\n|type[List] BRACKET\n|entry-point /doc\n|keywords[kw] :fallback /word\n  | if => Kw\n  | else => Kw\n|function[doc:List]\n  |state[:main]\n    |LETTER |.w | MARK |>> :word\n    |c[' \\n'] | -> |>>\n    |default | /error(UnexpectedChar) | -> |>>\n  |state[:word]\n    |LETTER | -> |>>\n    |default | TERM | KEYWORDS(kw) |>> :main\n|function[word:Word]\n  |state[:main]\n    |default |return\n";
+
+    #[test]
+    fn perfect_hash_has_no_collisions() {
+        let keys: Vec<Vec<u8>> = ["true", "false", "null", "if", "else", "while", "for", "return", "fn"]
+            .iter()
+            .map(|k| k.as_bytes().to_vec())
+            .collect();
+        let (seed, size) = perfect_hash(&keys);
+        let slots: BTreeSet<usize> = keys.iter().map(|k| fnv1a(seed, k) as usize & (size - 1)).collect();
+        assert_eq!(slots.len(), keys.len());
+        assert!(size.is_power_of_two());
+    }
+
+    #[test]
+    fn header_declares_the_api() {
+        let ir = crate::build_ir_with(TINY, "tiny.desc", crate::Frontend::OracleLexer).unwrap();
+        let files = generate(&ir, &COptions { prefix: "tiny".to_string() }).unwrap();
+        assert_eq!(files.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>(), ["tiny.h", "tiny.c"]);
+        let h = &files[0].1;
+        assert!(h.contains("    TINY_EVENT_LIST_START,\n    TINY_EVENT_LIST_END,\n    TINY_EVENT_ERROR,"));
+        assert!(h.contains("typedef void (*tiny_callback)(const tiny_event *event, void *user);"));
+        assert!(h.contains("int tiny_parse(const uint8_t *input, size_t len, tiny_callback cb, void *user);"));
+        let c = &files[1].1;
+        assert!(c.contains("static const dp_keyword dp_kw_kw_table["));
+        assert!(c.contains("parse_word(p);"));
+    }
+
+    #[test]
+    fn c_keywords_are_mangled() {
+        let desc = "|parser g\n|entry-point /doc\n|function[doc] | int = 0\n  |state[:main]\n    |c['a'] | int += 1 | -> |>>\n    |c['b'] | -> | /skip('b') |>>\n    |default | -> |return\n|function[skip] :char\n  |state[:main]\n    |c[:char] | -> |return\n    |default | -> |>>\n";
+        let ir = crate::build_ir_with(desc, "g.desc", crate::Frontend::OracleLexer).unwrap();
+        let c = &generate(&ir, &COptions::default()).unwrap()[1].1;
+        assert!(c.contains("int32_t v_int = 0;"), "{c}");
+        assert!(c.contains("v_int += 1;"));
+        assert!(c.contains("c == v_char"));
+    }
+}
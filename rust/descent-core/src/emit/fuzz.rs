@@ -0,0 +1,151 @@
+//! Differential fuzz crate (`descent-rs fuzz-target <file.desc>`).
+//!
+//! Generates a self-contained cargo-fuzz / libFuzzer crate for one grammar:
+//! the recursive parser (with its nested pushdown machine) as the library,
+//! and a `differential` target asserting backend parity on fuzzer-chosen
+//! input. Chunk-boundary bugs only show on splits nobody wrote by hand, so
+//! the fuzzer picks the splits too, and every chunk boundary is also closed
+//! early (`finish` on a restored snapshot) against a one-shot parse of that
+//! prefix.
+//!
+//! Crate dependencies follow the generated code: `phf` / `memchr` /
+//! `unicode-xid` are listed only when the parser uses them.
+
+use super::rust::{self, Options};
+use crate::diagnostic::Diagnostic;
+use crate::ir::ParserIR;
+
+/// The generated crate as (path relative to the crate root, contents).
+pub fn generate(ir: &ParserIR) -> Result<Vec<(&'static str, String)>, Diagnostic> {
+    let parser = rust::generate(ir, &Options { streaming: true, ..Default::default() })?;
+    let name = crate_name(ir);
+    let mut deps = String::from("libfuzzer-sys = \"0.4\"\n");
+    if parser.contains("memchr::") {
+        deps.push_str("memchr = \"2\"\n");
+    }
+    if parser.contains("phf::") {
+        deps.push_str("phf = { version = \"0.11\", features = [\"macros\"] }\n");
+    }
+    if parser.contains("unicode_xid::") {
+        deps.push_str("unicode-xid = \"0.2\"\n");
+    }
+    let lib = name.replace('-', "_");
+    Ok(vec![
+        ("Cargo.toml", CARGO_TOML.replace("__NAME__", &name).replace("__DEPS__", &deps)),
+        (".gitignore", "target\ncorpus\nartifacts\ncoverage\n".to_string()),
+        ("src/lib.rs", "//! Generated by descent - DO NOT EDIT\n\npub mod parser;\n".to_string()),
+        ("src/parser.rs", parser),
+        ("fuzz_targets/differential.rs", DIFFERENTIAL.replace("__LIB__", &lib)),
+    ])
+}
+
+/// `<grammar>-fuzz`, from the grammar's `|parser` name.
+fn crate_name(ir: &ParserIR) -> String {
+    let base: String = ir
+        .name
+        .as_deref()
+        .unwrap_or("parser")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    format!("{base}-fuzz")
+}
+
+const CARGO_TOML: &str = r#"# Generated by descent (`descent-rs fuzz-target`) - regenerate, don't edit.
+[package]
+name = "__NAME__"
+version = "0.0.0"
+publish = false
+edition = "2021"
+
+[package.metadata]
+cargo-fuzz = true
+
+[dependencies]
+__DEPS__
+[[bin]]
+name = "differential"
+path = "fuzz_targets/differential.rs"
+test = false
+doc = false
+bench = false
+
+[lints.rust]
+unexpected_cfgs = { level = "warn", check-cfg = ['cfg(feature, values("serde", "tokio"))'] }
+
+# Standalone: not part of any enclosing workspace.
+[workspace]
+"#;
+
+const DIFFERENTIAL: &str = r#"//! Generated by descent (`descent-rs fuzz-target`) - DO NOT EDIT
+//!
+//! Backend parity: the recursive parser's events must equal the pushdown
+//! machine's for any input under any chunking, and closing the machine at
+//! any chunk boundary (`finish` on a restored snapshot) must equal a
+//! one-shot parse of that prefix.
+//!
+//! Input layout: the first byte `n` (mod 16) counts the chunk-length bytes
+//! after it; the rest is the document, pushed in chunks of those lengths,
+//! cycled (0 = an empty chunk). No lengths, or all zero: one chunk.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use __LIB__::parser::pushdown::PushdownParser;
+use __LIB__::parser::{Event, Parser, StreamEvent};
+
+fn owned(e: StreamEvent<'_>) -> Event<'static> {
+    e.into_event(|c| std::borrow::Cow::Owned(c.into_owned()))
+}
+
+fn one_shot(input: &[u8]) -> Vec<Event<'_>> {
+    let mut events = Vec::new();
+    Parser::new(input).parse(|e| events.push(e));
+    events
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&n, rest)) = data.split_first() else {
+        return;
+    };
+    let (lens, input) = rest.split_at((n as usize % 16).min(rest.len()));
+    let whole = lens.iter().all(|&b| b == 0);
+    let mut cuts = lens.iter().map(|&b| b as usize).cycle();
+
+    let mut pd = PushdownParser::new();
+    let mut got = Vec::new();
+    let mut at = 0;
+    while at < input.len() {
+        let len = if whole { input.len() } else { cuts.next().unwrap() };
+        let end = (at + len).min(input.len());
+        pd.push_chunk(&input[at..end], &mut |e| got.push(owned(e)));
+        at = end;
+
+        let mut early = got.clone();
+        let mut probe = PushdownParser::new();
+        probe.restore(pd.snapshot());
+        probe.finish(&mut |e| early.push(owned(e)));
+        assert_eq!(early, one_shot(&input[..at]), "finish after {at} of {} bytes", input.len());
+    }
+    pd.finish(&mut |e| got.push(owned(e)));
+    assert_eq!(got, one_shot(input), "chunked vs one-shot");
+});
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crate_lists_only_used_dependencies() {
+        let src = "|parser tiny\n|type[Word] CONTENT\n|entry-point /doc\n|function[doc:Word]\n  |state[:main]\n    |c[\\n] |return\n    |default |->\n";
+        let ir = crate::build_ir_with(src, "tiny.desc", crate::Frontend::OracleLexer).unwrap();
+        let files = generate(&ir).unwrap();
+        let paths: Vec<_> = files.iter().map(|(p, _)| *p).collect();
+        assert_eq!(paths, ["Cargo.toml", ".gitignore", "src/lib.rs", "src/parser.rs", "fuzz_targets/differential.rs"]);
+        let toml = &files[0].1;
+        assert!(toml.contains("name = \"tiny-fuzz\""));
+        assert!(!toml.contains("phf"));
+        assert!(files[4].1.contains("use tiny_fuzz::parser::pushdown::PushdownParser;"));
+    }
+}
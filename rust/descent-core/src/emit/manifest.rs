@@ -0,0 +1,286 @@
+//! Parser manifest: the generated parser's vocabulary as JSON, for diffing
+//! against a spec's declared vocabulary (`descent-rs manifest`).
+//!
+//! Lists what the generated code can say, derived from the IR the same way
+//! the backends derive it:
+//! - `events`: one entry per `Event` variant — `<T>Start`/`<T>End` for
+//!   BRACKET types, `<T>` for CONTENT types, plus the built-in `Error`;
+//! - `error_codes`: `ParseErrorCode` variants in declaration order —
+//!   built-ins, `Unclosed<T>` for `expects_char` functions, then the
+//!   grammar's `/error(code)` codes (PascalCased, deduplicated as the
+//!   template does);
+//! - `warnings`: every literal a `Warning` event can carry — inline
+//!   `Warning(lit)` emits, plus `delimited_code` where some state falls
+//!   through to the generated EOF force-unwind — sorted;
+//! - `functions`: each function's `classify` kind;
+//! - `keywords` and `entry_point`.
+//!
+//! `diff` compares two manifests (`descent-rs manifest --diff`): vocabulary
+//! added or removed, and functions whose classification changed. Removals
+//! are what break consumers, so they are what a CI gate fails on.
+
+use super::rust::engine::pascalcase;
+use crate::classify;
+use crate::ir::{Command, Function, ParserIR, State};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+
+/// ParseErrorCode variants every generated parser declares.
+pub const BUILTIN_ERROR_CODES: &[&str] = &["UnexpectedEof", "UnexpectedChar", "LimitExceeded"];
+
+/// Build the manifest for `ir`.
+pub fn generate(ir: &ParserIR) -> Value {
+    json!({
+        "parser": ir.name,
+        "entry_point": ir.entry_point,
+        "events": events(ir),
+        "error_codes": error_codes(ir),
+        "warnings": warnings(ir),
+        "functions": classify::classify(ir).iter().map(|c| json!({
+            "name": c.name,
+            "return_type": c.return_type,
+            "kind": c.kind.as_str(),
+        })).collect::<Vec<_>>(),
+        "keywords": ir.keywords.iter().map(|k| json!({
+            "name": k.name,
+            "fallback": k.fallback_func,
+            "mappings": k.mappings.iter().map(|m| json!({
+                "keyword": m.keyword,
+                "event": m.event_type,
+            })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn events(ir: &ParserIR) -> Vec<Value> {
+    let mut out = Vec::new();
+    for t in &ir.types {
+        match t.kind.as_str() {
+            "bracket" => {
+                for suffix in ["Start", "End"] {
+                    out.push(json!({ "name": format!("{}{suffix}", t.name), "type": t.name, "kind": t.kind }));
+                }
+            }
+            "content" => out.push(json!({ "name": t.name, "type": t.name, "kind": t.kind })),
+            _ => {}
+        }
+    }
+    out.push(json!({ "name": "Error", "type": null, "kind": "error" }));
+    out
+}
+
+fn error_codes(ir: &ParserIR) -> Vec<String> {
+    let mut codes: Vec<String> = BUILTIN_ERROR_CODES.iter().map(|c| c.to_string()).collect();
+    let unclosed = ir
+        .functions
+        .iter()
+        .filter(|f| f.expects_char.is_some())
+        .map(|f| format!("Unclosed{}", f.return_type.as_deref().unwrap_or("")));
+    for code in unclosed.chain(ir.custom_error_codes.iter().map(|c| pascalcase(c))) {
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+    codes
+}
+
+fn warnings(ir: &ParserIR) -> Vec<String> {
+    fn collect(cmds: &[Command], out: &mut BTreeSet<String>) {
+        for cmd in cmds {
+            let literal = match cmd.ctype.as_str() {
+                "inline_emit_literal" if cmd.arg_str("type") == Some("Warning") => cmd.arg_str("literal"),
+                "return" if cmd.arg_str("emit_type") == Some("Warning") => cmd.arg_str("literal"),
+                _ => None,
+            };
+            out.extend(literal.map(|l| l.trim_matches('\'').to_string()));
+            for clause in cmd.clauses.iter().flatten() {
+                collect(&clause.commands, out);
+            }
+        }
+    }
+    let mut out = BTreeSet::new();
+    for func in &ir.functions {
+        if reaches_default_eof(func) {
+            out.extend(func.delimited_code.clone());
+        }
+        collect(&func.entry_actions, &mut out);
+        collect(func.eof_handler.as_deref().unwrap_or_default(), &mut out);
+        for state in &func.states {
+            collect(state.eof_handler.as_deref().unwrap_or_default(), &mut out);
+            for kase in &state.cases {
+                collect(&kase.commands, &mut out);
+            }
+        }
+    }
+    out.into_iter().collect()
+}
+
+/// Whether EOF in some state of `func` runs the generated default (the
+/// template's last EOF arm) rather than a handler or a re-run case.
+fn reaches_default_eof(func: &Function) -> bool {
+    let no_handler = |h: &Option<Vec<Command>>| h.as_ref().is_none_or(Vec::is_empty);
+    // Total `_ if` / `_` arms run at EOF too (emit::rust `byte_independent`).
+    let byte_independent = |s: &State| {
+        !s.cases.is_empty()
+            && s.eof_handler.is_none()
+            && s.cases.iter().all(|c| c.chars.is_none() && c.special_class.is_none() && c.param_ref.is_none())
+    };
+    no_handler(&func.eof_handler)
+        && func.states.iter().any(|s| {
+            no_handler(&s.eof_handler) && !s.eof_run_newline() && !s.eof_run_default() && !byte_independent(s)
+        })
+}
+
+/// Vocabulary changes between two manifests.
+#[derive(Debug, Default, PartialEq)]
+pub struct ManifestDiff {
+    pub added_events: Vec<String>,
+    pub removed_events: Vec<String>,
+    pub added_error_codes: Vec<String>,
+    pub removed_error_codes: Vec<String>,
+    pub added_warnings: Vec<String>,
+    pub removed_warnings: Vec<String>,
+    /// (function, old kind, new kind) for functions present in both.
+    pub reclassified: Vec<(String, String, String)>,
+}
+
+impl ManifestDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == ManifestDiff::default()
+    }
+
+    pub fn has_removals(&self) -> bool {
+        !(self.removed_events.is_empty() && self.removed_error_codes.is_empty() && self.removed_warnings.is_empty())
+    }
+
+    /// One line per change: `+ event FooStart`, `- error_code Bar`,
+    /// `~ function baz: positional -> delimited`.
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        let groups = [
+            ("event", &self.added_events, &self.removed_events),
+            ("error_code", &self.added_error_codes, &self.removed_error_codes),
+            ("warning", &self.added_warnings, &self.removed_warnings),
+        ];
+        for (what, added, removed) in groups {
+            for name in removed {
+                let _ = writeln!(out, "- {what} {name}");
+            }
+            for name in added {
+                let _ = writeln!(out, "+ {what} {name}");
+            }
+        }
+        for (name, old, new) in &self.reclassified {
+            let _ = writeln!(out, "~ function {name}: {old} -> {new}");
+        }
+        out
+    }
+}
+
+/// Compare an old manifest (e.g. a committed `manifest.json`) with a new one.
+/// Missing keys read as empty, so a manifest from an older descent still diffs.
+pub fn diff(old: &Value, new: &Value) -> ManifestDiff {
+    fn names(m: &Value, key: &str) -> Vec<String> {
+        let items = m.get(key).and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+        items
+            .iter()
+            .filter_map(|v| v.as_str().or_else(|| v.get("name").and_then(Value::as_str)))
+            .map(str::to_string)
+            .collect()
+    }
+    fn delta(old: &Value, new: &Value, key: &str) -> (Vec<String>, Vec<String>) {
+        let (old, new) = (names(old, key), names(new, key));
+        let added = new.iter().filter(|n| !old.contains(n)).cloned().collect();
+        let removed = old.iter().filter(|n| !new.contains(n)).cloned().collect();
+        (added, removed)
+    }
+    let kinds = |m: &Value| -> Vec<(String, String)> {
+        let items = m.get("functions").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+        items
+            .iter()
+            .filter_map(|f| Some((f.get("name")?.as_str()?.to_string(), f.get("kind")?.as_str()?.to_string())))
+            .collect()
+    };
+    let old_kinds = kinds(old);
+    let reclassified = kinds(new)
+        .into_iter()
+        .filter_map(|(name, kind)| {
+            let (_, was) = old_kinds.iter().find(|(n, _)| *n == name)?;
+            (*was != kind).then(|| (name, was.clone(), kind))
+        })
+        .collect();
+
+    let (added_events, removed_events) = delta(old, new, "events");
+    let (added_error_codes, removed_error_codes) = delta(old, new, "error_codes");
+    let (added_warnings, removed_warnings) = delta(old, new, "warnings");
+    ManifestDiff {
+        added_events,
+        removed_events,
+        added_error_codes,
+        removed_error_codes,
+        added_warnings,
+        removed_warnings,
+        reclassified,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_reports_vocabulary_and_classification_changes() {
+        let old = json!({
+            "events": [{ "name": "Text" }, { "name": "Error" }],
+            "error_codes": ["UnexpectedEof", "UnclosedArray"],
+            "warnings": ["UnclosedArray"],
+            "functions": [{ "name": "array", "kind": "delimited" }, { "name": "gone", "kind": "inert" }],
+        });
+        let new = json!({
+            "events": [{ "name": "Text" }, { "name": "Comment" }, { "name": "Error" }],
+            "error_codes": ["UnexpectedEof"],
+            "warnings": ["UnclosedArray"],
+            "functions": [{ "name": "array", "kind": "positional" }],
+        });
+        let d = diff(&old, &new);
+        assert!(d.has_removals());
+        assert_eq!(
+            d.report(),
+            "+ event Comment\n- error_code UnclosedArray\n~ function array: delimited -> positional\n"
+        );
+        assert!(diff(&new, &new).is_empty());
+    }
+
+    /// Variant names of `pub enum <name>` in generated code.
+    fn variants(code: &str, name: &str) -> Vec<String> {
+        let body = code.split(&format!("pub enum {name}")).nth(1).unwrap();
+        let body = &body[body.find('{').unwrap() + 1..body.find("\n}").unwrap()];
+        body.lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with("//"))
+            .map(|l| l.split(|c: char| !c.is_alphanumeric()).next().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn manifest_matches_the_generated_parser() {
+        for path in ["../tests/fixtures/udon_complete.desc", "../tests/fixtures/markdown.desc", "../tests/fixtures/brackets.desc"] {
+            let desc = std::fs::read_to_string(path).unwrap();
+            let ir = crate::build_ir_with(&desc, path, crate::Frontend::OracleLexer).unwrap();
+            let code = crate::emit::rust::generate(&ir, &Default::default()).unwrap();
+            let m = generate(&ir);
+            let names = |key: &str| -> Vec<String> {
+                let items = m[key].as_array().unwrap().iter();
+                items.map(|v| v.as_str().or_else(|| v["name"].as_str()).unwrap().to_string()).collect()
+            };
+            assert_eq!(names("events"), variants(&code, "Event<'a>"), "{path}");
+            assert_eq!(names("error_codes"), variants(&code, "ParseErrorCode"), "{path}");
+            let emitted: BTreeSet<String> = crate::lexer::re(r#"Warning \{ content: std::borrow::Cow::Borrowed\(b"([^"]*)"\)"#)
+                .captures_iter(&code)
+                .map(|c| c[1].to_string())
+                .collect();
+            assert_eq!(names("warnings"), emitted.into_iter().collect::<Vec<_>>(), "{path}");
+        }
+    }
+}
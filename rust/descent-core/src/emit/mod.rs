@@ -2,7 +2,17 @@
 //!
 //! This is the ONLY layer where target literals (Rust byte literals, escape
 //! rendering, expression transpilation) may be produced — the IR itself
-//! stays DSL-level (see ir.rs module docs and rust/PROGRESS.md).
+//! stays DSL-level (see ir.rs module docs and rust/PROGRESS.md). Targets
+//! are selected through the `Backend` trait and `Registry` (backend.rs).
 
+pub mod backend;
+pub mod c;
+pub mod fuzz;
+pub mod manifest;
 pub mod rust;
 pub mod rust_pushdown;
+
+pub use backend::{Backend, BackendOptions, OptionSpec, OutputFile, Registry};
+
+/// Per-backend reserved identifiers, for the validator's collision check.
+pub const RESERVED_NAMES: &[(&str, &[&str])] = &[("rust", rust::RESERVED), ("pushdown", rust_pushdown::RESERVED)];
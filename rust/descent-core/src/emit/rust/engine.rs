@@ -78,12 +78,22 @@ pub fn make_env() -> Result<Environment<'static>, Error> {
     env.add_filter("dstr", |v: Value| value_str(&v));
 
     env.add_test("ltruthy", |v: Value| liquid_truthy(&v));
+    // `{% filter emit %}Event::…{% endfilter %}`: one event emission. Under
+    // `cancellable` the callback's ControlFlow is checked and `Break` halts.
+    env.add_filter("emit", |state: &State, ev: String| {
+        if state.lookup("cancellable").is_some_and(|v| v.is_true()) {
+            format!("if on_event({ev}).is_break() {{ self.halt(); }}")
+        } else {
+            format!("on_event({ev});")
+        }
+    });
 
     env.add_function(
         "render_command",
         |state: &State, cmd: Value, func: Value, return_type_info: Value| -> Result<String, Error> {
             let tmpl = state.env().get_template("_command.j2")?;
-            tmpl.render(context! { cmd, func, return_type_info })
+            let cancellable = state.lookup("cancellable");
+            tmpl.render(context! { cmd, func, return_type_info, cancellable })
         },
     );
 
@@ -360,4 +370,14 @@ mod tests {
         assert_eq!(transform_call_args(";"), "b';'");
         assert_eq!(transform_call_args(""), "");
     }
+
+    #[test]
+    fn emit_filter_checks_break_when_cancellable() {
+        let env = make_env().unwrap();
+        let src = "{% filter emit %}Event::TextEnd { span: self.span() }{% endfilter %}";
+        let plain = env.render_str(src, minijinja::context! {}).unwrap();
+        assert_eq!(plain, "on_event(Event::TextEnd { span: self.span() });");
+        let cancel = env.render_str(src, minijinja::context! { cancellable => true }).unwrap();
+        assert_eq!(cancel, "if on_event(Event::TextEnd { span: self.span() }).is_break() { self.halt(); }");
+    }
 }
@@ -18,7 +18,9 @@
 pub mod engine;
 pub mod literals;
 
+use super::rust_pushdown::{self, PdOptions};
 use crate::charclass;
+use crate::diagnostic::Diagnostic;
 use crate::ir::*;
 use crate::ir_builder::tokenize_call_args;
 use crate::lexer::re;
@@ -27,25 +29,56 @@ use serde_json::{json, Map, Value};
 /// Unicode character classes that require the unicode-xid crate.
 const UNICODE_CLASSES: &[&str] = &["xid_start", "xid_cont", "xlbl_start", "xlbl_cont"];
 
-#[derive(Debug, Clone)]
+/// Names a grammar local or param must not take: bare identifiers bound in
+/// every generated `parse_<fn>` beside them (`on_event`, the multi-state
+/// `state`, BRACKET `start_span`, the `Some(b)` dispatch binding). `Parser`
+/// fields are only reached through `self.`, so they stay free. Checked by
+/// `validate` against `infer_locals` / `infer_param_types` output.
+pub const RESERVED: &[&str] = &["self", "on_event", "state", "start_span", "b"];
+
+#[derive(Debug, Clone, Default)]
 pub struct Options {
     pub trace: bool,
+    /// Also emit `StreamingParser` and `Parser::events()`, backed by the
+    /// pushdown machine nested as `pub mod pushdown`. Off by default: it
+    /// roughly triples the output, and grammars the machine cannot express
+    /// (E0406) then fail to generate at all.
     pub streaming: bool,
-}
-
-impl Default for Options {
-    fn default() -> Self {
-        Options { trace: false, streaming: true }
-    }
+    /// Callbacks return `ControlFlow<()>`; `Break` stops the parse (also
+    /// passed to the nested pushdown machine).
+    pub cancellable: bool,
 }
 
 /// Generate Rust parser source from the IR (Ruby: Generator#generate):
-/// build the context, render the minijinja templates, post-process.
-pub fn generate(ir: &ParserIR, opts: &Options) -> Result<String, minijinja::Error> {
+/// build the context, render the minijinja templates, post-process. A
+/// template error is E0404; with `streaming`, a grammar the pushdown
+/// machine cannot express is its E0406.
+pub fn generate(ir: &ParserIR, opts: &Options) -> Result<String, Diagnostic> {
     let ctx = build_context(ir, opts);
-    let env = engine::make_env()?;
-    let rendered = env.get_template("parser.j2")?.render(&ctx)?;
-    Ok(engine::post_process(&rendered))
+    let rendered = engine::make_env()
+        .and_then(|env| env.get_template("parser.j2")?.render(&ctx))
+        .map_err(|e| Diagnostic::error("E0404", format!("template error: {e:#}")))?;
+    let mut out = engine::post_process(&rendered);
+    if opts.streaming {
+        // `StreamingParser` wraps the pushdown machine, nested as
+        // `pushdown` so it reaches this module's event types via `super`.
+        let pd_opts = PdOptions {
+            event_path: "super".to_string(),
+            trace: opts.trace,
+            cancellable: opts.cancellable,
+            ..Default::default()
+        };
+        out.push_str("\npub mod pushdown {\n");
+        for line in rust_pushdown::generate(ir, &pd_opts)?.lines() {
+            if !line.is_empty() {
+                out.push_str("    ");
+                out.push_str(line);
+            }
+            out.push('\n');
+        }
+        out.push_str("}\n");
+    }
+    Ok(out)
 }
 
 /// Build the full template context (Ruby: Generator#build_context).
@@ -55,7 +88,7 @@ pub fn build_context(ir: &ParserIR, opts: &Options) -> Value {
 
     let saved_slots = collect_saved_slots(&functions_data);
 
-    json!({
+    let mut ctx = json!({
         "parser": ir.name,
         "entry_point": ir.entry_point,
         "saved_slots": saved_slots,
@@ -77,7 +110,12 @@ pub fn build_context(ir: &ParserIR, opts: &Options) -> Value {
         "uses_nl": usage.nl,
         "max_scan_arity": usage.max_scan_arity,
         "streaming": opts.streaming,
-    })
+    });
+    // Only when set, so the default context stays identical to Ruby's.
+    if opts.cancellable {
+        ctx["cancellable"] = json!(true);
+    }
+    ctx
 }
 
 fn type_to_value(t: &TypeInfo) -> Value {
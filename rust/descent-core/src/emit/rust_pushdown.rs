@@ -14,7 +14,8 @@
 //!   `return` emits (type-driven) and pops; `->[c]` (advance_to) gets its
 //!   own state so a chunk boundary mid-scan resumes idempotently; a
 //!   mid-sequence `->` past the buffer end becomes `pending_skip`, drained
-//!   when bytes arrive.
+//!   when bytes arrive — unless a later command reads the position, in
+//!   which case the `->` gets its own state and suspends for the byte.
 //! - Capture (mark/term/prepend) stays contiguous by design: the parser
 //!   owns an accumulation buffer and drains only bytes no capture can still
 //!   reference, so `mark..pos` never spans a seam and `TERM(-1)` needs no
@@ -29,11 +30,45 @@
 //! re-emission are owned. Delivery contract: a borrowed event is valid
 //! only during the callback that receives it (enforced by the HRTB
 //! `for<'e> FnMut(StreamEvent<'e>)` bound — nothing can outlive the call
-//! without an explicit copy). Deliberate remaining limit: no `--trace`
-//! plumbing. The generated module imports `ParseErrorCode` / `StreamEvent`
-//! / `ParseResult` from the sibling recursive module so the two backends
-//! interoperate.
-
+//! without an explicit copy). The generated module imports
+//! `ParseErrorCode` / `StreamEvent` / `ParseResult` from the sibling
+//! recursive module so the two backends interoperate; the recursive
+//! backend's `streaming` output nests this module as `pushdown` (event path
+//! `super`) and its `StreamingParser` delegates to it.
+//!
+//! `Events` is the pull-based form for a whole input in hand: an
+//! `Iterator<Item = Event<'a>>` that feeds the input a chunk at a time and
+//! queues only that chunk's events. Content borrowed from the parser's
+//! buffer is re-borrowed from the caller's input (same bytes, by address),
+//! so items outlive the machine without a copy.
+//!
+//! `PdOptions.async_adapter` adds `EventStream`, a `futures::Stream` of
+//! owned events over any `futures::io::AsyncRead` (tokio readers via
+//! `EventStream::from_tokio`). Borrowed events are copied only there, at
+//! the async boundary, because a poll cannot hand out a buffer borrow.
+//!
+//! `PushdownParser::with_limits(max_buffer, max_depth)` bounds memory for
+//! untrusted input: bytes retained across chunks (an unterminated capture
+//! pins everything since its mark) and frame-stack depth. Tripping either
+//! emits `Error(LimitExceeded)`, returns `ParseResult::LimitExceeded { kind,
+//! offset }`, and leaves the parser inert.
+//!
+//! Because the state is plain data, `snapshot()` / `restore()` checkpoint
+//! it, and the generated `IncrementalParser` builds reparse-on-edit on top:
+//! checkpoints every N bytes, resume from the last one before an edit, stop
+//! at the first old checkpoint the new parse re-converges with.
+//!
+//! `PdOptions.trace` instruments the machine on stderr. Lines the recursive
+//! backend also prints (`ENTER`, case dispatch, `EOF`, `UNCONDITIONAL`,
+//! `UNHANDLED`) use its exact format with global offsets; pushdown-only
+//! machinery (frame push/pop, transitions, continuation resumes,
+//! `pending_skip` drains, suspends) is prefixed `TRACE: PD`, so
+//! `grep -v 'TRACE: PD'` yields a trace diffable against the recursive one
+//! (identical when the whole input arrives in one chunk; at a chunk
+//! boundary `ENTER` can show `byte=EOF` for a byte not yet pushed).
+//! `descent-tests/tests/trace.rs` diffs the two over fixture samples.
+
+use crate::diagnostic::Diagnostic;
 use crate::ir::*;
 use crate::lexer::re;
 use std::collections::BTreeSet;
@@ -42,24 +77,45 @@ use std::fmt::Write as _;
 use super::rust::engine::{pascalcase, rust_expr};
 use super::rust::literals::escape_rust_byte;
 
+/// Names a grammar local or param must not take: bare identifiers the
+/// emitted code binds beside them. Locals and params are frame fields next
+/// to `st`, and params are also `enter_<fn>` arguments next to `self` and
+/// `on_event`. Everything else is reached through `self.` or the frame `f.`,
+/// so parser fields (`line`, `pos`, `max_depth`, ...) and `f` itself are
+/// free. Checked by `validate`.
+pub const RESERVED: &[&str] = &["self", "st", "on_event"];
+
 /// Options for pushdown generation.
 #[derive(Debug, Clone)]
 pub struct PdOptions {
     /// Rust path of the sibling recursive module that owns the shared
     /// event/error types (e.g. "crate::parser").
     pub event_path: String,
+    /// Emit `eprintln!` trace lines (see the module docs for the format).
+    pub trace: bool,
+    /// Also emit `EventStream`, the async adapter (needs `futures`; the
+    /// tokio reader shim is behind the consumer's `tokio` feature).
+    pub async_adapter: bool,
+    /// Callbacks return `ControlFlow<()>`; `Break` stops the parser
+    /// (`ParseResult::Stopped`).
+    pub cancellable: bool,
 }
 
 impl Default for PdOptions {
     fn default() -> Self {
-        PdOptions { event_path: "crate::parser".to_string() }
+        PdOptions { event_path: "crate::parser".to_string(), trace: false, async_adapter: false, cancellable: false }
     }
 }
 
-pub fn generate(ir: &ParserIR, opts: &PdOptions) -> String {
+/// Generate the pushdown parser, or E0406 for a construct the machine
+/// cannot express.
+pub fn generate(ir: &ParserIR, opts: &PdOptions) -> Result<String, Diagnostic> {
     let mut g = Gen::new(ir, opts);
     g.run();
-    g.out
+    match g.unsupported {
+        Some(d) => Err(d),
+        None => Ok(g.out),
+    }
 }
 
 // ============================================================================
@@ -133,13 +189,67 @@ struct Gen<'i> {
     /// Continuation states allocated while rendering the current function.
     cont_states: Vec<(String, String)>,
     cont_counter: usize,
+    /// Byte-string literals passed to Bytes params, for the snapshot
+    /// deserializer to map back onto `&'static` values.
+    bytes_literals: BTreeSet<String>,
+    /// The first construct the machine cannot express (E0406).
+    unsupported: Option<Diagnostic>,
 }
 
 const IND: usize = 28;
 
+/// Derives on every type a `ParserSnapshot` holds: cloneable, comparable
+/// (incremental reparse convergence), and
+/// serde-serializable when the consuming crate enables its `serde` feature.
+const SNAPSHOT_DERIVE: &str =
+    "#[derive(Debug, Clone, PartialEq)]\n#[cfg_attr(feature = \"serde\", derive(serde::Serialize, serde::Deserialize))]\n";
+
 impl<'i> Gen<'i> {
     fn new(ir: &'i ParserIR, opts: &'i PdOptions) -> Self {
-        Gen { ir, opts, out: String::new(), cont_states: Vec::new(), cont_counter: 0 }
+        Gen {
+            ir,
+            opts,
+            out: String::new(),
+            cont_states: Vec::new(),
+            cont_counter: 0,
+            bytes_literals: BTreeSet::new(),
+            unsupported: None,
+        }
+    }
+
+    /// Record an unsupported construct; generation finishes but `generate`
+    /// returns the first one.
+    fn unsupported(&mut self, message: String) {
+        self.unsupported.get_or_insert_with(|| Diagnostic::error("E0406", message));
+    }
+
+    /// Return type of the `on_event` callback bound: `ControlFlow<()>`
+    /// under `cancellable`, unit otherwise.
+    fn flow(&self) -> &'static str {
+        if self.opts.cancellable {
+            " -> std::ops::ControlFlow<()>"
+        } else {
+            ""
+        }
+    }
+
+    /// One `on_event(ev)` statement; under `cancellable` a `Break` halts.
+    fn emit(&self, ev: &str) -> String {
+        if self.opts.cancellable {
+            format!("if on_event({ev}).is_break() {{ self.halt(); }}")
+        } else {
+            format!("on_event({ev});")
+        }
+    }
+
+    /// A runtime section with the mode placeholders filled in: `__FLOW__`
+    /// (callback return type) and `__GO__` (what internal sinks return).
+    fn runtime(&self, text: &str) -> String {
+        let go = if self.opts.cancellable { " std::ops::ControlFlow::Continue(())" } else { "" };
+        text.replace("__FLOW__", self.flow())
+            .replace("__GO__", go)
+            .replace("__LATCH__\n", if self.opts.cancellable { LATCH } else { "" })
+            .replace("__LIMIT_EVENT__", &self.emit("StreamEvent::Error { code: ParseErrorCode::LimitExceeded, span: offset..offset }"))
     }
 
     fn type_kind(&self, name: &Option<String>) -> &'i str {
@@ -155,6 +265,33 @@ impl<'i> Gen<'i> {
         FnInfo { func, kind: self.type_kind(&func.return_type), vars }
     }
 
+    /// One generated `eprintln!` trace line (nothing unless `opts.trace`).
+    fn trace(&self, b: &mut String, ind: usize, args: &str) {
+        if self.opts.trace {
+            let _ = writeln!(b, "{:ind$}eprintln!({args});", "");
+        }
+    }
+
+    /// Push the frame back and suspend for more input; `at` names the
+    /// suspension point in the trace.
+    fn suspend(&self, p: &str, at: &str) -> String {
+        let trace = if self.opts.trace {
+            format!(" eprintln!(\"TRACE: PD SUSPEND {at} | depth={{}} pos={{}}\", self.stack.len(), self.base + self.pos);")
+        } else {
+            String::new()
+        };
+        format!("self.stack.push(Frame::{p}(f));{trace} return ParseResult::NeedMoreData;")
+    }
+
+    /// Trace the current frame being popped (it is not pushed back).
+    fn trace_pop(&self, b: &mut String, info: &FnInfo<'i>, ind: usize) {
+        let args = format!(
+            "\"TRACE: PD POP {} | depth={{}} pos={{}}\", self.stack.len(), self.base + self.pos",
+            info.func.name
+        );
+        self.trace(b, ind, &args);
+    }
+
     fn fresh_cont(&mut self) -> String {
         self.cont_counter += 1;
         format!("PdK{}", self.cont_counter)
@@ -177,13 +314,13 @@ impl<'i> Gen<'i> {
              //! event is valid only during the callback that receives it — copy\n\
              //! (`into_owned`) anything that must survive past the callback or\n\
              //! the next `push_chunk`.\n\n\
-             use {event_path}::{{ParseErrorCode, ParseResult, StreamEvent}};\n\n"
+             use {event_path}::{{Event, LimitKind, ParseErrorCode, ParseResult, StreamEvent}};\n\n"
         );
 
         for func in &self.ir.functions {
             self.render_frame_struct(func);
         }
-        let _ = writeln!(self.out, "#[derive(Debug)]\nenum Frame {{");
+        let _ = writeln!(self.out, "{SNAPSHOT_DERIVE}enum Frame {{");
         for func in &self.ir.functions {
             let p = pascalcase(&func.name);
             let _ = writeln!(self.out, "    {p}({p}Frame),");
@@ -210,14 +347,29 @@ impl<'i> Gen<'i> {
         }
         let _ = writeln!(
             self.out,
-            "/// SAVE(slot) captures — owned (content, global span) so a drain or\n/// chunk seam can never invalidate them. Re-emitted (borrowed) by\n/// TypeName(USE_SAVED(slot)); an unsaved slot is empty content @ 0..0.\n#[derive(Debug, Default)]\nstruct SavedSlots {{"
+            "/// SAVE(slot) captures — owned (content, global span) so a drain or\n/// chunk seam can never invalidate them. Re-emitted (borrowed) by\n/// TypeName(USE_SAVED(slot)); an unsaved slot is empty content @ 0..0.\n{SNAPSHOT_DERIVE}#[derive(Default)]\nstruct SavedSlots {{"
         );
         for s in &slots {
             let _ = writeln!(self.out, "    {s}: (Vec<u8>, std::ops::Range<usize>),");
         }
         let _ = writeln!(self.out, "}}\n");
+        let _ = writeln!(
+            self.out,
+            "impl SavedSlots {{\n    /// Re-base captured spans at or after `at` by `delta` (an edit).\n    #[allow(unused_variables)]\n    fn shift(&mut self, at: usize, delta: isize) {{"
+        );
+        for s in &slots {
+            let _ = writeln!(self.out, "        pd_shift_range(&mut self.{s}.1, at, delta);");
+        }
+        let _ = writeln!(self.out, "    }}\n}}\n");
 
-        let _ = write!(self.out, "{}", RUNTIME.replace("__ENTRY__", &ep));
+        let runtime = self.runtime(RUNTIME).replace("__ENTRY__", &ep);
+        self.out.push_str(&runtime);
+        if self.opts.cancellable {
+            self.out.push_str(CANCEL_RUNTIME);
+        }
+        if self.opts.trace {
+            self.out.push_str(TRACE_RUNTIME);
+        }
 
         let mut arms = String::new();
         for func in &self.ir.functions {
@@ -227,26 +379,41 @@ impl<'i> Gen<'i> {
         }
         self.render_keyword_lookups();
 
+        let (mut skip_trace, mut skip_suspend_trace) = (String::new(), String::new());
+        self.trace(
+            &mut skip_trace,
+            20,
+            "\"TRACE: PD SKIP | pending={} byte={} pos={}\", self.pending_skip, Self::trace_byte(self.peek()), self.base + self.pos",
+        );
+        self.trace(
+            &mut skip_suspend_trace,
+            20,
+            "\"TRACE: PD SUSPEND pending_skip | pending={} pos={}\", self.pending_skip, self.base + self.pos",
+        );
+        let flow = self.flow();
         let _ = write!(
             self.out,
             "    /// Drive the machine until it needs more bytes or the stack empties.\n\
              \x20   #[allow(unreachable_code, unused_variables)]\n\
              \x20   fn run<F>(&mut self, on_event: &mut F) -> ParseResult\n\
              \x20   where\n\
-             \x20       F: for<'e> FnMut(StreamEvent<'e>),\n\
+             \x20       F: for<'e> FnMut(StreamEvent<'e>){flow},\n\
              \x20   {{\n\
              \x20       'run: loop {{\n\
              \x20           // Drain pending mid-sequence advances first.\n\
              \x20           while self.pending_skip > 0 {{\n\
-             \x20               if self.pos < self.buf.len() {{\n\
+             \x20               if self.pos < self.buf.len() {{\n{skip_trace}\
              \x20                   self.advance();\n\
              \x20                   self.pending_skip -= 1;\n\
              \x20               }} else if self.finished {{\n\
              \x20                   self.pending_skip = 0;\n\
-             \x20               }} else {{\n\
+             \x20               }} else {{\n{skip_suspend_trace}\
              \x20                   return ParseResult::NeedMoreData;\n\
              \x20               }}\n\
              \x20           }}\n\
+             \x20           if self.stack.len() > self.max_depth {{\n\
+             \x20               return self.exceed(LimitKind::Depth, on_event);\n\
+             \x20           }}\n\
              \x20           let Some(frame) = self.stack.pop() else {{\n\
              \x20               return ParseResult::Complete;\n\
              \x20           }};\n\
@@ -258,17 +425,67 @@ impl<'i> Gen<'i> {
              \x20   }}\n\
              }}\n"
         );
+        self.render_static_bytes_serde();
+        let tail = self.runtime(INCREMENTAL_RUNTIME) + &self.runtime(EVENTS_RUNTIME);
+        self.out.push_str(&tail);
+        if self.opts.async_adapter {
+            let adapter = self.runtime(ASYNC_RUNTIME);
+            self.out.push_str(&adapter);
+        }
+    }
+
+    /// Bytes params are `&'static [u8]` grammar literals: a deserialized
+    /// snapshot maps each value back onto the literal it came from.
+    fn render_static_bytes_serde(&mut self) {
+        let has_bytes = self
+            .ir
+            .functions
+            .iter()
+            .any(|f| f.param_types.iter().any(|(_, t)| *t == ParamType::Bytes));
+        if !has_bytes {
+            return;
+        }
+        let lits: Vec<&str> = self.bytes_literals.iter().map(String::as_str).collect();
+        let _ = write!(
+            self.out,
+            "\n/// A Bytes param. Aliased so serde's derive does not infer a\n\
+             /// `'de: 'static` borrow for frames holding one.\n\
+             type PdBytes = &'static [u8];\n\n\
+             /// Every byte-string literal a Bytes param can hold.\n\
+             #[cfg(feature = \"serde\")]\n\
+             static PD_BYTES_LITERALS: &[&[u8]] = &[{}];\n\n\
+             /// Serde for `&'static [u8]` frame fields: written as bytes, read back\n\
+             /// as the matching grammar literal.\n\
+             #[cfg(feature = \"serde\")]\n\
+             mod pd_static_bytes {{\n\
+             \x20   pub fn serialize<S: serde::Serializer>(v: &&'static [u8], s: S) -> Result<S::Ok, S::Error> {{\n\
+             \x20       serde::Serialize::serialize(*v, s)\n\
+             \x20   }}\n\n\
+             \x20   pub fn deserialize<'de, D: serde::Deserializer<'de>>(d: D) -> Result<&'static [u8], D::Error> {{\n\
+             \x20       let v: Vec<u8> = serde::Deserialize::deserialize(d)?;\n\
+             \x20       super::PD_BYTES_LITERALS\n\
+             \x20           .iter()\n\
+             \x20           .copied()\n\
+             \x20           .find(|l| *l == &v[..])\n\
+             \x20           .ok_or_else(|| serde::de::Error::custom(\"byte-string param is not a grammar literal\"))\n\
+             \x20   }}\n\
+             }}\n",
+            lits.join(", ")
+        );
     }
 
     fn render_frame_struct(&mut self, func: &Function) {
         let p = pascalcase(&func.name);
-        let _ = writeln!(self.out, "#[derive(Debug)]\nstruct {p}Frame {{");
+        let _ = writeln!(self.out, "{SNAPSHOT_DERIVE}struct {p}Frame {{");
         let _ = writeln!(self.out, "    st: {p}St,");
         for (name, ty) in &func.param_types {
             let rty = match ty {
                 ParamType::I32 => "i32",
                 ParamType::Byte => "u8",
-                ParamType::Bytes => "&'static [u8]",
+                ParamType::Bytes => {
+                    let _ = writeln!(self.out, "    #[cfg_attr(feature = \"serde\", serde(with = \"pd_static_bytes\"))]");
+                    "PdBytes"
+                }
             };
             let _ = writeln!(self.out, "    {name}: {rty},");
         }
@@ -295,13 +512,22 @@ impl<'i> Gen<'i> {
         }
         let _ = writeln!(
             self.out,
-            "    fn enter_{name}<F>(&mut self, {params_sig}on_event: &mut F)\n    where\n        F: for<'e> FnMut(StreamEvent<'e>),\n    {{",
-            name = func.name
+            "    fn enter_{name}<F>(&mut self, {params_sig}on_event: &mut F)\n    where\n        F: for<'e> FnMut(StreamEvent<'e>){flow},\n    {{",
+            name = func.name,
+            flow = self.flow()
         );
+        let mut enter = String::new();
+        let args = format!(
+            "\"TRACE: L{} ENTER {} | byte={{}} pos={{}}\", Self::trace_byte(self.peek()), self.base + self.pos",
+            func.lineno, func.name
+        );
+        self.trace(&mut enter, 8, &args);
+        self.out.push_str(&enter);
         match info.kind {
             "bracket" => {
                 let t = func.return_type.as_deref().unwrap();
-                let _ = writeln!(self.out, "        on_event(StreamEvent::{t}Start {{ span: self.gspan() }});");
+                let e = self.emit(&format!("StreamEvent::{t}Start {{ span: self.gspan() }}"));
+                let _ = writeln!(self.out, "        {e}");
             }
             "content" => {
                 let _ = writeln!(self.out, "        self.mark();");
@@ -325,6 +551,10 @@ impl<'i> Gen<'i> {
             let _ = writeln!(self.out, "            {l}: {init},");
         }
         let _ = writeln!(self.out, "        }}));");
+        let mut push = String::new();
+        let args = format!("\"TRACE: PD PUSH {} | depth={{}}\", self.stack.len()", func.name);
+        self.trace(&mut push, 8, &args);
+        self.out.push_str(&push);
         let _ = writeln!(self.out, "    }}\n");
     }
 
@@ -338,15 +568,13 @@ impl<'i> Gen<'i> {
             let _ = writeln!(self.out, "];\n\n#[allow(unused_variables, dead_code)]\nimpl PushdownParser {{");
             let _ = writeln!(
                 self.out,
-                "    fn lookup_{name}<F>(&mut self, on_event: &mut F) -> bool\n    where\n        F: for<'e> FnMut(StreamEvent<'e>),\n    {{\n        let (content, span) = self.take_capture();\n        let Some(&(_, id)) = {const_name}.iter().find(|(k, _)| *k == &content[..]) else {{ return false; }};\n        match id {{",
-                name = kw.name
+                "    fn lookup_{name}<F>(&mut self, on_event: &mut F) -> bool\n    where\n        F: for<'e> FnMut(StreamEvent<'e>){flow},\n    {{\n        let (content, span) = self.take_capture();\n        let Some(&(_, id)) = {const_name}.iter().find(|(k, _)| *k == &content[..]) else {{ return false; }};\n        match id {{",
+                name = kw.name,
+                flow = self.flow()
             );
             for (i, m) in kw.mappings.iter().enumerate() {
-                let _ = writeln!(
-                    self.out,
-                    "            {i} => on_event(StreamEvent::{t} {{ content, span }}),",
-                    t = m.event_type
-                );
+                let e = self.emit(&format!("StreamEvent::{t} {{ content, span }}", t = m.event_type));
+                let _ = writeln!(self.out, "            {i} => {},", e.trim_end_matches(';'));
             }
             let _ = writeln!(self.out, "            _ => unreachable!(),\n        }}\n        true\n    }}\n");
         }
@@ -385,24 +613,27 @@ impl<'i> Gen<'i> {
         }
 
         // Per-function state enum, inserted before the Frame enum.
-        let mut st_enum = format!("#[derive(Debug, Clone, Copy, PartialEq)]\nenum {p}St {{ ");
+        let mut st_enum = format!("{SNAPSHOT_DERIVE}#[derive(Copy)]\nenum {p}St {{ ");
         for (name, _) in &bodies {
             let _ = write!(st_enum, "{name}, ");
         }
         st_enum.push_str("}\n\n");
-        self.out = self.out.replacen(
-            "#[derive(Debug)]\nenum Frame {",
-            &format!("{st_enum}#[derive(Debug)]\nenum Frame {{"),
-            1,
-        );
+        let frame_head = format!("{SNAPSHOT_DERIVE}enum Frame {{");
+        self.out = self.out.replacen(&frame_head, &format!("{st_enum}{frame_head}"), 1);
 
         // In-arm state loop: state hops (`continue 'st`) stay inside this
         // frame's arm — no stack pop/push, no Frame-variant re-match. Only
         // calls, returns, and suspensions go back through the trampoline.
         // The pending_skip guard bails to the trampoline top (which owns
         // the drain-or-suspend decision) exactly as the pre-loop code did.
+        let mut resume = String::new();
+        let args = format!(
+            "\"TRACE: PD RESUME {}:{{:?}} | depth={{}} pos={{}}\", f.st, self.stack.len() + 1, self.base + self.pos",
+            func.name
+        );
+        self.trace(&mut resume, 20, &args);
         let mut arm = format!(
-            "                Frame::{p}(mut f) => {{\n                    'st: loop {{\n                    if self.pending_skip > 0 {{ self.stack.push(Frame::{p}(f)); continue 'run; }}\n                    match f.st {{\n"
+            "                Frame::{p}(mut f) => {{\n{resume}                    'st: loop {{\n                    if self.pending_skip > 0 {{ self.stack.push(Frame::{p}(f)); continue 'run; }}\n                    match f.st {{\n"
         );
         for (name, body) in &bodies {
             let _ = write!(
@@ -419,8 +650,21 @@ impl<'i> Gen<'i> {
         let mut b = String::new();
         let home = pascalcase(&state.name);
 
-        if state.is_unconditional {
-            let cmds = state.cases[0].commands.clone();
+        // Only multi-state functions render an unconditional state as such
+        // (the recursive template's single-state loop has no such branch,
+        // and its scannable check comes first), so the trace names the same
+        // line either way.
+        if state.is_unconditional && info.func.states.len() > 1 && !state.scannable() {
+            let kase = &state.cases[0];
+            let args = format!(
+                "\"TRACE: L{} {}:{}{} UNCONDITIONAL | term={{}} pos={{}}\", self.trace_content(), self.base + self.pos",
+                kase.lineno,
+                info.func.name,
+                state.name,
+                substate(kase)
+            );
+            self.trace(&mut b, IND, &args);
+            let cmds = kase.commands.clone();
             self.render_seq(&mut b, &cmds, info, p, IND, &SeqEnd::Redispatch, &home);
             return b;
         }
@@ -441,7 +685,7 @@ impl<'i> Gen<'i> {
                 if case.is_default() {
                     continue;
                 }
-                self.render_case(&mut b, case, info, p, IND + 4, &home);
+                self.render_case(&mut b, case, state, info, p, IND + 4);
             }
             if state.newline_injected {
                 let _ = writeln!(
@@ -454,8 +698,9 @@ impl<'i> Gen<'i> {
             let _ = writeln!(b, "{:i$}None => {{", "", i = IND + 4);
             let _ = writeln!(
                 b,
-                "{:i$}if !self.finished {{ self.stack.push(Frame::{p}(f)); return ParseResult::NeedMoreData; }}",
+                "{:i$}if !self.finished {{ {} }}",
                 "",
+                self.suspend(p, &format!("{}:{}", info.func.name, state.name)),
                 i = IND + 8
             );
             self.render_eof(&mut b, state, info, p, IND + 8, &home);
@@ -479,8 +724,9 @@ impl<'i> Gen<'i> {
             let _ = writeln!(b, "{:IND$}if self.pos >= self.buf.len() {{", "");
             let _ = writeln!(
                 b,
-                "{:i$}if !self.finished {{ self.stack.push(Frame::{p}(f)); return ParseResult::NeedMoreData; }}",
+                "{:i$}if !self.finished {{ {} }}",
                 "",
+                self.suspend(p, &format!("{}:{}", info.func.name, state.name)),
                 i = IND + 4
             );
             self.render_eof(&mut b, state, info, p, IND + 4, &home);
@@ -488,6 +734,7 @@ impl<'i> Gen<'i> {
         }
 
         if state.cases.len() == 1 && state.cases[0].is_default() {
+            self.trace_case(&mut b, &state.cases[0], state, info, IND);
             let cmds = state.cases[0].commands.clone();
             self.render_seq(&mut b, &cmds, info, p, IND, &SeqEnd::Redispatch, &home);
             return b;
@@ -499,10 +746,15 @@ impl<'i> Gen<'i> {
             if case.is_default() {
                 saw_default = true;
             }
-            self.render_case(&mut b, case, info, p, IND + 4, &home);
+            self.render_case(&mut b, case, state, info, p, IND + 4);
         }
         if !saw_default {
             let mut ret_b = String::new();
+            let args = format!(
+                "\"TRACE: {}:{} UNHANDLED | byte={{}} pos={{}}\", Self::trace_byte(self.peek()), self.base + self.pos",
+                info.func.name, state.name
+            );
+            self.trace(&mut ret_b, IND + 8, &args);
             self.render_seq(
                 &mut ret_b,
                 &[Command::new("return", serde_json::json!({}))],
@@ -525,6 +777,11 @@ impl<'i> Gen<'i> {
     /// else the type-default with the delimited **force-unwind** (content +
     /// `Warning(Unclosed<Construct>)` + End) for delimited functions.
     fn render_eof(&mut self, b: &mut String, state: &State, info: &FnInfo<'i>, p: &str, ind: usize, home: &str) {
+        let args = format!(
+            "\"TRACE: L{} {}:{} EOF | term={{}} pos={{}}\", self.trace_content(), self.base + self.pos",
+            state.lineno, info.func.name, state.name
+        );
+        self.trace(b, ind, &args);
         let handler: Option<&Vec<Command>> = state
             .eof_handler
             .as_ref()
@@ -548,41 +805,48 @@ impl<'i> Gen<'i> {
         }
         if info.kind == "content" {
             let t = info.func.return_type.as_deref().unwrap();
-            let _ = writeln!(
-                b,
-                "{:ind$}{{ let (c, sp) = self.take_capture(); on_event(StreamEvent::{t} {{ content: c, span: sp }}); }}",
-                ""
-            );
+            let e = self.emit(&format!("StreamEvent::{t} {{ content: c, span: sp }}"));
+            let _ = writeln!(b, "{:ind$}{{ let (c, sp) = self.take_capture(); {e} }}", "");
         }
         if let Some(code) = info.func.delimited_code.as_deref() {
             // Delimited force-unwind: keep-content warning, then End for brackets.
-            let _ = writeln!(
-                b,
-                "{:ind$}on_event(StreamEvent::Warning {{ content: std::borrow::Cow::Borrowed(&b\"{code}\"[..]), span: self.gspan() }});",
-                ""
-            );
+            let e = self.emit(&format!("StreamEvent::Warning {{ content: std::borrow::Cow::Borrowed(&b\"{code}\"[..]), span: self.gspan() }}"));
+            let _ = writeln!(b, "{:ind$}{e}", "");
             if info.kind == "bracket" {
                 let t = info.func.return_type.as_deref().unwrap();
-                let _ = writeln!(b, "{:ind$}on_event(StreamEvent::{t}End {{ span: self.gspan() }});", "");
+                let e = self.emit(&format!("StreamEvent::{t}End {{ span: self.gspan() }}"));
+                let _ = writeln!(b, "{:ind$}{e}", "");
             }
         } else if info.func.expects_char.is_some() {
             let t = info.func.return_type.as_deref().unwrap_or("");
-            let _ = writeln!(
-                b,
-                "{:ind$}on_event(StreamEvent::Error {{ code: ParseErrorCode::Unclosed{t}, span: self.gspan() }});",
-                ""
-            );
+            let e = self.emit(&format!("StreamEvent::Error {{ code: ParseErrorCode::Unclosed{t}, span: self.gspan() }}"));
+            let _ = writeln!(b, "{:ind$}{e}", "");
         } else if info.kind == "bracket" {
             let t = info.func.return_type.as_deref().unwrap();
-            let _ = writeln!(b, "{:ind$}on_event(StreamEvent::{t}End {{ span: self.gspan() }});", "");
+            let e = self.emit(&format!("StreamEvent::{t}End {{ span: self.gspan() }}"));
+            let _ = writeln!(b, "{:ind$}{e}", "");
         }
         if info.kind == "internal" {
             let _ = writeln!(b, "{:ind$}self.ret = 0;", "");
         }
+        self.trace_pop(b, info, ind);
         let _ = writeln!(b, "{:ind$}continue 'run;", "");
     }
 
-    fn render_case(&mut self, b: &mut String, case: &Case, info: &FnInfo<'i>, p: &str, ind: usize, home: &str) {
+    /// The recursive backend's per-case dispatch trace line.
+    fn trace_case(&self, b: &mut String, case: &Case, state: &State, info: &FnInfo<'i>, ind: usize) {
+        let args = format!(
+            "\"TRACE: L{} {}:{}{} | byte={{}} term={{}} pos={{}}\", Self::trace_byte(self.peek()), self.trace_content(), self.base + self.pos",
+            case.lineno,
+            info.func.name,
+            state.name,
+            substate(case)
+        );
+        self.trace(b, ind, &args);
+    }
+
+    fn render_case(&mut self, b: &mut String, case: &Case, state: &State, info: &FnInfo<'i>, p: &str, ind: usize) {
+        let home = pascalcase(&state.name);
         let pat = if case.is_default() {
             "_".to_string()
         } else if let Some(cond) = &case.condition {
@@ -604,8 +868,9 @@ impl<'i> Gen<'i> {
             "Some(_)".to_string()
         };
         let _ = writeln!(b, "{:ind$}{pat} => {{", "");
+        self.trace_case(b, case, state, info, ind + 4);
         let cmds = case.commands.clone();
-        self.render_seq(b, &cmds, info, p, ind + 4, &SeqEnd::Redispatch, home);
+        self.render_seq(b, &cmds, info, p, ind + 4, &SeqEnd::Redispatch, &home);
         let _ = writeln!(b, "{:ind$}}}", "");
     }
 
@@ -638,7 +903,27 @@ impl<'i> Gen<'i> {
             let rest = &cmds[i + 1..];
             match cmd.ctype.as_str() {
                 "advance" => {
-                    let _ = writeln!(b, "{:ind$}self.advance_or_pend();", "");
+                    // Pending the byte is only sound when nothing later in
+                    // the sequence reads the position (spans, MARK/TERM,
+                    // PREV, calls); otherwise the advance gets its own
+                    // state and waits for the byte like a dispatch would.
+                    if rest.iter().all(|c| matches!(c.ctype.as_str(), "advance" | "transition")) {
+                        let _ = writeln!(b, "{:ind$}self.advance_or_pend();", "");
+                        continue;
+                    }
+                    let k = self.fresh_cont();
+                    let mut kb = String::new();
+                    let _ = writeln!(
+                        kb,
+                        "{:IND$}if self.pos >= self.buf.len() && !self.finished {{ {} }}",
+                        "",
+                        self.suspend(p, &format!("{}:{k}", info.func.name))
+                    );
+                    let _ = writeln!(kb, "{:IND$}self.advance();", "");
+                    self.render_seq(&mut kb, rest, info, p, IND, end, home);
+                    self.cont_states.push((k.clone(), kb));
+                    self.apply_end(b, &SeqEnd::Goto(k), p, ind);
+                    return;
                 }
                 "advance_to" => {
                     // Own state for idempotent chunk-boundary resume: the
@@ -653,8 +938,9 @@ impl<'i> Gen<'i> {
                     let mut kb = String::new();
                     let _ = writeln!(
                         kb,
-                        "{:IND$}if self.scan_to{n}({args}).is_none() && !self.finished {{ self.stack.push(Frame::{p}(f)); return ParseResult::NeedMoreData; }}",
+                        "{:IND$}if self.scan_to{n}({args}).is_none() && !self.finished {{ {} }}",
                         "",
+                        self.suspend(p, &format!("{}:{k_scan}", info.func.name)),
                         args = chars.join(", ")
                     );
                     self.apply_end(&mut kb, &SeqEnd::Goto(k_rest.clone()), p, IND);
@@ -710,7 +996,13 @@ impl<'i> Gen<'i> {
                         _ => "=",
                     };
                     if let Some(caps) = re(r"^/(\w+)(?:\(([^)]*)\))?\s*$").captures(expr.trim()) {
-                        assert_eq!(op, "=", "call-assign must be a plain assign");
+                        if op != "=" {
+                            self.unsupported(format!(
+                                "pushdown backend: '{var} {op} {expr}' in function '{}' (a call result can only be assigned with '=')",
+                                info.func.name
+                            ));
+                            continue;
+                        }
                         let callee = caps.get(1).unwrap().as_str().to_string();
                         let args = caps.get(2).map(|m| m.as_str().to_string()).unwrap_or_default();
                         self.split_call(b, &callee, &args, rest, info, p, ind, Some(var), end, home);
@@ -725,11 +1017,8 @@ impl<'i> Gen<'i> {
                             .filter(|s| !s.is_empty())
                             .map(pascalcase)
                             .unwrap_or_else(|| "UnexpectedChar".to_string());
-                        let _ = writeln!(
-                            b,
-                            "{:ind$}on_event(StreamEvent::Error {{ code: ParseErrorCode::{code}, span: self.gspan() }});",
-                            ""
-                        );
+                        let e = self.emit(&format!("StreamEvent::Error {{ code: ParseErrorCode::{code}, span: self.gspan() }}"));
+                        let _ = writeln!(b, "{:ind$}{e}", "");
                         continue;
                     }
                     let callee = cmd.arg_str("name").unwrap_or("").to_string();
@@ -746,17 +1035,31 @@ impl<'i> Gen<'i> {
                     self.cont_states.push((k.clone(), kb));
                     let _ = writeln!(b, "{:ind$}f.st = {p}St::{k};", "");
                     let _ = writeln!(b, "{:ind$}let matched = self.lookup_{kw}(on_event);", "");
-                    let _ = writeln!(b, "{:ind$}self.stack.push(Frame::{p}(f));", "");
-                    if let Some(kwd) = kwd {
-                        if let Some(fb) = &kwd.fallback_func {
-                            // Corpus fallbacks are arg-less (emit_bare_value);
-                            // frame-reading args would need pre-binding here.
-                            let args = kwd.fallback_args.clone().unwrap_or_default();
-                            assert!(args.trim().is_empty(), "keyword fallback args unsupported in pushdown backend");
-                            let _ = writeln!(b, "{:ind$}if !matched {{", "");
-                            let _ = writeln!(b, "{:i$}self.enter_{fb}(on_event);", "", i = ind + 4);
-                            let _ = writeln!(b, "{:ind$}}}", "");
+                    // Fallback args may read frame fields: bind them before
+                    // the frame moves, as `split_call` does.
+                    let fallback = kwd.as_ref().and_then(|kwd| {
+                        let fb = kwd.fallback_func.clone()?;
+                        let args = kwd.fallback_args.clone().unwrap_or_default();
+                        let callee_fn = self.ir.functions.iter().find(|x| x.name == fb);
+                        let n = callee_fn.map_or(0, |c| c.params.len());
+                        let names: Vec<String> = (0..n).map(|i| format!("pd_a{i}")).collect();
+                        if n > 0 {
+                            let call_args = render_call_args_typed(&args, &info.vars, callee_fn, &mut self.bytes_literals);
+                            let _ = writeln!(b, "{:ind$}let ({},) = ({call_args});", "", names.join(", "));
                         }
+                        Some((fb, names))
+                    });
+                    let _ = writeln!(b, "{:ind$}self.stack.push(Frame::{p}(f));", "");
+                    if let Some((fb, names)) = fallback {
+                        let _ = writeln!(b, "{:ind$}if !matched {{", "");
+                        let _ = writeln!(
+                            b,
+                            "{:i$}self.enter_{fb}({}on_event);",
+                            "",
+                            names.iter().map(|x| format!("{x}, ")).collect::<String>(),
+                            i = ind + 4
+                        );
+                        let _ = writeln!(b, "{:ind$}}}", "");
                     }
                     let _ = writeln!(b, "{:ind$}continue 'run;", "");
                     return;
@@ -767,14 +1070,18 @@ impl<'i> Gen<'i> {
                 }
                 "error" => {
                     let code = pascalcase(cmd.arg_str("value").unwrap_or("unexpected_char"));
-                    let _ = writeln!(
-                        b,
-                        "{:ind$}on_event(StreamEvent::Error {{ code: ParseErrorCode::{code}, span: self.gspan() }});",
-                        ""
-                    );
+                    let e = self.emit(&format!("StreamEvent::Error {{ code: ParseErrorCode::{code}, span: self.gspan() }}"));
+                    let _ = writeln!(b, "{:ind$}{e}", "");
                 }
                 "transition" => {
                     let target = cmd.arg_str("value").unwrap_or("").replace(':', "");
+                    if !target.is_empty() {
+                        let args = format!(
+                            "\"TRACE: PD TRANSITION {}:{target} | pos={{}}\", self.base + self.pos",
+                            info.func.name
+                        );
+                        self.trace(b, ind, &args);
+                    }
                     if target.is_empty() {
                         // `|>>` self-loop: the GRAMMAR state, which may not
                         // be the (continuation) state we're rendered into.
@@ -816,7 +1123,12 @@ impl<'i> Gen<'i> {
                     }
                     return;
                 }
-                other => panic!("pushdown backend: unhandled command type '{other}'"),
+                other => {
+                    self.unsupported(format!(
+                        "pushdown backend: command '{other}' in function '{}' is not supported",
+                        info.func.name
+                    ));
+                }
             }
         }
         self.apply_end(b, end, p, ind);
@@ -844,7 +1156,7 @@ impl<'i> Gen<'i> {
         self.render_seq(&mut kb, rest, info, p, IND, end, home);
         self.cont_states.push((k.clone(), kb));
         let callee_fn = self.ir.functions.iter().find(|x| x.name == callee);
-        let call_args = render_call_args_typed(args, &info.vars, callee_fn);
+        let call_args = render_call_args_typed(args, &info.vars, callee_fn, &mut self.bytes_literals);
         let _ = writeln!(b, "{:ind$}f.st = {p}St::{k};", "");
         if call_args.is_empty() {
             let _ = writeln!(b, "{:ind$}self.stack.push(Frame::{p}(f));", "");
@@ -874,58 +1186,42 @@ impl<'i> Gen<'i> {
         match cmd.ctype.as_str() {
             "emit" => {
                 let t = cmd.arg_str("value").unwrap_or("");
-                let _ = writeln!(
-                    b,
-                    "{:ind$}{{ let (c, sp) = self.take_capture(); on_event(StreamEvent::{t} {{ content: c, span: sp }}); }}",
-                    ""
-                );
+                let e = self.emit(&format!("StreamEvent::{t} {{ content: c, span: sp }}"));
+                let _ = writeln!(b, "{:ind$}{{ let (c, sp) = self.take_capture(); {e} }}", "");
             }
             "inline_emit_bare" => {
                 let t = cmd.arg_str("type").unwrap_or("");
-                let _ = writeln!(
-                    b,
-                    "{:ind$}on_event(StreamEvent::{t} {{ content: std::borrow::Cow::Borrowed(&b\"\"[..]), span: self.gspan() }});",
-                    ""
-                );
+                let e = self.emit(&format!("StreamEvent::{t} {{ content: std::borrow::Cow::Borrowed(&b\"\"[..]), span: self.gspan() }}"));
+                let _ = writeln!(b, "{:ind$}{e}", "");
             }
             "inline_emit_mark" => {
                 let t = cmd.arg_str("type").unwrap_or("");
-                let _ = writeln!(
-                    b,
-                    "{:ind$}{{ let (c, sp) = self.take_capture(); on_event(StreamEvent::{t} {{ content: c, span: sp }}); }}",
-                    ""
-                );
+                let e = self.emit(&format!("StreamEvent::{t} {{ content: c, span: sp }}"));
+                let _ = writeln!(b, "{:ind$}{{ let (c, sp) = self.take_capture(); {e} }}", "");
             }
             "inline_emit_saved" => {
                 // Unconditional like the recursive backend's saved_{slot}
                 // range (an unsaved slot is empty content @ 0..0).
                 let t = cmd.arg_str("type").unwrap_or("");
                 let slot = cmd.arg_str("slot").unwrap_or("");
-                let _ = writeln!(
-                    b,
-                    "{:ind$}on_event(StreamEvent::{t} {{ content: std::borrow::Cow::Borrowed(&self.saved.{slot}.0), span: self.saved.{slot}.1.clone() }});",
-                    ""
-                );
+                let e = self.emit(&format!("StreamEvent::{t} {{ content: std::borrow::Cow::Borrowed(&self.saved.{slot}.0), span: self.saved.{slot}.1.clone() }}"));
+                let _ = writeln!(b, "{:ind$}{e}", "");
             }
             "inline_emit_param" => {
                 let t = cmd.arg_str("type").unwrap_or("");
                 let p = cmd.arg_str("param_ref").unwrap_or("");
-                let _ = writeln!(
-                    b,
-                    "{:ind$}on_event(StreamEvent::{t} {{ content: std::borrow::Cow::Borrowed(f.{p}), span: self.gspan() }});",
-                    ""
-                );
+                let e = self.emit(&format!("StreamEvent::{t} {{ content: std::borrow::Cow::Borrowed(f.{p}), span: self.gspan() }}"));
+                let _ = writeln!(b, "{:ind$}{e}", "");
             }
             "inline_emit_literal" => {
                 let t = cmd.arg_str("type").unwrap_or("");
                 let raw = cmd.arg_str("literal").unwrap_or("");
                 let lit = if raw.starts_with('\'') { raw.trim_matches('\'') } else { raw };
-                let _ = writeln!(
-                    b,
-                    "{:ind$}on_event(StreamEvent::{t} {{ content: std::borrow::Cow::Borrowed(&b\"{}\"[..]), span: self.gspan() }});",
-                    "",
+                let e = self.emit(&format!(
+                    "StreamEvent::{t} {{ content: std::borrow::Cow::Borrowed(&b\"{}\"[..]), span: self.gspan() }}",
                     esc_str(lit)
-                );
+                ));
+                let _ = writeln!(b, "{:ind$}{e}", "");
             }
             _ => unreachable!(),
         }
@@ -936,35 +1232,30 @@ impl<'i> Gen<'i> {
     fn render_return(&mut self, b: &mut String, cmd: &Command, info: &FnInfo<'i>, ind: usize) {
         if let Some(rv) = cmd.arg_str("return_value") {
             let _ = writeln!(b, "{:ind$}self.ret = {};", "", pd_expr(rv, &info.vars));
+            self.trace_pop(b, info, ind);
             let _ = writeln!(b, "{:ind$}continue 'run;", "");
             return;
         }
         if let Some(t) = cmd.arg_str("emit_type") {
             match cmd.arg_str("emit_mode") {
                 Some("mark") => {
-                    let _ = writeln!(
-                        b,
-                        "{:ind$}{{ let (c, sp) = self.take_capture(); on_event(StreamEvent::{t} {{ content: c, span: sp }}); }}",
-                        ""
-                    );
+                    let e = self.emit(&format!("StreamEvent::{t} {{ content: c, span: sp }}"));
+                    let _ = writeln!(b, "{:ind$}{{ let (c, sp) = self.take_capture(); {e} }}", "");
                 }
                 Some("literal") => {
                     let lit = cmd.arg_str("literal").unwrap_or("");
-                    let _ = writeln!(
-                        b,
-                        "{:ind$}on_event(StreamEvent::{t} {{ content: std::borrow::Cow::Borrowed(&b\"{}\"[..]), span: self.gspan() }});",
-                        "",
+                    let e = self.emit(&format!(
+                        "StreamEvent::{t} {{ content: std::borrow::Cow::Borrowed(&b\"{}\"[..]), span: self.gspan() }}",
                         esc_str(lit)
-                    );
+                    ));
+                    let _ = writeln!(b, "{:ind$}{e}", "");
                 }
                 _ => {
-                    let _ = writeln!(
-                        b,
-                        "{:ind$}on_event(StreamEvent::{t} {{ content: std::borrow::Cow::Borrowed(&b\"\"[..]), span: self.gspan() }});",
-                        ""
-                    );
+                    let e = self.emit(&format!("StreamEvent::{t} {{ content: std::borrow::Cow::Borrowed(&b\"\"[..]), span: self.gspan() }}"));
+                    let _ = writeln!(b, "{:ind$}{e}", "");
                 }
             }
+            self.trace_pop(b, info, ind);
             let _ = writeln!(b, "{:ind$}continue 'run;", "");
             return;
         }
@@ -979,18 +1270,17 @@ impl<'i> Gen<'i> {
             }
             "content" if !suppress => {
                 let t = info.func.return_type.as_deref().unwrap();
-                let _ = writeln!(
-                    b,
-                    "{:ind$}{{ let (c, sp) = self.take_capture(); on_event(StreamEvent::{t} {{ content: c, span: sp }}); }}",
-                    ""
-                );
+                let e = self.emit(&format!("StreamEvent::{t} {{ content: c, span: sp }}"));
+                let _ = writeln!(b, "{:ind$}{{ let (c, sp) = self.take_capture(); {e} }}", "");
             }
             "bracket" => {
                 let t = info.func.return_type.as_deref().unwrap();
-                let _ = writeln!(b, "{:ind$}on_event(StreamEvent::{t}End {{ span: self.gspan() }});", "");
+                let e = self.emit(&format!("StreamEvent::{t}End {{ span: self.gspan() }}"));
+                let _ = writeln!(b, "{:ind$}{e}", "");
             }
             _ => {}
         }
+        self.trace_pop(b, info, ind);
         let _ = writeln!(b, "{:ind$}continue 'run;", "");
     }
 }
@@ -1012,6 +1302,11 @@ fn collect_saved_slots(cmds: &[Command], slots: &mut BTreeSet<String>) {
     }
 }
 
+/// `.substate` suffix for a case's trace label.
+fn substate(case: &Case) -> String {
+    case.substate.as_deref().filter(|s| !s.is_empty()).map(|s| format!(".{s}")).unwrap_or_default()
+}
+
 fn init_expr(dsl: &str, func: &Function) -> String {
     // Initializers run inside `enter_<fn>` where params are in scope by
     // their own names — no frame prefix.
@@ -1051,8 +1346,13 @@ fn non_init_entry_actions(func: &Function, kind: &str) -> Vec<Command> {
 /// the CALLEE's parameter type (the recursive backend's
 /// `transform_call_args_by_type`): Bytes params take byte-string literals
 /// (`'``'`/`<>` -> `b"``"`/`b""`), Byte and I32 take the plain pipeline.
-/// Trailing ", ".
-fn render_call_args_typed(args: &str, vars: &BTreeSet<String>, callee: Option<&Function>) -> String {
+/// Bytes literals are also recorded in `lits`. Trailing ", ".
+fn render_call_args_typed(
+    args: &str,
+    vars: &BTreeSet<String>,
+    callee: Option<&Function>,
+    lits: &mut BTreeSet<String>,
+) -> String {
     let trimmed = args.trim();
     if trimmed.is_empty() {
         return String::new();
@@ -1073,7 +1373,9 @@ fn render_call_args_typed(args: &str, vars: &BTreeSet<String>, callee: Option<&F
                 }
                 let parsed = crate::charclass::parse(a);
                 let joined: String = parsed.chars.concat();
-                return format!("b\"{}\"", esc_str(&joined));
+                let lit = format!("b\"{}\"", esc_str(&joined));
+                lits.insert(lit.clone());
+                return lit;
             }
             pd_expr(a, vars)
         })
@@ -1146,10 +1448,105 @@ fn is_xlbl_cont(b: u8) -> bool {
     b == b'-' || is_xid_cont(b)
 }
 
+/// `p` re-based for an edit that moved everything at or after `at`.
+#[inline]
+fn pd_shift(p: usize, at: usize, delta: isize) -> usize {
+    if p >= at {
+        (p as isize + delta) as usize
+    } else {
+        p
+    }
+}
+
+#[inline]
+fn pd_shift_range(r: &mut std::ops::Range<usize>, at: usize, delta: isize) {
+    *r = pd_shift(r.start, at, delta)..pd_shift(r.end, at, delta);
+}
+
+/// The complete parse state of a `PushdownParser` — frame stack, retained
+/// buffer, capture and position bookkeeping, SAVE slots — as plain data.
+/// Taken by `PushdownParser::snapshot`, resumed by `restore`; serializable
+/// when the consuming crate enables its `serde` feature. Limits are not
+/// part of it: they belong to the parser a snapshot is restored into.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParserSnapshot {
+    stack: Vec<Frame>,
+    buf: Vec<u8>,
+    base: usize,
+    pos: usize,
+    mark_pos: usize,
+    mark_active: bool,
+    term_pos: usize,
+    prepend_buf: Vec<u8>,
+    term_prepend_len: usize,
+    pending_skip: u32,
+    prev_byte: u8,
+    ret: i32,
+    line: u32,
+    column: u32,
+    started: bool,
+    saved: SavedSlots,
+}
+
+impl ParserSnapshot {
+    /// Same machine state, position included: compare against a snapshot
+    /// already `shift`ed past an edit, so `line` matches only where the
+    /// resumed parse really is on the same line.
+    fn same_state(&self, other: &ParserSnapshot) -> bool {
+        self.stack == other.stack
+            && self.buf == other.buf
+            && self.base == other.base
+            && self.pos == other.pos
+            && self.mark_pos == other.mark_pos
+            && self.mark_active == other.mark_active
+            && self.term_pos == other.term_pos
+            && self.prepend_buf == other.prepend_buf
+            && self.term_prepend_len == other.term_prepend_len
+            && self.pending_skip == other.pending_skip
+            && self.prev_byte == other.prev_byte
+            && self.ret == other.ret
+            && self.line == other.line
+            && self.column == other.column
+            && self.started == other.started
+            && self.saved == other.saved
+    }
+
+    /// Re-base for an edit that moved everything at or after `at` by
+    /// `delta` bytes and `lines` newlines.
+    fn shift(&mut self, at: usize, delta: isize, lines: isize) {
+        self.base = pd_shift(self.base, at, delta);
+        self.saved.shift(at, delta);
+        self.line = (self.line as isize + lines) as u32;
+    }
+
+    /// Global byte offset the snapshot resumes from: the next `push_chunk`
+    /// after `restore` must start with the input byte at this offset.
+    pub fn offset(&self) -> usize {
+        self.base + self.buf.len()
+    }
+
+    /// Line number at the parse position.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Column number at the parse position.
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
 /// Resumable pushdown parser: feed bytes with `push_chunk`, close with
 /// `finish`. Owns an accumulation buffer so capture (`mark..pos`) never
 /// spans a seam; consumed bytes before the active mark are drained after
 /// every run. Spans are global byte offsets.
+///
+/// `with_limits` bounds what untrusted input can make it hold: bytes
+/// retained across chunks (`max_buffer`) and frame-stack depth
+/// (`max_depth`). Exceeding either emits an `Error` event and stops the
+/// parser with `ParseResult::LimitExceeded`. Peak buffer use is the
+/// retained bytes plus the chunk being pushed.
 pub struct PushdownParser {
     stack: Vec<Frame>,
     buf: Vec<u8>,
@@ -1162,6 +1559,8 @@ pub struct PushdownParser {
     prepend_buf: Vec<u8>,
     term_prepend_len: usize,
     pending_skip: u32,
+    /// The byte before buf[0] (0 at input start), so PREV survives a drain.
+    prev_byte: u8,
     ret: i32,
     line: u32,
     column: u32,
@@ -1169,6 +1568,10 @@ pub struct PushdownParser {
     started: bool,
     /// SAVE(slot) captures — see [`SavedSlots`].
     saved: SavedSlots,
+    max_buffer: usize,
+    max_depth: usize,
+    /// Set once a limit trips; the parser is dead from then on.
+    limit: Option<ParseResult>,
 }
 
 impl Default for PushdownParser {
@@ -1179,7 +1582,15 @@ impl Default for PushdownParser {
 
 #[allow(unused_variables, dead_code)]
 impl PushdownParser {
+    /// Unbounded parser — for trusted input only; see `with_limits`.
     pub fn new() -> Self {
+        Self::with_limits(usize::MAX, usize::MAX)
+    }
+
+    /// Parser that stops with `ParseResult::LimitExceeded` once more than
+    /// `max_buffer` bytes are retained between chunks or the frame stack
+    /// grows deeper than `max_depth`.
+    pub fn with_limits(max_buffer: usize, max_depth: usize) -> Self {
         PushdownParser {
             stack: Vec::new(),
             buf: Vec::new(),
@@ -1192,40 +1603,159 @@ impl PushdownParser {
             term_prepend_len: 0,
             saved: SavedSlots::default(),
             pending_skip: 0,
+            prev_byte: 0,
             ret: 0,
             line: 1,
             column: 1,
             finished: false,
             started: false,
+            max_buffer,
+            max_depth,
+            limit: None,
         }
     }
 
     /// Feed a chunk. Events fire for everything decidable so far.
     pub fn push_chunk<F>(&mut self, chunk: &[u8], on_event: &mut F) -> ParseResult
     where
-        F: for<'e> FnMut(StreamEvent<'e>),
+        F: for<'e> FnMut(StreamEvent<'e>)__FLOW__,
     {
+        if let Some(r) = self.limit {
+            return r;
+        }
+__LATCH__
         self.buf.extend_from_slice(chunk);
         if !self.started {
             self.started = true;
             self.enter___ENTRY__(on_event);
         }
         let r = self.run(on_event);
+        if let Some(stop) = self.limit {
+            return stop;
+        }
         self.drain_consumed();
+        if self.buf.len() > self.max_buffer {
+            return self.exceed(LimitKind::Buffer, on_event);
+        }
         r
     }
 
     /// Close the stream: remaining structure runs its EOF behavior.
-    pub fn finish<F>(mut self, on_event: &mut F)
+    /// Returns `Complete`, or what stopped the parser if something did.
+    pub fn finish<F>(mut self, on_event: &mut F) -> ParseResult
     where
-        F: for<'e> FnMut(StreamEvent<'e>),
+        F: for<'e> FnMut(StreamEvent<'e>)__FLOW__,
     {
+        if let Some(r) = self.limit {
+            return r;
+        }
+__LATCH__
         self.finished = true;
         if !self.started {
             self.started = true;
             self.enter___ENTRY__(on_event);
         }
-        let _ = self.run(on_event);
+        let r = self.run(on_event);
+        self.limit.unwrap_or(r)
+    }
+
+    /// Global byte offset of the parse position.
+    pub fn offset(&self) -> usize {
+        self.base + self.pos
+    }
+
+    /// Line number at the parse position.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Column number at the parse position.
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+
+    /// Checkpoint the parse state. Between `push_chunk` calls the machine is
+    /// always at a resumable boundary, so a snapshot can be taken at any
+    /// time (e.g. at every line for incremental reparse).
+    pub fn snapshot(&self) -> ParserSnapshot {
+        ParserSnapshot {
+            stack: self.stack.clone(),
+            buf: self.buf.clone(),
+            base: self.base,
+            pos: self.pos,
+            mark_pos: self.mark_pos,
+            mark_active: self.mark_active,
+            term_pos: self.term_pos,
+            prepend_buf: self.prepend_buf.clone(),
+            term_prepend_len: self.term_prepend_len,
+            pending_skip: self.pending_skip,
+            prev_byte: self.prev_byte,
+            ret: self.ret,
+            line: self.line,
+            column: self.column,
+            started: self.started,
+            saved: self.saved.clone(),
+        }
+    }
+
+    /// Resume from `snapshot`: feed input from `snapshot.offset()` on and
+    /// the parse continues exactly as it would have from that point. The
+    /// configured limits are kept; a previously tripped limit is cleared.
+    pub fn restore(&mut self, snapshot: ParserSnapshot) {
+        let ParserSnapshot {
+            stack,
+            buf,
+            base,
+            pos,
+            mark_pos,
+            mark_active,
+            term_pos,
+            prepend_buf,
+            term_prepend_len,
+            pending_skip,
+            prev_byte,
+            ret,
+            line,
+            column,
+            started,
+            saved,
+        } = snapshot;
+        self.stack = stack;
+        self.buf = buf;
+        self.base = base;
+        self.pos = pos;
+        self.mark_pos = mark_pos;
+        self.mark_active = mark_active;
+        self.term_pos = term_pos;
+        self.prepend_buf = prepend_buf;
+        self.term_prepend_len = term_prepend_len;
+        self.pending_skip = pending_skip;
+        self.prev_byte = prev_byte;
+        self.ret = ret;
+        self.line = line;
+        self.column = column;
+        self.started = started;
+        self.saved = saved;
+        self.finished = false;
+        self.limit = None;
+    }
+
+    /// Trip a limit: report it, release the buffer and stack, and stop.
+    /// A parser the callback already stopped stays `Stopped`.
+    fn exceed<F>(&mut self, kind: LimitKind, on_event: &mut F) -> ParseResult
+    where
+        F: for<'e> FnMut(StreamEvent<'e>)__FLOW__,
+    {
+        if let Some(r) = self.limit {
+            return r;
+        }
+        let offset = self.base + self.pos;
+        __LIMIT_EVENT__
+        self.buf = Vec::new();
+        self.stack = Vec::new();
+        let r = ParseResult::LimitExceeded { kind, offset };
+        self.limit = Some(r);
+        r
     }
 
     /// Drop consumed bytes that no capture can still reference.
@@ -1234,6 +1764,7 @@ impl PushdownParser {
         if keep_from == 0 {
             return;
         }
+        self.prev_byte = self.buf[keep_from - 1];
         self.buf.drain(..keep_from);
         self.base += keep_from;
         self.pos -= keep_from;
@@ -1341,7 +1872,7 @@ impl PushdownParser {
         if self.pos > 0 {
             self.buf[self.pos - 1]
         } else {
-            0
+            self.prev_byte
         }
     }
 
@@ -1430,3 +1961,432 @@ impl PushdownParser {
     }
 
 "#;
+
+/// Trace formatting helpers, appended to the runtime under `PdOptions.trace`
+/// (the recursive backend's `trace_byte` / `trace_content`, over `buf`).
+const TRACE_RUNTIME: &str = r#"    /// Format a byte for trace display (shows printable chars or escape codes).
+    fn trace_byte(b: Option<u8>) -> String {
+        match b {
+            None => "EOF".to_string(),
+            Some(b'\n') => "'\\n'".to_string(),
+            Some(b'\t') => "'\\t'".to_string(),
+            Some(b'\r') => "'\\r'".to_string(),
+            Some(b' ') => "' '".to_string(),
+            Some(b) if b.is_ascii_graphic() => format!("'{}'", b as char),
+            Some(b) => format!("0x{:02x}", b),
+        }
+    }
+
+    /// Format accumulated content for trace display (truncated).
+    /// Shows slice content only (prepend buffer shown separately if non-empty).
+    /// Empty before the first MARK, as the recursive parser's is.
+    fn trace_content(&self) -> String {
+        let end = if self.term_pos != usize::MAX { self.term_pos } else { self.pos };
+        let slice = if self.mark_active { &self.buf[self.mark_pos.min(end)..end] } else { &[][..] };
+        let prepend_info = if self.prepend_buf.is_empty() {
+            String::new()
+        } else {
+            format!("+{}", self.prepend_buf.len())
+        };
+        if slice.is_empty() && self.prepend_buf.is_empty() {
+            return "[]".to_string();
+        }
+        let s = std::str::from_utf8(slice).unwrap_or("<binary>");
+        if s.len() > 32 {
+            format!("[{:?}...]{}", &s[..32], prepend_info)
+        } else {
+            format!("[{:?}]{}", s, prepend_info)
+        }
+    }
+
+"#;
+
+/// Prologue of `push_chunk` / `finish` under `cancellable`: latch the
+/// caller's `Break` so the few events the machine emits on its way to the
+/// next read never reach it.
+const LATCH: &str = "        let mut latched = Self::latch(on_event);\n        let on_event = &mut latched;\n";
+
+/// Stop support under `cancellable`; spliced into `impl PushdownParser`.
+const CANCEL_RUNTIME: &str = r#"
+    /// Forward events until the callback first returns `Break`, then
+    /// answer `Break` without calling it again.
+    fn latch<F>(on_event: &mut F) -> impl for<'e> FnMut(StreamEvent<'e>) -> std::ops::ControlFlow<()> + '_
+    where
+        F: for<'e> FnMut(StreamEvent<'e>) -> std::ops::ControlFlow<()>,
+    {
+        let mut stopped = false;
+        move |e: StreamEvent<'_>| {
+            if !stopped {
+                stopped = on_event(e).is_break();
+            }
+            if stopped {
+                std::ops::ControlFlow::Break(())
+            } else {
+                std::ops::ControlFlow::Continue(())
+            }
+        }
+    }
+
+    /// The callback returned `Break`: stop here. Cutting the buffer at the
+    /// capture end and clearing `finished` makes the next byte read
+    /// suspend, so `run` returns there, and `limit` keeps the parser
+    /// stopped from then on.
+    fn halt(&mut self) {
+        if self.limit.is_none() {
+            self.limit = Some(ParseResult::Stopped { offset: self.base + self.pos });
+            let end = if self.term_pos != usize::MAX { self.term_pos.max(self.pos) } else { self.pos };
+            self.buf.truncate(end);
+            self.finished = false;
+        }
+    }
+"#;
+
+/// `IncrementalParser`: pushdown checkpoints + reparse-until-convergence.
+/// Appended after the trampoline; uses only the runtime's public surface
+/// plus `ParserSnapshot::{same_state, shift}`.
+const INCREMENTAL_RUNTIME: &str = r#"
+/// Which events an `IncrementalParser::edit` replaced: `old` indexes the
+/// event list before the edit, `new` the list after it. Events past
+/// `new.end` are the old tail, unchanged except that spans after the edit
+/// moved by its length difference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventSplice {
+    pub old: std::ops::Range<usize>,
+    pub new: std::ops::Range<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Checkpoint {
+    snap: ParserSnapshot,
+    /// Events emitted before the snapshot was taken.
+    events: usize,
+}
+
+/// Reparse-on-edit over a document held in memory. The document is fed to
+/// a `PushdownParser` in `interval`-byte chunks with a snapshot after each;
+/// an edit resumes from the last checkpoint before it and stops at the first
+/// old checkpoint past it whose state the new parse re-converges with, so
+/// only the events in between are regenerated.
+pub struct IncrementalParser {
+    text: Vec<u8>,
+    interval: usize,
+    events: Vec<StreamEvent<'static>>,
+    /// Strictly increasing offsets; `checkpoints[0]` is the fresh parser.
+    checkpoints: Vec<Checkpoint>,
+}
+
+#[allow(dead_code)]
+impl IncrementalParser {
+    /// Parse `text`, checkpointing every `interval` bytes.
+    pub fn new(text: Vec<u8>, interval: usize) -> Self {
+        let mut ip = IncrementalParser { text, interval: interval.max(1), events: Vec::new(), checkpoints: Vec::new() };
+        let p = PushdownParser::new();
+        ip.checkpoints.push(Checkpoint { snap: p.snapshot(), events: 0 });
+        ip.run_to_end(p, 0);
+        ip
+    }
+
+    /// The current document.
+    pub fn text(&self) -> &[u8] {
+        &self.text
+    }
+
+    /// Every event of the current document, in order.
+    pub fn events(&self) -> &[StreamEvent<'static>] {
+        &self.events
+    }
+
+    /// Replace `range` of the document with `replacement` and reparse as
+    /// little as the checkpoints allow. A range past the end of the
+    /// document is clamped to it; an inverted one inserts at its end.
+    pub fn edit(&mut self, range: std::ops::Range<usize>, replacement: &[u8]) -> EventSplice {
+        let end = range.end.min(self.text.len());
+        let range = range.start.min(end)..end;
+        let old_end = range.end;
+        let new_end = range.start + replacement.len();
+        let delta = new_end as isize - old_end as isize;
+        let newlines = |b: &[u8]| b.iter().filter(|&&c| c == b'\n').count() as isize;
+        let lines = newlines(replacement) - newlines(&self.text[range.clone()]);
+        self.text.splice(range.clone(), replacement.iter().copied());
+
+        let ci = self.checkpoints.iter().rposition(|c| c.snap.offset() <= range.start).unwrap_or(0);
+        let old_cps = self.checkpoints.split_off(ci + 1);
+        let first = self.checkpoints[ci].events;
+        let mut p = PushdownParser::new();
+        p.restore(self.checkpoints[ci].snap.clone());
+        let mut at = self.checkpoints[ci].snap.offset();
+        let mut fresh: Vec<StreamEvent<'static>> = Vec::new();
+
+        // Through the edit at the usual interval.
+        while at < new_end {
+            let next = (at + self.interval).min(new_end);
+            at = self.feed(&mut p, at, next, &mut fresh, first);
+        }
+        // Past it, on the old checkpoints' (shifted) boundaries, until the
+        // state matches one of them.
+        for (k, old) in old_cps.iter().enumerate() {
+            if old.snap.offset() < old_end {
+                continue;
+            }
+            let target = (old.snap.offset() as isize + delta) as usize;
+            if target < at {
+                continue;
+            }
+            at = self.feed(&mut p, at, target, &mut fresh, first);
+            let mut shifted = old.snap.clone();
+            shifted.shift(old_end, delta, lines);
+            if !p.snapshot().same_state(&shifted) {
+                continue;
+            }
+            // Converged: the old events from here on are still right.
+            let mut tail = self.events.split_off(old.events);
+            for e in &mut tail {
+                let span = e.span_mut();
+                *span = pd_shift(span.start, old_end, delta)..pd_shift(span.end, old_end, delta);
+            }
+            let grown = fresh.len() as isize - (old.events - first) as isize;
+            let old_range = first..old.events;
+            self.events.truncate(first);
+            self.events.extend(fresh);
+            let new_range = first..self.events.len();
+            self.events.extend(tail);
+            for c in &old_cps[k + 1..] {
+                let mut c = c.clone();
+                c.snap.shift(old_end, delta, lines);
+                c.events = (c.events as isize + grown) as usize;
+                self.checkpoints.push(c);
+            }
+            return EventSplice { old: old_range, new: new_range };
+        }
+        // No convergence: the rest of the document is new.
+        let old_len = self.events.len();
+        self.events.truncate(first);
+        self.events.extend(fresh);
+        self.run_to_end(p, at);
+        EventSplice { old: first..old_len, new: first..self.events.len() }
+    }
+
+    /// Push `text[from..to]`, checkpoint, and return `to`.
+    fn feed(&mut self, p: &mut PushdownParser, from: usize, to: usize, out: &mut Vec<StreamEvent<'static>>, first: usize) -> usize {
+        if to > from {
+            p.push_chunk(&self.text[from..to], &mut |e| { out.push(e.into_owned());__GO__ });
+        } else if self.checkpoints.last().is_some_and(|c| c.snap.offset() == to) {
+            self.checkpoints.pop();
+        }
+        self.checkpoints.push(Checkpoint { snap: p.snapshot(), events: first + out.len() });
+        to
+    }
+
+    /// Parse from `at` to the end of the document, checkpointing, then
+    /// finish into `self.events`.
+    fn run_to_end(&mut self, mut p: PushdownParser, mut at: usize) {
+        let mut out = Vec::new();
+        let first = self.events.len();
+        while at < self.text.len() {
+            let next = (at + self.interval).min(self.text.len());
+            at = self.feed(&mut p, at, next, &mut out, first);
+        }
+        p.finish(&mut |e| { out.push(e.into_owned());__GO__ });
+        self.events.extend(out);
+    }
+}
+"#;
+
+/// `Events`: the pull iterator. Appended after the trampoline; borrowed
+/// content is re-borrowed from the caller's input by the event's span.
+const EVENTS_RUNTIME: &str = r#"
+/// Input bytes fed to the machine per refill of an `Events` queue.
+const PD_EVENTS_CHUNK: usize = 4096;
+
+/// Pull iterator over a complete input: `Iterator<Item = Event<'a>>`.
+/// Each `next` on an empty queue pushes the next `PD_EVENTS_CHUNK` bytes
+/// (or runs `finish`), so at most one chunk's events are held and work
+/// stops when the iterator is dropped.
+pub struct Events<'a> {
+    input: &'a [u8],
+    /// Bytes of `input` already pushed.
+    fed: usize,
+    parser: Option<PushdownParser>,
+    pending: std::collections::VecDeque<Event<'a>>,
+}
+
+impl<'a> Events<'a> {
+    /// Events of `input`, parsed by an unbounded `PushdownParser`.
+    pub fn new(input: &'a [u8]) -> Self {
+        Events { input, fed: 0, parser: Some(PushdownParser::new()), pending: std::collections::VecDeque::new() }
+    }
+
+    /// Run the machine over the next chunk (or EOF), queueing its events.
+    fn refill(&mut self) {
+        let Some(parser) = self.parser.as_mut() else {
+            return;
+        };
+        let end = (self.fed + PD_EVENTS_CHUNK).min(self.input.len());
+        let chunk = &self.input[self.fed..end];
+        let input = self.input;
+        let pending = &mut self.pending;
+        let mut sink = |e: StreamEvent<'_>| {
+            // A buffer capture's span is its global offset and length, so
+            // its bytes are `input[span]`: borrow those instead. Literal,
+            // param and PREPEND-combined content doesn't match its span
+            // and is copied.
+            let span = e.span().clone();
+            pending.push_back(e.into_event(|c| match c {
+                std::borrow::Cow::Borrowed(s) if s.is_empty() => std::borrow::Cow::Borrowed(&[][..]),
+                std::borrow::Cow::Borrowed(s) if input.get(span.clone()) == Some(s) => {
+                    std::borrow::Cow::Borrowed(&input[span.clone()])
+                }
+                c => std::borrow::Cow::Owned(c.into_owned()),
+            }));__GO__
+        };
+        if chunk.is_empty() {
+            if let Some(parser) = self.parser.take() {
+                parser.finish(&mut sink);
+            }
+            return;
+        }
+        self.fed = end;
+        match parser.push_chunk(chunk, &mut sink) {
+            ParseResult::NeedMoreData => {}
+            ParseResult::Complete => {
+                if let Some(parser) = self.parser.take() {
+                    parser.finish(&mut sink);
+                }
+            }
+            ParseResult::LimitExceeded { .. } | ParseResult::Stopped { .. } => self.parser = None,
+        }
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Event<'a>> {
+        while self.pending.is_empty() && self.parser.is_some() {
+            self.refill();
+        }
+        self.pending.pop_front()
+    }
+}
+"#;
+
+/// `EventStream`: the async adapter, appended under `PdOptions.async_adapter`.
+const ASYNC_RUNTIME: &str = r#"
+/// A `StreamEvent` detached from the parser's buffer.
+pub type OwnedEvent = StreamEvent<'static>;
+
+/// Async adapter: reads chunks from `R`, feeds them to a `PushdownParser`,
+/// and yields the events as a `futures::Stream`. Events are made owned
+/// here — the borrowed-event contract cannot cross a poll. The stream ends
+/// after EOF handling, after a tripped limit (its `Error` event is the last
+/// item), or on a read error, which `take_error` then returns; a read error
+/// does not run EOF handling (the input was cut, not closed).
+pub struct EventStream<R> {
+    reader: R,
+    parser: Option<PushdownParser>,
+    chunk: Box<[u8]>,
+    pending: std::collections::VecDeque<OwnedEvent>,
+    error: Option<std::io::Error>,
+}
+
+#[allow(dead_code)]
+impl<R> EventStream<R> {
+    /// Stream `reader` through an unbounded parser in 8 KiB reads.
+    pub fn new(reader: R) -> Self {
+        Self::with_parser(reader, PushdownParser::new(), 8192)
+    }
+
+    /// Stream `reader` through `parser` (e.g. one built `with_limits`),
+    /// reading up to `chunk_size` bytes at a time.
+    pub fn with_parser(reader: R, parser: PushdownParser, chunk_size: usize) -> Self {
+        EventStream {
+            reader,
+            parser: Some(parser),
+            chunk: vec![0; chunk_size.max(1)].into_boxed_slice(),
+            pending: std::collections::VecDeque::new(),
+            error: None,
+        }
+    }
+
+    /// The read error that ended the stream, if any.
+    pub fn take_error(&mut self) -> Option<std::io::Error> {
+        self.error.take()
+    }
+}
+
+impl<R: futures::io::AsyncRead + Unpin> futures::Stream for EventStream<R> {
+    type Item = OwnedEvent;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<OwnedEvent>> {
+        use std::task::Poll;
+        let this = self.get_mut();
+        loop {
+            if let Some(e) = this.pending.pop_front() {
+                return Poll::Ready(Some(e));
+            }
+            let Some(parser) = this.parser.as_mut() else {
+                return Poll::Ready(None);
+            };
+            let n = match std::pin::Pin::new(&mut this.reader).poll_read(cx, &mut this.chunk) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Poll::Ready(Err(e)) => {
+                    this.error = Some(e);
+                    this.parser = None;
+                    continue;
+                }
+                Poll::Ready(Ok(n)) => n,
+            };
+            let pending = &mut this.pending;
+            let mut sink = |e: StreamEvent<'_>| { pending.push_back(e.into_owned());__GO__ };
+            if n == 0 {
+                if let Some(parser) = this.parser.take() {
+                    parser.finish(&mut sink);
+                }
+                continue;
+            }
+            match parser.push_chunk(&this.chunk[..n], &mut sink) {
+                ParseResult::NeedMoreData => {}
+                ParseResult::Complete => {
+                    if let Some(parser) = this.parser.take() {
+                        parser.finish(&mut sink);
+                    }
+                }
+                ParseResult::LimitExceeded { .. } | ParseResult::Stopped { .. } => this.parser = None,
+            }
+        }
+    }
+}
+
+/// Adapts a `tokio::io::AsyncRead` to `futures::io::AsyncRead` for
+/// `EventStream`.
+#[cfg(feature = "tokio")]
+pub struct TokioReader<R>(pub R);
+
+#[cfg(feature = "tokio")]
+impl<R: tokio::io::AsyncRead + Unpin> futures::io::AsyncRead for TokioReader<R> {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        let mut rb = tokio::io::ReadBuf::new(buf);
+        match std::pin::Pin::new(&mut self.get_mut().0).poll_read(cx, &mut rb) {
+            std::task::Poll::Ready(Ok(())) => std::task::Poll::Ready(Ok(rb.filled().len())),
+            std::task::Poll::Ready(Err(e)) => std::task::Poll::Ready(Err(e)),
+            std::task::Poll::Pending => std::task::Poll::Pending,
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R: tokio::io::AsyncRead + Unpin> EventStream<TokioReader<R>> {
+    /// Stream a tokio reader (socket, file, `&[u8]`, ...).
+    pub fn from_tokio(reader: R) -> Self {
+        Self::new(TokioReader(reader))
+    }
+}
+"#;
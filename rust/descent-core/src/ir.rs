@@ -94,6 +94,9 @@ pub struct Function {
     /// `None` for positional functions and for delimited ones whose closer
     /// `expects_char` already handles.
     pub delimited_code: Option<String>,
+    /// `|mixed`: opts out of the validator's rejection of functions that
+    /// classify **mixed** (positional and delimited exits in one function).
+    pub allow_mixed: bool,
     pub emits_content_on_close: bool,
     /// Param name -> sorted byte values passed at call sites (neutral,
     /// unescaped — Ruby stores these pre-Rust-escaped; see module docs).
@@ -116,6 +119,11 @@ pub struct State {
     pub name: String,
     pub cases: Vec<Case>,
     pub eof_handler: Option<Vec<Command>>,
+    /// State-line actions (`|state[:name] MARK`). Informational: the builder
+    /// has already copied them ahead of every transition into the state
+    /// (and into the function's entry actions for the first state), so
+    /// emitters and analyses never run this list themselves.
+    pub entry_actions: Vec<Command>,
     /// Chars (each one character) for SIMD memchr scan, or None.
     pub scan_chars: Option<Vec<String>>,
     /// Byte-parameter names that join the scan set at runtime (memchr takes
@@ -12,26 +12,21 @@
 
 use crate::ast;
 use crate::charclass;
+use crate::diagnostic::{Diagnostic, Diagnostics, Span};
 use crate::ir::*;
 use crate::lexer::re;
 use serde_json::{json, Map, Value};
 use std::collections::BTreeSet;
-use std::fmt;
 
-/// Equivalent of Ruby's Descent::ValidationError.
-#[derive(Debug)]
-pub struct ValidationError(pub String);
+/// Ruby's Descent::ValidationError, as a structured diagnostic (E02xx).
+type Result<T> = std::result::Result<T, Diagnostic>;
 
-impl fmt::Display for ValidationError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
-    }
+/// Keep every `Ok`, push every `Err` onto `errs` — the builder reports all
+/// of a grammar's errors in one run and only fails at the end of `build`.
+fn collect_all<T>(items: impl Iterator<Item = Result<T>>, errs: &mut Vec<Diagnostic>) -> Vec<T> {
+    items.filter_map(|r| r.map_err(|e| errs.push(e)).ok()).collect()
 }
 
-impl std::error::Error for ValidationError {}
-
-type Result<T> = std::result::Result<T, ValidationError>;
-
 pub struct IRBuilder<'a> {
     ast: &'a ast::Machine,
 }
@@ -41,30 +36,62 @@ impl<'a> IRBuilder<'a> {
         IRBuilder { ast }
     }
 
-    pub fn build(&self) -> Result<ParserIR> {
+    pub fn build(&self) -> std::result::Result<ParserIR, Diagnostics> {
+        let mut errs = Vec::new();
+        // |template expansion pre-pass: splice each `|use[name(Type)]` into
+        // its state as the template's rows, so everything after this point
+        // (consts included) sees ordinary hand-written rows.
+        let expanded;
+        let ast: &ast::Machine = if self.ast.templates.is_empty()
+            && self.ast.functions.iter().all(|f| f.states.iter().all(|s| s.uses.is_empty()))
+        {
+            self.ast
+        } else {
+            expanded = expand_templates(self.ast, &mut errs);
+            &expanded
+        };
+
+        // |ends expansion pre-pass: each `|ends[set -> Type]` becomes the
+        // set's emit-and-return rows ahead of the state's |default, so scan
+        // and EOF inference see them exactly as if written out.
+        let ended;
+        let ast: &ast::Machine = if ast.terminators.is_empty()
+            && ast.functions.iter().all(|f| f.states.iter().all(|s| s.ends.is_empty()))
+        {
+            ast
+        } else {
+            ended = expand_terminators(ast, &mut errs);
+            &ended
+        };
+
         // |const substitution pre-pass: replace declared SCREAMING_CASE
         // names with their integer values in every expression position
         // (assignments, conditions, call args, return values) so all
         // downstream analysis — both backends, param typing, scan/expects
         // inference — just sees numbers.
         let substituted;
-        let ast: &ast::Machine = if self.ast.consts.is_empty() {
-            self.ast
+        let ast: &ast::Machine = if ast.consts.is_empty() {
+            ast
         } else {
-            substituted = substitute_consts(self.ast)?;
+            substituted = substitute_consts(ast, &mut errs);
             &substituted
         };
 
         let types = build_types(&ast.types);
-        let mut functions = ast
-            .functions
-            .iter()
-            .map(|f| build_function(f, &types))
-            .collect::<Result<Vec<_>>>()?;
+        let mut functions: Vec<Function> = ast.functions.iter().map(|f| build_function(f, &types, &mut errs)).collect();
+        if !errs.is_empty() {
+            return Err(Diagnostics(errs));
+        }
         let keywords = ast.keywords.iter().map(build_keywords).collect();
 
-        // Collect custom error codes from /error(code) calls
-        let custom_error_codes = collect_custom_error_codes(&functions);
+        // Collect custom error codes from /error(code) calls, plus the
+        // `Unclosed<Name>` codes `|unclosed` declares.
+        let mut custom_error_codes = collect_custom_error_codes(&functions);
+        for code in functions.iter().filter_map(|f| f.delimited_code.clone()) {
+            if let Err(i) = custom_error_codes.binary_search(&code) {
+                custom_error_codes.insert(i, code);
+            }
+        }
 
         // Collect prepend values by tracing call sites (also propagates
         // param types from call sites / callees, mutating param_types).
@@ -89,9 +116,9 @@ impl<'a> IRBuilder<'a> {
         // callee closers that `expects_char` cannot see — e.g. `quoted(:q)`).
         // NOTE: `Unclosed<ReturnType>` is a first-cut derivation; it matches the
         // hand codes for the regular constructs (StringValue/Interpolation/
-        // Array) but not the irregular ones (embed_content:Text→UnclosedEmbedded,
-        // freeform→UnterminatedFreeform), which keep their hand arms for now and
-        // want a construct-name convention (TODO-DESCENT "derive Unclosed<Name>").
+        // Array). Constructs whose name differs from the return type
+        // (embed_content:Text → UnclosedEmbedded) declare theirs with
+        // `|unclosed Name`; a declared code is already set and never overridden.
         let delimited: std::collections::HashSet<String> = crate::classify::classify(&ir)
             .into_iter()
             .filter(|c| c.kind == crate::classify::Kind::Delimited)
@@ -104,7 +131,7 @@ impl<'a> IRBuilder<'a> {
         // the old `expects_char` path's wrong `Error`+enum emission for them
         // (the B-5 severity/order finding) in favour of the correct
         // content-string `Warning`.
-        for f in ir.functions.iter_mut() {
+        for f in ir.functions.iter_mut().filter(|f| f.delimited_code.is_none()) {
             if delimited.contains(&f.name) {
                 if let Some(rt) = &f.return_type {
                     f.delimited_code = Some(format!("Unclosed{rt}"));
@@ -115,6 +142,193 @@ impl<'a> IRBuilder<'a> {
     }
 }
 
+// ---------------------------------------------------------------------------
+// |template expansion (pre-pass over the AST)
+// ---------------------------------------------------------------------------
+
+/// Clone the machine with every `|use[name(Args)]` replaced by the
+/// template's rows (and its `|eof`, for a state without one), each template
+/// param replaced by its argument wherever an event type is named. Rows keep
+/// the template's line numbers, so diagnostics and traces point at the row
+/// as written.
+fn expand_templates(m: &ast::Machine, errs: &mut Vec<Diagnostic>) -> ast::Machine {
+    let mut seen = std::collections::HashMap::new();
+    for t in &m.templates {
+        if let Some(first) = seen.insert(t.name.as_str(), t.lineno) {
+            errs.push(
+                Diagnostic::error("E0208", format!("duplicate template '{}'", t.name))
+                    .at(Span::line(t.lineno).find(t.name.as_str()))
+                    .label(Span::line(first).find(t.name.as_str()), "first declared here"),
+            );
+        }
+    }
+
+    let mut out = m.clone();
+    for func in &mut out.functions {
+        for state in &mut func.states {
+            // Back to front: each splice leaves the earlier `at`s valid.
+            let first_err = errs.len();
+            for u in std::mem::take(&mut state.uses).into_iter().rev() {
+                let Some(t) = m.templates.iter().find(|t| t.name == u.name) else {
+                    errs.push(
+                        Diagnostic::error("E0206", format!("unknown template '{}'", u.name))
+                            .at(Span::line(u.lineno).find(u.name.as_str())),
+                    );
+                    continue;
+                };
+                if t.params.len() != u.args.len() {
+                    errs.push(
+                        Diagnostic::error(
+                            "E0207",
+                            format!("template '{}' takes {} argument(s), got {}", t.name, t.params.len(), u.args.len()),
+                        )
+                        .at(Span::line(u.lineno).find(u.name.as_str()))
+                        .label(Span::line(t.lineno).find(t.name.as_str()), "declared here"),
+                    );
+                    continue;
+                }
+                let bind: Vec<(&str, &str)> =
+                    t.params.iter().map(String::as_str).zip(u.args.iter().map(String::as_str)).collect();
+                let rows = t.cases.iter().cloned().map(|mut kase| {
+                    for cmd in &mut kase.commands {
+                        bind_type_args(cmd, &bind);
+                    }
+                    kase
+                });
+                state.cases.splice(u.at..u.at, rows);
+                if let Some(h) = &t.eof_handler {
+                    if let Some(own) = &state.eof_handler {
+                        errs.push(
+                            Diagnostic::error(
+                                "E0209",
+                                format!("state '{}' has its own |eof and uses template '{}', which has one", state.name, t.name),
+                            )
+                            .at(Span::line(u.lineno).find(u.name.as_str()))
+                            .label(Span::line(own.lineno), "state's |eof"),
+                        );
+                    } else {
+                        let mut h = h.clone();
+                        for cmd in &mut h.commands {
+                            bind_type_args(cmd, &bind);
+                        }
+                        state.eof_handler = Some(h);
+                    }
+                }
+            }
+            // ...but report in source order.
+            errs[first_err..].reverse();
+        }
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// |terminators / |ends expansion (pre-pass over the AST)
+// ---------------------------------------------------------------------------
+
+/// Clone the machine with every `|ends[set -> Type]` replaced by one
+/// `|c[member] | Type(USE_MARK) |return` row per byte member of the set,
+/// inserted before the state's first default (the byte stays unconsumed
+/// for the caller), and `|eof | Type(USE_MARK) |return` for an `eof`
+/// member. Rows take the `|ends` row's line number.
+fn expand_terminators(m: &ast::Machine, errs: &mut Vec<Diagnostic>) -> ast::Machine {
+    let mut seen = std::collections::HashMap::new();
+    for t in &m.terminators {
+        if let Some(first) = seen.insert(t.name.as_str(), t.lineno) {
+            errs.push(
+                Diagnostic::error("E0211", format!("duplicate terminator set '{}'", t.name))
+                    .at(Span::line(t.lineno).find(t.name.as_str()))
+                    .label(Span::line(first).find(t.name.as_str()), "first declared here"),
+            );
+        }
+    }
+
+    let mut out = m.clone();
+    for func in &mut out.functions {
+        for state in &mut func.states {
+            for e in std::mem::take(&mut state.ends) {
+                let Some(set) = m.terminators.iter().find(|t| t.name == e.set) else {
+                    errs.push(
+                        Diagnostic::error("E0210", format!("unknown terminator set '{}'", e.set))
+                            .at(Span::line(e.lineno).find(e.set.as_str())),
+                    );
+                    continue;
+                };
+                let lineno = e.lineno;
+                let commands = vec![
+                    ast::Command::Cmd { kind: ast::CmdKind::InlineEmitMark(e.event_type.clone()), lineno },
+                    ast::Command::Cmd { kind: ast::CmdKind::Return(String::new()), lineno },
+                ];
+                let at = state
+                    .cases
+                    .iter()
+                    .position(|c| c.chars.is_none() && c.condition.is_none())
+                    .unwrap_or(state.cases.len());
+                let rows = set.members.iter().filter(|m| *m != "eof").map(|member| ast::Case {
+                    chars: Some(member.clone()),
+                    condition: None,
+                    substate: None,
+                    commands: commands.clone(),
+                    lineno,
+                });
+                state.cases.splice(at..at, rows);
+                if set.members.iter().any(|m| m == "eof") {
+                    if let Some(own) = &state.eof_handler {
+                        errs.push(
+                            Diagnostic::error(
+                                "E0212",
+                                format!("state '{}' has its own |eof and ends on set '{}', which has eof", state.name, set.name),
+                            )
+                            .at(Span::line(e.lineno).find(e.set.as_str()))
+                            .label(Span::line(own.lineno), "state's |eof"),
+                        );
+                    } else {
+                        state.eof_handler = Some(ast::EOFHandler { commands: commands.clone(), lineno });
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Replace template params named as event types: inline emits
+/// (`T(USE_MARK)`, `T`, ...), `emit(T)` and the type of `|return T...`.
+fn bind_type_args(cmd: &mut ast::Command, bind: &[(&str, &str)]) {
+    let arg = |ty: &str| bind.iter().find(|(p, _)| *p == ty).map(|(_, a)| a.to_string());
+    match cmd {
+        ast::Command::Conditional { clauses, .. } => {
+            for clause in clauses {
+                for c in &mut clause.commands {
+                    bind_type_args(c, bind);
+                }
+            }
+        }
+        ast::Command::Cmd { kind, .. } => {
+            use ast::CmdKind::*;
+            match kind {
+                InlineEmitMark(ty)
+                | InlineEmitBare(ty)
+                | Emit(Some(ty))
+                | InlineEmitSaved { ty, .. }
+                | InlineEmitLiteral { ty, .. }
+                | InlineEmitParam { ty, .. } => {
+                    if let Some(a) = arg(ty) {
+                        *ty = a;
+                    }
+                }
+                Return(value) => {
+                    let name_len = value.find('(').unwrap_or(value.len());
+                    if let Some(a) = arg(&value[..name_len]) {
+                        value.replace_range(..name_len, &a);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // |const substitution (pre-pass over the AST)
 // ---------------------------------------------------------------------------
@@ -123,14 +337,15 @@ impl<'a> IRBuilder<'a> {
 /// value in expression positions: assignment exprs, `|if[...]` conditions
 /// (case- and clause-level), call args, and return values. Emit positions
 /// (event type names) are deliberately untouched.
-fn substitute_consts(m: &ast::Machine) -> Result<ast::Machine> {
-    let mut seen = std::collections::HashSet::new();
+fn substitute_consts(m: &ast::Machine, errs: &mut Vec<Diagnostic>) -> ast::Machine {
+    let mut seen = std::collections::HashMap::new();
     for c in &m.consts {
-        if !seen.insert(c.name.as_str()) {
-            return Err(ValidationError(format!(
-                "L{}: duplicate const '{}'",
-                c.lineno, c.name
-            )));
+        if let Some(first) = seen.insert(c.name.as_str(), c.lineno) {
+            errs.push(
+                Diagnostic::error("E0201", format!("duplicate const '{}'", c.name))
+                    .at(Span::line(c.lineno).find(c.name.as_str()))
+                    .label(Span::line(first).find(c.name.as_str()), "first declared here"),
+            );
         }
     }
     let consts: Vec<(regex::Regex, String)> = m
@@ -163,6 +378,9 @@ fn substitute_consts(m: &ast::Machine) -> Result<ast::Machine> {
             }
         }
         for state in &mut func.states {
+            for cmd in &mut state.entry_actions {
+                substitute_consts_in_command(cmd, &sub, &m.consts);
+            }
             for kase in &mut state.cases {
                 if let Some(cond) = &kase.condition {
                     kase.condition = Some(sub(cond));
@@ -178,7 +396,7 @@ fn substitute_consts(m: &ast::Machine) -> Result<ast::Machine> {
             }
         }
     }
-    Ok(out)
+    out
 }
 
 fn substitute_consts_in_command(
@@ -256,7 +474,7 @@ fn build_types(type_decls: &[ast::TypeDecl]) -> Vec<TypeInfo> {
         .collect()
 }
 
-fn build_function(func: &ast::Function, types: &[TypeInfo]) -> Result<Function> {
+fn build_function(func: &ast::Function, types: &[TypeInfo], errs: &mut Vec<Diagnostic>) -> Function {
     let return_type_info = func
         .return_type
         .as_ref()
@@ -266,34 +484,20 @@ fn build_function(func: &ast::Function, types: &[TypeInfo]) -> Result<Function>
     let emits_events = return_type_info.map(|t| t.is_bracket() || t.is_content());
 
     let locals = infer_locals(func);
-    let states = func
-        .states
-        .iter()
-        .map(|s| build_state(s, &func.params))
-        .collect::<Result<Vec<_>>>()?;
+    let mut states: Vec<State> = func.states.iter().map(|s| build_state(s, &func.params, errs)).collect();
 
     let (expects_char, emits_content_on_close) = infer_expects(&states);
     let param_types = infer_param_types(&func.params, &states);
 
-    let eof_handler = match &func.eof_handler {
-        Some(h) => {
-            let cmds = h
-                .commands
-                .iter()
-                .map(build_command)
-                .collect::<Result<Vec<_>>>()?;
-            Some(mark_returns_after_inline_emits(cmds))
-        }
-        None => None,
-    };
+    let eof_handler = func
+        .eof_handler
+        .as_ref()
+        .map(|h| mark_returns_after_inline_emits(collect_all(h.commands.iter().map(build_command), errs)));
 
-    let entry_actions = func
-        .entry_actions
-        .iter()
-        .map(build_command)
-        .collect::<Result<Vec<_>>>()?;
+    let mut entry_actions = collect_all(func.entry_actions.iter().map(build_command), errs);
+    lower_state_entry_actions(&mut states, &mut entry_actions);
 
-    Ok(Function {
+    Function {
         name: func.name.clone(),
         return_type: func.return_type.clone(),
         params: func.params.clone(),
@@ -304,19 +508,18 @@ fn build_function(func: &ast::Function, types: &[TypeInfo]) -> Result<Function>
         entry_actions,
         emits_events,
         expects_char,
-        delimited_code: None, // set by the classification post-pass in build()
+        // `|unclosed Name` declares it; otherwise set by the classification
+        // post-pass in build().
+        delimited_code: func.unclosed.as_ref().map(|n| format!("Unclosed{n}")),
+        allow_mixed: func.mixed,
         emits_content_on_close,
         prepend_values: vec![],
         lineno: func.lineno,
-    })
+    }
 }
 
-fn build_state(state: &ast::State, params: &[String]) -> Result<State> {
-    let cases = state
-        .cases
-        .iter()
-        .map(|c| build_case(c, params))
-        .collect::<Result<Vec<_>>>()?;
+fn build_state(state: &ast::State, params: &[String], errs: &mut Vec<Diagnostic>) -> State {
+    let cases: Vec<Case> = state.cases.iter().map(|c| build_case(c, params, errs)).collect();
     let (mut scan_chars, scan_params) = infer_scan_targets(&cases);
     let is_self_looping = cases
         .iter()
@@ -333,17 +536,10 @@ fn build_state(state: &ast::State, params: &[String]) -> Result<State> {
             c.chars.is_none() && c.special_class.is_none() && c.param_ref.is_none() && c.condition.is_none()
         });
 
-    let eof_handler = match &state.eof_handler {
-        Some(h) => {
-            let cmds = h
-                .commands
-                .iter()
-                .map(build_command)
-                .collect::<Result<Vec<_>>>()?;
-            Some(mark_returns_after_inline_emits(cmds))
-        }
-        None => None,
-    };
+    let eof_handler = state
+        .eof_handler
+        .as_ref()
+        .map(|h| mark_returns_after_inline_emits(collect_all(h.commands.iter().map(build_command), errs)));
 
     // Inject '\n' into scan_chars if not already a user target (and room —
     // runtime params count against the 6-needle limit).
@@ -356,10 +552,11 @@ fn build_state(state: &ast::State, params: &[String]) -> Result<State> {
         }
     }
 
-    Ok(State {
+    State {
         name: state.name.clone(),
         cases,
         eof_handler,
+        entry_actions: collect_all(state.entry_actions.iter().map(build_command), errs),
         scan_chars,
         scan_params,
         is_self_looping,
@@ -367,26 +564,23 @@ fn build_state(state: &ast::State, params: &[String]) -> Result<State> {
         is_unconditional,
         newline_injected,
         lineno: state.lineno,
-    })
+    }
 }
 
-fn build_case(kase: &ast::Case, params: &[String]) -> Result<Case> {
-    if let Some(chars) = &kase.chars {
-        validate_char_syntax(chars, kase.lineno)?;
-    }
-    validate_prepend_commands(&kase.commands, params, kase.lineno)?;
-    validate_call_args(&kase.commands, params, kase.lineno)?;
+fn build_case(kase: &ast::Case, params: &[String], errs: &mut Vec<Diagnostic>) -> Case {
+    let checks = [
+        kase.chars.as_ref().map_or(Ok(()), |chars| validate_char_syntax(chars, kase.lineno)),
+        validate_prepend_commands(&kase.commands, params, kase.lineno),
+        validate_call_args(&kase.commands, params, kase.lineno),
+    ];
+    errs.extend(checks.into_iter().filter_map(Result::err));
     let (chars, special_class, param_ref) = parse_chars(kase.chars.as_deref(), params);
-    let commands = kase
-        .commands
-        .iter()
-        .map(build_command)
-        .collect::<Result<Vec<_>>>()?;
+    let commands = collect_all(kase.commands.iter().map(build_command), errs);
 
     // Fix #11: inline emit before a bare return suppresses auto-emit.
     let commands = mark_returns_after_inline_emits(commands);
 
-    Ok(Case {
+    Case {
         chars,
         special_class,
         param_ref,
@@ -394,7 +588,7 @@ fn build_case(kase: &ast::Case, params: &[String]) -> Result<Case> {
         substate: kase.substate.clone(),
         commands,
         lineno: kase.lineno,
-    })
+    }
 }
 
 /// Mark return commands that follow inline emits to suppress auto-emit.
@@ -497,42 +691,41 @@ fn process_escapes(s: &str) -> String {
 /// Validate and process advance_to (`->[...]`) arguments.
 fn validate_advance_to(s: &str, lineno: usize) -> Result<String> {
     if s.is_empty() {
-        return Err(ValidationError(format!(
-            "L{lineno}: ->[] requires at least one character"
-        )));
+        return Err(Diagnostic::error("E0202", "->[] requires at least one character")
+            .at(Span::line(lineno).find("->[]")));
     }
 
     let result = charclass::parse(s);
 
     if result.special_class.is_some() {
-        return Err(ValidationError(format!(
-            "L{}: ->[] does not support character classes like {}. \
-             Only literal bytes are supported (uses SIMD memchr).",
-            lineno,
-            s.to_uppercase()
-        )));
+        return Err(Diagnostic::error(
+            "E0202",
+            format!("->[] does not support character classes like {}", s.to_uppercase()),
+        )
+        .at(Span::line(lineno).find(s))
+        .help("only literal bytes are supported (uses SIMD memchr)"));
     }
 
     if let Some(p) = &result.param_ref {
-        return Err(ValidationError(format!(
-            "L{lineno}: ->[] does not support parameter references like :{p}. \
-             Only literal bytes are supported (uses SIMD memchr)."
-        )));
+        return Err(Diagnostic::error("E0202", format!("->[] does not support parameter references like :{p}"))
+            .at(Span::line(lineno).find(s))
+            .help("only literal bytes are supported (uses SIMD memchr)"));
     }
 
     let bytes = result.bytes.unwrap_or_default();
     if bytes.is_empty() {
-        return Err(ValidationError(format!(
-            "L{lineno}: ->[] resolved to empty bytes from '{s}'"
-        )));
+        return Err(Diagnostic::error("E0202", format!("->[] resolved to empty bytes from '{s}'"))
+            .at(Span::line(lineno).find(s)));
     }
 
     let n = bytes.chars().count();
     if n > 6 {
-        return Err(ValidationError(format!(
-            "L{lineno}: ->[{s}] has {n} chars but maximum is 6 \
-             (chained memchr limit). Split into multiple scans or restructure grammar."
-        )));
+        return Err(Diagnostic::error(
+            "E0202",
+            format!("->[{s}] has {n} chars but maximum is 6 (chained memchr limit)"),
+        )
+        .at(Span::line(lineno).find(s))
+        .help("split into multiple scans or restructure the grammar"));
     }
 
     Ok(bytes)
@@ -567,11 +760,12 @@ fn validate_char_syntax(chars_str: &str, lineno: usize) -> Result<()> {
 
     // <TOKEN> escape sequences outside a proper <...> class wrapper
     if re(r"<[A-Z]+>").is_match(chars_str) {
-        return Err(ValidationError(format!(
-            "Line {lineno}: Escape sequence like <SQ>, <P> etc. found outside \
-             class wrapper in c[{chars_str}]. \
-             Wrap everything in a class: c[<...>] not c[THING <ESC> ...]"
-        )));
+        return Err(Diagnostic::error(
+            "E0203",
+            format!("Escape sequence like <SQ>, <P> etc. found outside class wrapper in c[{chars_str}]"),
+        )
+        .at(Span::line(lineno).find(chars_str))
+        .help("wrap everything in a class: c[<...>] not c[THING <ESC> ...]"));
     }
 
     // Combined class + chars (e.g., LETTER'[.?!)
@@ -580,21 +774,20 @@ fn validate_char_syntax(chars_str: &str, lineno: usize) -> Result<()> {
             .captures(chars_str)
             .map(|c| c[1].to_string())
             .unwrap_or_default();
-        return Err(ValidationError(format!(
-            "Line {lineno}: Invalid character syntax in c[{chars_str}]. \
-             Bare quote after class name is ambiguous. \
-             Use class syntax instead: c[<{class_name} ...>]"
-        )));
+        return Err(Diagnostic::error(
+            "E0203",
+            format!("Invalid character syntax in c[{chars_str}]: bare quote after class name is ambiguous"),
+        )
+        .at(Span::line(lineno).find(chars_str))
+        .help(format!("use class syntax instead: c[<{class_name} ...>]")));
     }
 
     // Unterminated quotes
     let quote_count = chars.iter().filter(|&&c| c == '\'').count();
     if quote_count % 2 == 1 {
-        return Err(ValidationError(format!(
-            "Line {lineno}: Unterminated quote in c[{chars_str}]. \
-             Single quotes must be paired. \
-             To match a literal quote, use c[<SQ>] or c['\\'']"
-        )));
+        return Err(Diagnostic::error("E0203", format!("Unterminated quote in c[{chars_str}]"))
+            .at(Span::line(lineno).find(chars_str))
+            .help("single quotes must be paired; to match a literal quote, use c[<SQ>] or c['\\'']"));
     }
 
     // Any character outside /A-Za-z0-9_-/ that isn't quoted
@@ -627,14 +820,12 @@ fn validate_char_syntax(chars_str: &str, lineno: usize) -> Result<()> {
             other => format!("c['{other}']"),
         };
 
-        return Err(ValidationError(format!(
-            "Line {}: Unquoted '{}' in c[{}]. \
-             Characters outside /A-Za-z0-9_-/ must be quoted. Use {}",
-            lineno,
-            ch.escape_debug(),
-            chars_str,
-            suggestion
-        )));
+        return Err(Diagnostic::error(
+            "E0203",
+            format!("Unquoted '{}' in c[{}]: characters outside /A-Za-z0-9_-/ must be quoted", ch.escape_debug(), chars_str),
+        )
+        .at(Span::line(lineno).find(chars_str))
+        .help(format!("use {suggestion}")));
     }
 
     Ok(())
@@ -657,11 +848,12 @@ fn validate_prepend_commands(commands: &[ast::Command], params: &[String], linen
         if !params.iter().any(|p| p == literal) {
             continue;
         }
-        return Err(ValidationError(format!(
-            "Line {lineno}: PREPEND({literal}) looks like a parameter reference. \
-             Use PREPEND(:{literal}) to reference the '{literal}' parameter, \
-             or PREPEND('{literal}') for a literal string."
-        )));
+        return Err(Diagnostic::error("E0204", format!("PREPEND({literal}) looks like a parameter reference"))
+            .at(Span::line(lineno).find(format!("({literal})")))
+            .help(format!(
+                "use PREPEND(:{literal}) to reference the '{literal}' parameter, \
+                 or PREPEND('{literal}') for a literal string"
+            )));
     }
 
     Ok(())
@@ -710,10 +902,12 @@ fn validate_call_args(commands: &[ast::Command], params: &[String], lineno: usiz
             if !params.iter().any(|p| p == arg) {
                 continue;
             }
-            return Err(ValidationError(format!(
-                "Line {lineno}: /...(...{arg}...) - bare identifier '{arg}' matches a parameter name. \
-                 Use ':{arg}' to pass the parameter value, or \"'{arg}'\" for a literal string."
-            )));
+            return Err(Diagnostic::error(
+                "E0205",
+                format!("/...(...{arg}...) - bare identifier '{arg}' matches a parameter name"),
+            )
+            .at(Span::line(lineno).find(value.as_str()))
+            .help(format!("use ':{arg}' to pass the parameter value, or \"'{arg}'\" for a literal string")));
         }
     }
 
@@ -926,6 +1120,49 @@ fn has_self_transition(kase: &Case) -> bool {
     })
 }
 
+/// Copy each state's `entry_actions` ahead of every transition into it
+/// from another state (inside conditionals too), and for the first state
+/// after the function's own entry actions. `|>>` and `|>> :self` are
+/// self-loops and don't re-enter.
+fn lower_state_entry_actions(states: &mut [State], func_entry: &mut Vec<Command>) {
+    if states.iter().all(|s| s.entry_actions.is_empty()) {
+        return;
+    }
+    let entry: std::collections::HashMap<String, Vec<Command>> =
+        states.iter().map(|s| (s.name.clone(), s.entry_actions.clone())).collect();
+
+    fn splice(cmds: &mut Vec<Command>, from: &str, entry: &std::collections::HashMap<String, Vec<Command>>) {
+        let mut out = Vec::with_capacity(cmds.len());
+        for mut cmd in std::mem::take(cmds) {
+            if let Some(clauses) = &mut cmd.clauses {
+                for clause in clauses {
+                    splice(&mut clause.commands, from, entry);
+                }
+            }
+            if cmd.ctype == "transition" {
+                let to = cmd.arg_str("value").unwrap_or("").trim().trim_start_matches(':').replace('-', "_");
+                if !to.is_empty() && to != from {
+                    out.extend(entry.get(&to).into_iter().flatten().cloned());
+                }
+            }
+            out.push(cmd);
+        }
+        *cmds = out;
+    }
+
+    if let Some(first) = states.first() {
+        func_entry.extend(first.entry_actions.iter().cloned());
+    }
+    for state in states.iter_mut() {
+        for kase in &mut state.cases {
+            splice(&mut kase.commands, &state.name, &entry);
+        }
+        if let Some(h) = &mut state.eof_handler {
+            splice(h, &state.name, &entry);
+        }
+    }
+}
+
 /// Infer expected closing delimiter from return cases.
 fn infer_expects(states: &[State]) -> (Option<String>, bool) {
     let mut return_cases: Vec<&Case> = vec![];
@@ -1036,6 +1273,7 @@ fn infer_locals(func: &ast::Function) -> Vec<String> {
     }
 
     for state in &func.states {
+        collect_locals_from_commands(&state.entry_actions, &mut locals);
         for kase in &state.cases {
             collect_locals_from_commands(&kase.commands, &mut locals);
         }
@@ -1374,9 +1612,9 @@ mod tests {
 |function[document] | m = OPEN
   |state[:main]
     |c['\n']    | ->                    |>>
-    |default    | st = /line(OPEN)      |>> :route
+    |default    | rc = /line(OPEN)      |>> :route
   |state[:route]
-    |if[st == FIN]  | m = FIN           |>> :main
+    |if[rc == FIN]  | m = FIN           |>> :main
     |default        |                   |>> :main
 
 |function[line:INT] :mode
@@ -1393,7 +1631,7 @@ mod tests {
         assert_eq!(call.ctype, "assign");
         assert_eq!(call.arg_str("expr"), Some("/line(1)"));
         // condition
-        assert_eq!(doc.states[1].cases[0].condition.as_deref(), Some("st == 0"));
+        assert_eq!(doc.states[1].cases[0].condition.as_deref(), Some("rc == 0"));
         // assignment inside the guarded case
         assert_eq!(doc.states[1].cases[0].commands[0].arg_str("expr"), Some("0"));
         // |return CONST -> numeric return_value
@@ -1435,7 +1673,7 @@ mod tests {
         let rec = crate::emit::rust::generate(&ir, &Default::default()).unwrap();
         assert!(rec.contains("self.parse_sfx(b\"$?\", b\"?\", on_event)"), "recursive assign-call args");
         assert!(rec.contains("std::borrow::Cow::Borrowed(tag)"), "recursive emit param payload");
-        let pd = crate::emit::rust_pushdown::generate(&ir, &Default::default());
+        let pd = crate::emit::rust_pushdown::generate(&ir, &Default::default()).unwrap();
         assert!(pd.contains("std::borrow::Cow::Borrowed(f.tag)"), "pushdown emit param payload");
     }
 
@@ -1465,10 +1703,197 @@ mod tests {
         assert!(st.newline_injected);
         let rec = crate::emit::rust::generate(&ir, &Default::default()).unwrap();
         assert!(rec.contains(r"self.scan_to3(b'\n', b'\\', q)"), "recursive scan args");
-        let pd = crate::emit::rust_pushdown::generate(&ir, &Default::default());
+        let pd = crate::emit::rust_pushdown::generate(&ir, &Default::default()).unwrap();
         assert!(pd.contains(r"self.scan_to3(b'\n', b'\\', f.q)"), "pushdown scan args");
     }
 
+    #[test]
+    fn unclosed_directive_declares_the_construct() {
+        let desc = r#"
+|parser p
+|type[Text] CONTENT
+|entry-point /doc
+|function[doc]
+  |state[:m]
+    |c['{'] | -> | /embed |>>
+    |default | ->         |>>
+|function[embed:Text]
+  |unclosed Embedded
+  |state[:m]
+    |c['}']  | ->        |return
+    |default | ->        |>>
+"#;
+        let ir = crate::build_ir_with(desc, "t.desc", crate::Frontend::OracleLexer).unwrap();
+        let embed = ir.functions.iter().find(|f| f.name == "embed").unwrap();
+        assert_eq!(embed.delimited_code.as_deref(), Some("UnclosedEmbedded"));
+        assert!(ir.custom_error_codes.contains(&"UnclosedEmbedded".to_string()));
+        let rec = crate::emit::rust::generate(&ir, &Default::default()).unwrap();
+        assert!(rec.contains("    UnclosedEmbedded,"), "ParseErrorCode variant");
+        assert!(rec.contains(r#"Borrowed(b"UnclosedEmbedded")"#), "recursive force-unwind");
+        let pd = crate::emit::rust_pushdown::generate(&ir, &Default::default()).unwrap();
+        assert!(pd.contains(r#"Borrowed(&b"UnclosedEmbedded"[..])"#), "pushdown force-unwind");
+    }
+
+    #[test]
+    fn build_reports_every_case_error() {
+        let desc = r#"
+|parser multi
+|entry-point /doc
+|function[doc] :p
+  |state[:main]
+    |c[abc|]    | ->                  |>>
+    |c['x']     | PREPEND(p)          |>>
+    |default    | ->[]                |>>
+|function[other]
+  |state[:main]
+    |c[a|b]     | ->                  |>>
+"#;
+        let m = crate::parse_with(desc, "t.desc", crate::Frontend::OracleLexer).unwrap();
+        let errs = super::IRBuilder::new(&m).build().unwrap_err();
+        let codes: Vec<&str> = errs.0.iter().map(|d| d.code).collect();
+        assert_eq!(codes, ["E0203", "E0204", "E0203"]);
+    }
+
+    #[test]
+    fn template_use_splices_rows_with_type_substituted() {
+        let head = "|parser p\n|type[Integer] CONTENT\n|type[Float] CONTENT\n|entry-point /v\n";
+        let templated = r#"
+|template[end] :T
+  |c['\n']  | TERM | T(USE_MARK) |return
+  |c[' ']   | TERM | T(USE_MARK) |return
+|function[v]
+  |state[:int]
+    |c[<0-9>] | ->   |>>
+    |use[end(Integer)]
+    |c['.']   | ->   |>> :frac
+    |default  |return
+  |state[:frac]
+    |use[end(Float)]
+    |default  | ->   |>>
+"#;
+        let expanded = r#"
+|function[v]
+  |state[:int]
+    |c[<0-9>] | ->   |>>
+    |c['\n']  | TERM | Integer(USE_MARK) |return
+    |c[' ']   | TERM | Integer(USE_MARK) |return
+    |c['.']   | ->   |>> :frac
+    |default  |return
+  |state[:frac]
+    |c['\n']  | TERM | Float(USE_MARK) |return
+    |c[' ']   | TERM | Float(USE_MARK) |return
+    |default  | ->   |>>
+"#;
+        let rows = |src: &str| {
+            let ir = crate::build_ir_with(&format!("{head}{src}"), "t.desc", crate::Frontend::OracleLexer).unwrap();
+            ir.functions[0]
+                .states
+                .iter()
+                .flat_map(|s| s.cases.iter().map(|c| (c.chars.clone(), c.commands.iter().map(|c| format!("{} {:?}", c.ctype, c.args)).collect::<Vec<_>>())))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rows(templated), rows(expanded));
+    }
+
+    #[test]
+    fn template_use_errors() {
+        let desc = r#"
+|parser p
+|type[Integer] CONTENT
+|entry-point /v
+|template[end] :T
+  |c[' '] | T(USE_MARK) |return
+|template[end] :T
+  |c[' '] | T(USE_MARK) |return
+|function[v]
+  |state[:a]
+    |use[nope(Integer)]
+    |use[end]
+    |default | -> |>>
+"#;
+        let m = crate::parse_with(desc, "t.desc", crate::Frontend::OracleLexer).unwrap();
+        let errs = super::IRBuilder::new(&m).build().unwrap_err();
+        let codes: Vec<&str> = errs.0.iter().map(|d| d.code).collect();
+        assert_eq!(codes, ["E0208", "E0206", "E0207"]);
+    }
+
+    #[test]
+    fn ends_expands_before_default_and_joins_scan_and_eof() {
+        let desc = r#"
+|parser p
+|type[Integer] CONTENT
+|entry-point /v
+|terminators[val_end] eof '\n' ' ' :close
+|function[v] :close
+  |state[:int]
+    |ends[val_end -> Integer]
+    |c['_']    | ->   |>>
+    |default   | ->   |>>
+"#;
+        let ir = crate::build_ir_with(desc, "t.desc", crate::Frontend::OracleLexer).unwrap();
+        let st = &ir.functions[0].states[0];
+        let rows: Vec<_> = st.cases.iter().map(|c| (c.chars.clone(), c.param_ref.clone())).collect();
+        let some = |s: &str| Some(vec![s.to_string()]);
+        assert_eq!(
+            rows,
+            [(some("_"), None), (some("\n"), None), (some(" "), None), (None, Some("close".to_string())), (None, None)]
+        );
+        let types: Vec<_> = st.cases[1].commands.iter().map(|c| c.ctype.as_str()).collect();
+        assert_eq!(types, ["inline_emit_mark", "return"]);
+        assert!(st.eof_handler.is_some());
+        assert_eq!(st.scan_chars, Some(vec!["_".to_string(), "\n".to_string(), " ".to_string()]));
+        assert_eq!(st.scan_params, ["close"]);
+
+        let bad = r#"
+|parser p
+|type[Integer] CONTENT
+|entry-point /v
+|terminators[a] eof ' '
+|terminators[a] ' '
+|function[v]
+  |state[:int]
+    |ends[nope -> Integer]
+    |ends[a -> Integer]
+    |eof       |return
+    |default   | ->   |>>
+"#;
+        let m = crate::parse_with(bad, "t.desc", crate::Frontend::OracleLexer).unwrap();
+        let errs = super::IRBuilder::new(&m).build().unwrap_err();
+        let codes: Vec<&str> = errs.0.iter().map(|d| d.code).collect();
+        assert_eq!(codes, ["E0211", "E0210", "E0212"]);
+    }
+
+    #[test]
+    fn state_entry_actions_run_on_entry_but_not_on_self_loops() {
+        let desc = r#"
+|parser p
+|type[Word] CONTENT
+|entry-point /doc
+|function[doc]
+  |state[:main] n = 0
+    |c[' ']      | ->   |>> :word
+    |default     | ->   |>>
+  |state[:word] MARK
+    |if[COL > 8] | ->   |>> :word
+    |c[' ']      | Word(USE_MARK) | ->  |>> :main
+    |default     | ->   |>>
+"#;
+        let ir = crate::build_ir_with(desc, "t.desc", crate::Frontend::OracleLexer).unwrap();
+        let doc = &ir.functions[0];
+        let types = |cmds: &[crate::ir::Command]| cmds.iter().map(|c| c.ctype.clone()).collect::<Vec<_>>();
+        // The first state is entered when the function starts.
+        assert_eq!(types(&doc.entry_actions), ["assign"]);
+        assert_eq!(types(&doc.states[1].entry_actions), ["mark"]);
+        assert_eq!(types(&doc.states[0].cases[0].commands), ["advance", "mark", "transition"]);
+        assert_eq!(types(&doc.states[1].cases[1].commands), ["inline_emit_mark", "advance", "assign", "transition"]);
+        // `|>> :word` from :word is a self-loop.
+        assert_eq!(types(&doc.states[1].cases[0].commands), ["advance", "transition"]);
+
+        let bad = desc.replace("|state[:word] MARK", "|state[:word] ->");
+        let errs = crate::parse_with(&bad, "t.desc", crate::Frontend::OracleLexer).unwrap_err();
+        assert_eq!(errs.0[0].code, "E0113");
+    }
+
     #[test]
     fn duplicate_const_rejected() {
         let desc = "|parser p\n|const[X] 1\n|const[X] 2\n|entry-point /f\n|function[f]\n  |state[:m]\n    |default | -> |>>\n";
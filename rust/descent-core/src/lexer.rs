@@ -15,7 +15,7 @@
 //! event parser (self-hosting bootstrap) can replace this module as long as it
 //! produces the same `Token`s.
 
-use std::fmt;
+use crate::diagnostic::{Diagnostic, Span};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
@@ -25,24 +25,6 @@ pub struct Token {
     pub lineno: usize,
 }
 
-#[derive(Debug)]
-pub struct LexerError {
-    pub message: String,
-    pub lineno: Option<usize>,
-    pub source_file: String,
-}
-
-impl fmt::Display for LexerError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.lineno {
-            Some(l) => write!(f, "{}:{}: {}", self.source_file, l, self.message),
-            None => write!(f, "{}: {}", self.source_file, self.message),
-        }
-    }
-}
-
-impl std::error::Error for LexerError {}
-
 pub struct Lexer<'a> {
     content: &'a str,
     source_file: String,
@@ -53,7 +35,7 @@ impl<'a> Lexer<'a> {
         Lexer { content, source_file: source_file.to_string() }
     }
 
-    pub fn tokenize(&self) -> Result<Vec<Token>, LexerError> {
+    pub fn tokenize(&self) -> Result<Vec<Token>, Diagnostic> {
         let stripped = strip_comments(self.content);
         let stripped_chars: Vec<char> = stripped.chars().collect();
 
@@ -171,18 +153,22 @@ fn split_lines_keep_nl(s: &str) -> Vec<&str> {
 ///
 /// `pub`: reader seam — the udon-core front-end re-splits sameline command
 /// tails (UDON Text runs containing pipes) with this exact splitter.
-pub fn split_on_pipes(content: &str, source_file: &str) -> Result<Vec<String>, LexerError> {
+pub fn split_on_pipes(content: &str, source_file: &str) -> Result<Vec<String>, Diagnostic> {
     let mut parts: Vec<String> = Vec::new();
     let mut current = String::new();
     let mut in_bracket = false;
     let mut in_quote: Option<char> = None;
     let mut prev_char: Option<char> = None;
     let mut lineno: usize = 1;
-    let mut quote_start_line: Option<usize> = None;
+    let mut column: usize = 0;
+    let mut quote_start: Option<(usize, usize)> = None;
 
     for c in content.chars() {
         if c == '\n' {
             lineno += 1;
+            column = 0;
+        } else {
+            column += 1;
         }
         match c {
             '\'' => {
@@ -191,7 +177,7 @@ pub fn split_on_pipes(content: &str, source_file: &str) -> Result<Vec<String>, L
                     in_quote = None;
                 } else if in_quote.is_none() {
                     in_quote = Some('\'');
-                    quote_start_line = Some(lineno);
+                    quote_start = Some((lineno, column));
                 }
             }
             '"' => {
@@ -200,7 +186,7 @@ pub fn split_on_pipes(content: &str, source_file: &str) -> Result<Vec<String>, L
                     in_quote = None;
                 } else if in_quote.is_none() {
                     in_quote = Some('"');
-                    quote_start_line = Some(lineno);
+                    quote_start = Some((lineno, column));
                 }
             }
             '[' => {
@@ -232,14 +218,11 @@ pub fn split_on_pipes(content: &str, source_file: &str) -> Result<Vec<String>, L
     }
 
     if let Some(q) = in_quote {
-        return Err(LexerError {
-            message: format!(
-                "Unterminated {} quote - opened but never closed",
-                if q == '\'' { "single" } else { "double" }
-            ),
-            lineno: quote_start_line,
-            source_file: source_file.to_string(),
-        });
+        let (line, col) = quote_start.unwrap_or((1, 1));
+        let kind = if q == '\'' { "single" } else { "double" };
+        return Err(Diagnostic::error("E0001", format!("Unterminated {kind} quote"))
+            .at(Span { file: source_file.to_string(), ..Span::line(line).column(col, 1) })
+            .help("the quote is opened here but never closed"));
     }
 
     if !current.is_empty() {
@@ -302,7 +285,7 @@ pub fn extract_bracketed_id(part: &[char]) -> (String, Option<usize>) {
 /// `pub`: reader seam — the udon-core front-end reconstructs descent *parts*
 /// from UDON events and feeds them through this shared decomposition, so
 /// tag-casing/id/rest quirks live in exactly one place.
-pub fn parse_part(part: &str, lineno: usize, source_file: &str) -> Result<Option<Token>, LexerError> {
+pub fn parse_part(part: &str, lineno: usize, source_file: &str) -> Result<Option<Token>, Diagnostic> {
     // Comment strip round 2, per line within the part.
     let mut kept_lines: Vec<String> = Vec::new();
     for (line_idx, line) in split_lines_keep_nl(part).iter().enumerate() {
@@ -342,14 +325,12 @@ pub fn parse_part(part: &str, lineno: usize, source_file: &str) -> Result<Option
         }
 
         if in_quote {
-            return Err(LexerError {
-                message: format!(
-                    "Unterminated single quote at column {}",
-                    quote_start_col.unwrap_or(0) + 1
-                ),
-                lineno: Some(lineno + line_idx),
-                source_file: source_file.to_string(),
-            });
+            // Underline from the quote to the end of the row.
+            let from: String = chars[quote_start_col.unwrap_or(0)..].iter().collect();
+            let span = Span::line(lineno + line_idx).find(from.trim_end());
+            return Err(Diagnostic::error("E0002", "Unterminated single quote")
+                .at(Span { file: source_file.to_string(), ..span })
+                .help("to match a literal quote use c[<SQ>] or c['\\'']"));
         }
 
         let kept: String = match comment_start {
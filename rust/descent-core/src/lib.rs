@@ -11,21 +11,28 @@
 //! (`Frontend::OracleLexer`), kept token-identical on the corpus.
 
 pub mod ast;
+pub mod callgraph;
 pub mod charclass;
 pub mod classify;
+pub mod diagnostic;
 pub mod dump;
 pub mod emit;
 pub mod ir;
 pub mod ir_builder;
 pub mod lexer;
+pub mod lint;
 pub mod parser;
 pub mod reader;
+pub mod sample;
+pub mod validate;
 
 pub use ast::Machine;
+pub use diagnostic::{Diagnostic, Diagnostics, Severity, Span};
 pub use ir::ParserIR;
-pub use ir_builder::{IRBuilder, ValidationError};
-pub use lexer::{Lexer, LexerError, Token};
-pub use parser::{ParseError, Parser};
+pub use ir_builder::IRBuilder;
+pub use lexer::{Lexer, Token};
+pub use parser::Parser;
+pub use validate::Validator;
 
 /// Front-end selection. `UdonCore` (default) tokenizes via the vendored
 /// stage-0 udon-core parser; `OracleLexer` is the hand-ported Ruby lexer,
@@ -37,46 +44,68 @@ pub enum Frontend {
     OracleLexer,
 }
 
-/// Content -> Tokens through the chosen front-end.
+/// Content -> Tokens through the chosen front-end, with its warnings
+/// (the reader's W00xx; the oracle lexer has none).
 pub fn tokenize(
     content: &str,
     source_file: &str,
     frontend: Frontend,
-) -> Result<Vec<Token>, LexerError> {
-    match frontend {
-        Frontend::UdonCore => {
-            reader::Reader::tokens(content.as_bytes(), source_file).map(|(tokens, _warnings)| tokens)
-        }
-        Frontend::OracleLexer => Lexer::new(content, source_file).tokenize(),
-    }
+) -> Result<(Vec<Token>, Vec<Diagnostic>), Diagnostic> {
+    let (tokens, warnings) = match frontend {
+        Frontend::UdonCore => reader::Reader::tokens(content.as_bytes(), source_file)?,
+        Frontend::OracleLexer => (Lexer::new(content, source_file).tokenize()?, Vec::new()),
+    };
+    Ok((tokens, warnings.into_iter().map(|d| d.in_file(source_file)).collect()))
 }
 
 /// Convenience: content -> AST (default front-end).
-pub fn parse(content: &str, source_file: &str) -> Result<Machine, Box<dyn std::error::Error>> {
+pub fn parse(content: &str, source_file: &str) -> Result<Machine, Diagnostics> {
     parse_with(content, source_file, Frontend::default())
 }
 
-/// Content -> AST through the chosen front-end.
-pub fn parse_with(
+/// Content -> AST through the chosen front-end. Diagnostics come back with
+/// `source_file` stamped on their spans; render them against `content`.
+/// Front-end warnings are left to `build_unvalidated`, like the validator's.
+pub fn parse_with(content: &str, source_file: &str, frontend: Frontend) -> Result<Machine, Diagnostics> {
+    let (tokens, _) = tokenize(content, source_file, frontend)?;
+    Parser::new(tokens).parse().map_err(|d| d.in_file(source_file))
+}
+
+/// Content -> IR without the validation gate, plus the front-end's warnings
+/// (what `descent-rs validate` uses, so warnings are reported alongside
+/// errors).
+pub fn build_unvalidated(
     content: &str,
     source_file: &str,
     frontend: Frontend,
-) -> Result<Machine, Box<dyn std::error::Error>> {
-    let tokens = tokenize(content, source_file, frontend)?;
-    Ok(Parser::new(tokens).parse()?)
+) -> Result<(ParserIR, Vec<Diagnostic>), Diagnostics> {
+    let (tokens, warnings) = tokenize(content, source_file, frontend)?;
+    let machine = Parser::new(tokens).parse().map_err(|d| d.in_file(source_file))?;
+    let ir = IRBuilder::new(&machine).build().map_err(|d| d.in_file(source_file))?;
+    Ok((ir, warnings))
 }
 
 /// Convenience: content -> target-neutral IR (default front-end).
-pub fn build_ir(content: &str, source_file: &str) -> Result<ParserIR, Box<dyn std::error::Error>> {
+pub fn build_ir(content: &str, source_file: &str) -> Result<ParserIR, Diagnostics> {
     build_ir_with(content, source_file, Frontend::default())
 }
 
-/// Content -> target-neutral IR through the chosen front-end.
-pub fn build_ir_with(
-    content: &str,
-    source_file: &str,
-    frontend: Frontend,
-) -> Result<ParserIR, Box<dyn std::error::Error>> {
+/// Content -> target-neutral IR through the chosen front-end. The IR is
+/// validated (`validate::Validator`) before it is returned: validation
+/// errors fail the build, warnings are left to `descent-rs validate`.
+pub fn build_ir_with(content: &str, source_file: &str, frontend: Frontend) -> Result<ParserIR, Diagnostics> {
     let machine = parse_with(content, source_file, frontend)?;
-    Ok(IRBuilder::new(&machine).build()?)
+    build_ir_from(&machine, source_file)
+}
+
+/// Already-parsed AST -> validated IR, for callers that also need the
+/// `Machine` (`descent-rs lint`): the second half of `build_ir_with`.
+pub fn build_ir_from(machine: &Machine, source_file: &str) -> Result<ParserIR, Diagnostics> {
+    let ir = IRBuilder::new(machine).build().map_err(|d| d.in_file(source_file))?;
+    let validator = Validator::new(&ir).validate();
+    if !validator.is_valid() {
+        let errors = validator.issues.into_iter().filter(Diagnostic::is_error).collect();
+        return Err(Diagnostics(errors).in_file(source_file));
+    }
+    Ok(ir)
 }
@@ -0,0 +1,377 @@
+//! Advisory grammar lints, run on demand by `descent-rs lint`. Unlike the
+//! validator these never block generation: each flags a construct that is
+//! legal but almost certainly a mistake.
+//!
+//! | code  | name                 | fires on                                  |
+//! |-------|----------------------|-------------------------------------------|
+//! | L0001 | `unreachable_state`  | a state no `|>>` chain from the function's first state reaches |
+//! | L0002 | `non_advancing_loop` | a state whose every case self-loops without consuming input |
+//! | L0003 | `shadowed_case`      | a case an earlier unconditional case already matches |
+//! | L0004 | `unused_keywords`    | a `|keywords` map no `KEYWORDS(name)` looks up |
+//! | L0005 | `unused_type`        | a `|type` that is never returned or emitted |
+//! | L0006 | `unused_const`       | a `|const` never referenced               |
+//!
+//! L0000 is an `|allow` naming no lint. The code is the diagnostic code;
+//! the name is what `|allow[name]` takes. In the grammar header it silences
+//! a lint everywhere; inside a `|function` it silences it for that function
+//! only (grammar-wide lints — the `unused_*` ones — only honour the header
+//! form). Needs the AST as well as the IR: consts and allows are gone once
+//! the IR is built.
+
+use crate::ast::{self, Machine};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::ir::{Case, Command, Function, ParserIR, State};
+use crate::validate::{normalize_state_name, walk};
+use std::collections::HashSet;
+
+/// Every lint as (code, name); the name is what `|allow[...]` accepts.
+pub const LINTS: &[(&str, &str)] = &[
+    ("L0001", "unreachable_state"),
+    ("L0002", "non_advancing_loop"),
+    ("L0003", "shadowed_case"),
+    ("L0004", "unused_keywords"),
+    ("L0005", "unused_type"),
+    ("L0006", "unused_const"),
+];
+
+/// An `|allow` naming no lint (not itself silenceable).
+pub const UNKNOWN_LINT: &str = "L0000";
+
+/// The name of the lint with diagnostic code `code`.
+pub fn lint_name(code: &str) -> Option<&'static str> {
+    LINTS.iter().find(|(c, _)| *c == code).map(|(_, name)| *name)
+}
+
+/// A finding of lint `name`, coded from `LINTS`.
+fn warning(name: &str, message: String) -> Diagnostic {
+    let code = LINTS.iter().find(|(_, n)| *n == name).map_or(UNKNOWN_LINT, |(c, _)| *c);
+    Diagnostic::warning(code, message)
+}
+
+/// Run every lint not allowed by the grammar and return the findings
+/// (all warnings), in grammar order per lint.
+pub fn lint(ast: &Machine, ir: &ParserIR) -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+    let global: HashSet<&str> = ast.allows.iter().map(|a| a.lint.as_str()).collect();
+    let allows = ast.allows.iter().chain(ast.functions.iter().flat_map(|f| &f.allows));
+    let names: Vec<&str> = LINTS.iter().map(|(_, name)| *name).collect();
+    for a in allows.filter(|a| !names.contains(&a.lint.as_str())) {
+        out.push(
+            Diagnostic::warning(UNKNOWN_LINT, format!("unknown lint '{}' in |allow", a.lint))
+                .at(Span::line(a.lineno).find(a.lint.as_str()))
+                .help(format!("known lints: {}", names.join(", "))),
+        );
+    }
+
+    for func in &ir.functions {
+        let mut allowed = global.clone();
+        if let Some(f) = ast.functions.iter().find(|f| f.name == func.name) {
+            allowed.extend(f.allows.iter().map(|a| a.lint.as_str()));
+        }
+        let mut found = Vec::new();
+        unreachable_states(func, &mut found);
+        non_advancing_loops(func, &mut found);
+        shadowed_cases(func, &mut found);
+        out.extend(found.into_iter().filter(|d| lint_name(d.code).is_none_or(|n| !allowed.contains(n))));
+    }
+
+    let mut found = Vec::new();
+    unused_keywords(ir, &mut found);
+    unused_types(ir, &mut found);
+    unused_consts(ast, &mut found);
+    out.extend(found.into_iter().filter(|d| lint_name(d.code).is_none_or(|n| !global.contains(n))));
+
+    for d in &mut out {
+        if let Some(name) = lint_name(d.code) {
+            d.help = Some(format!("`|allow[{name}]` to silence"));
+        }
+    }
+    out
+}
+
+/// Commands of every case, state EOF handler and the function's own.
+fn all_commands(func: &Function) -> impl Iterator<Item = &Vec<Command>> {
+    func.states
+        .iter()
+        .flat_map(|s| s.cases.iter().map(|c| &c.commands).chain(s.eof_handler.iter()))
+        .chain(func.eof_handler.iter())
+        .chain(std::iter::once(&func.entry_actions))
+}
+
+// ========== Per-function lints ==========
+
+fn unreachable_states(func: &Function, out: &mut Vec<Diagnostic>) {
+    let Some(first) = func.states.first() else {
+        return;
+    };
+    let index = |name: &str| func.states.iter().position(|s| normalize_state_name(&s.name) == name);
+    let mut seen = vec![false; func.states.len()];
+    seen[0] = true;
+    let mut queue = vec![first];
+    while let Some(state) = queue.pop() {
+        let cmds = state.cases.iter().map(|c| &c.commands).chain(state.eof_handler.iter());
+        let mut targets = Vec::new();
+        for c in cmds {
+            walk(c, &mut |cmd| {
+                if let Some(t) = cmd.arg_str("value").filter(|t| cmd.ctype == "transition" && !t.is_empty()) {
+                    targets.push(normalize_state_name(t));
+                }
+            });
+        }
+        for i in targets.iter().filter_map(|t| index(t)) {
+            if !seen[i] {
+                seen[i] = true;
+                queue.push(&func.states[i]);
+            }
+        }
+    }
+    for (state, _) in func.states.iter().zip(&seen).filter(|(_, s)| !**s) {
+        out.push(
+            warning("unreachable_state", format!("state '{}' of /{} is never entered", state.name, func.name))
+                .at(Span::line(state.lineno).find(state.name.as_str()))
+                .label(Span::line(first.lineno).find(first.name.as_str()), "function starts here"),
+        );
+    }
+}
+
+/// A case that neither consumes input nor leaves the state: running it
+/// puts the machine back where it was.
+fn spins(state: &State, kase: &Case) -> bool {
+    let mut moves = false;
+    walk(&kase.commands, &mut |cmd| {
+        moves |= match cmd.ctype.as_str() {
+            "advance" | "advance_to" | "scan" | "call" | "keywords_lookup" | "keywords_try" | "error"
+            | "return" => true,
+            "transition" => cmd
+                .arg_str("value")
+                .is_some_and(|t| !t.is_empty() && normalize_state_name(t) != normalize_state_name(&state.name)),
+            _ => false,
+        };
+    });
+    !moves
+}
+
+fn non_advancing_loops(func: &Function, out: &mut Vec<Diagnostic>) {
+    for state in &func.states {
+        if state.cases.is_empty() || !state.cases.iter().all(|k| spins(state, k)) {
+            continue;
+        }
+        let mut d = warning(
+            "non_advancing_loop",
+            format!("state '{}' of /{} loops forever: no case consumes input or leaves", state.name, func.name),
+        )
+        .at(Span::line(state.lineno).find(state.name.as_str()));
+        for kase in &state.cases {
+            d = d.label(Span::line(kase.lineno), "neither advances nor leaves");
+        }
+        out.push(d);
+    }
+}
+
+/// The bytes a case matches, when they are statically known: literal chars
+/// only (unicode classes and `:param` refs are runtime checks).
+fn known_chars(kase: &Case) -> Option<HashSet<&str>> {
+    if kase.special_class.is_some() || kase.param_ref.is_some() {
+        return None;
+    }
+    kase.chars.as_ref().map(|c| c.iter().map(String::as_str).collect())
+}
+
+fn shadowed_cases(func: &Function, out: &mut Vec<Diagnostic>) {
+    for state in &func.states {
+        for (j, later) in state.cases.iter().enumerate() {
+            let shadow = state.cases[..j].iter().find(|earlier| {
+                if earlier.condition.is_some() || earlier.param_ref.is_some() {
+                    return false;
+                }
+                if earlier.is_default() {
+                    return true;
+                }
+                let Some(theirs) = earlier.chars.as_ref() else {
+                    return false;
+                };
+                let theirs: HashSet<&str> = theirs.iter().map(String::as_str).collect();
+                known_chars(later).is_some_and(|mine| mine.is_subset(&theirs))
+            });
+            if let Some(earlier) = shadow {
+                out.push(
+                    warning("shadowed_case", format!("case in '{}' of /{} can never match", state.name, func.name))
+                        .at(Span::line(later.lineno))
+                        .label(Span::line(earlier.lineno), "every input it matches is taken here first"),
+                );
+            }
+        }
+    }
+}
+
+// ========== Grammar-wide lints ==========
+
+fn unused_keywords(ir: &ParserIR, out: &mut Vec<Diagnostic>) {
+    let mut used = HashSet::new();
+    for func in &ir.functions {
+        for cmds in all_commands(func) {
+            walk(cmds, &mut |cmd| {
+                if cmd.ctype == "keywords_lookup" || cmd.ctype == "keywords_try" {
+                    used.extend(cmd.arg_str("name").map(str::to_string));
+                }
+            });
+        }
+    }
+    for kw in ir.keywords.iter().filter(|k| !used.contains(&k.name)) {
+        out.push(
+            warning("unused_keywords", format!("keywords map '{}' is never looked up", kw.name))
+                .at(Span::line(kw.lineno).find(kw.name.as_str())),
+        );
+    }
+}
+
+fn unused_types(ir: &ParserIR, out: &mut Vec<Diagnostic>) {
+    let mut used: HashSet<String> = ir.functions.iter().filter_map(|f| f.return_type.clone()).collect();
+    used.extend(ir.keywords.iter().flat_map(|k| k.mappings.iter().map(|m| m.event_type.clone())));
+    for func in &ir.functions {
+        for cmds in all_commands(func) {
+            walk(cmds, &mut |cmd| {
+                let named = match cmd.ctype.as_str() {
+                    "emit" => cmd.arg_str("value"),
+                    "return" => cmd.arg_str("emit_type"),
+                    t if t.starts_with("inline_emit_") => cmd.arg_str("type"),
+                    _ => None,
+                };
+                if let Some(t) = named {
+                    let base = t.strip_suffix("Start").or_else(|| t.strip_suffix("End")).unwrap_or(t);
+                    used.insert(t.to_string());
+                    used.insert(base.to_string());
+                }
+            });
+        }
+    }
+    for ty in ir.types.iter().filter(|t| !used.contains(&t.name)) {
+        out.push(
+            warning("unused_type", format!("type '{}' is never returned or emitted", ty.name))
+                .at(Span::line(ty.lineno).find(ty.name.as_str())),
+        );
+    }
+}
+
+/// Const references survive only in the AST (`IRBuilder` substitutes them
+/// away), at the same sites `substitute_consts` rewrites.
+fn unused_consts(ast: &Machine, out: &mut Vec<Diagnostic>) {
+    let mut texts: Vec<&str> = Vec::new();
+    fn collect<'a>(cmds: &'a [ast::Command], texts: &mut Vec<&'a str>) {
+        for cmd in cmds {
+            match cmd {
+                ast::Command::Conditional { clauses, .. } => {
+                    for clause in clauses {
+                        texts.extend(clause.condition.as_deref());
+                        collect(&clause.commands, texts);
+                    }
+                }
+                ast::Command::Cmd { kind, .. } => {
+                    use ast::CmdKind::*;
+                    match kind {
+                        Assign { expr, .. } | AddAssign { expr, .. } | SubAssign { expr, .. } => texts.push(expr),
+                        Call(v) | Return(v) => texts.push(v),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+    for func in &ast.functions {
+        collect(&func.entry_actions, &mut texts);
+        if let Some(h) = &func.eof_handler {
+            collect(&h.commands, &mut texts);
+        }
+        for state in &func.states {
+            collect(&state.entry_actions, &mut texts);
+            for kase in &state.cases {
+                texts.extend(kase.condition.as_deref());
+                collect(&kase.commands, &mut texts);
+            }
+            if let Some(h) = &state.eof_handler {
+                collect(&h.commands, &mut texts);
+            }
+        }
+    }
+    let words: HashSet<&str> = texts
+        .iter()
+        .flat_map(|t| t.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_')))
+        .collect();
+    for c in ast.consts.iter().filter(|c| !words.contains(c.name.as_str())) {
+        out.push(
+            warning("unused_const", format!("const '{}' is never referenced", c.name))
+                .at(Span::line(c.lineno).find(c.name.as_str())),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lints(desc: &str) -> Vec<(&'static str, Option<usize>)> {
+        let m = crate::parse_with(desc, "t.desc", crate::Frontend::OracleLexer).unwrap();
+        let ir = crate::IRBuilder::new(&m).build().unwrap();
+        lint(&m, &ir).iter().map(|d| (d.code, d.line())).collect()
+    }
+
+    #[test]
+    fn reports_each_lint() {
+        let desc = "\
+|parser t
+|type[Text] CONTENT
+|type[Spare] CONTENT
+|const[LIMIT] 4
+|entry-point /doc
+|function[doc:Text]
+  |state[:main]
+    |c[ab]    | ->  |>>
+    |c['a']   | ->  |>>
+    |default  | ->  |return
+  |state[:orphan]
+    |default  |>>
+    |c['x']   |>>
+|keywords[words]
+  | yes => Text
+";
+        assert_eq!(
+            lints(desc),
+            vec![
+                ("L0001", Some(11)),
+                ("L0002", Some(11)),
+                ("L0003", Some(9)),
+                ("L0003", Some(13)),
+                ("L0004", Some(14)),
+                ("L0005", Some(3)),
+                ("L0006", Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn allow_silences_globally_and_per_function() {
+        let desc = "\
+|parser t
+|allow[unused_type]
+|allow[no_such_lint]
+|type[Spare] CONTENT
+|entry-point /doc
+|function[doc]
+  |allow[unreachable_state]
+  |state[:main]
+    |default  | ->  |return
+  |state[:orphan]
+    |default  | ->  |return
+|function[other]
+  |state[:main]
+    |default  | ->  |return
+  |state[:orphan]
+    |default  | ->  |return
+";
+        assert_eq!(lints(desc), vec![("L0000", Some(3)), ("L0001", Some(15))]);
+        let m = crate::parse_with(desc, "t.desc", crate::Frontend::OracleLexer).unwrap();
+        let ir = crate::IRBuilder::new(&m).build().unwrap();
+        let found = lint(&m, &ir);
+        assert_eq!(found[1].help.as_deref(), Some("`|allow[unreachable_state]` to silence"));
+    }
+}
@@ -1,20 +1,28 @@
 //! Builds AST from the token stream. Port of `lib/descent/parser.rb`.
 
 use crate::ast::*;
-use crate::lexer::{re, Token};
-
-#[derive(Debug)]
-pub struct ParseError(pub String);
-
-impl std::fmt::Display for ParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
-    }
+use crate::diagnostic::{Diagnostic, Diagnostics, Span};
+use crate::lexer::{parse_part, re, Token};
+
+/// Error recovery: after a diagnostic, skip to the next token with one of
+/// these tags and carry on, so one run reports every broken declaration.
+const RESYNC_TOP: &[&str] =
+    &["parser", "entry-point", "type", "const", "function", "keywords", "template", "terminators", "allow"];
+const RESYNC_FUNCTION: &[&str] =
+    &["state", "function", "type", "const", "keywords", "template", "terminators", "allow", "unclosed", "mixed"];
+
+const STRUCTURAL: &[&str] =
+    &["function", "type", "const", "state", "keywords", "template", "terminators", "allow", "unclosed", "mixed"];
+const CASE_KEYWORDS: &[&str] = &["c", "default", "eof", "if", "use", "ends"];
+
+/// The body of a state or template: its cases, `|eof`, `|use` and `|ends` rows.
+struct Rows {
+    cases: Vec<Case>,
+    eof_handler: Option<EOFHandler>,
+    uses: Vec<TemplateUse>,
+    ends: Vec<StateEnds>,
 }
-impl std::error::Error for ParseError {}
 
-const STRUCTURAL: &[&str] = &["function", "type", "const", "state", "keywords"];
-const CASE_KEYWORDS: &[&str] = &["c", "default", "eof", "if"];
 const CHAR_CLASSES: &[&str] = &[
     "letter", "label_cont", "digit", "hex_digit", "ws", "nl", "xid_start", "xid_cont",
     "xlbl_start", "xlbl_cont",
@@ -51,11 +59,23 @@ fn inline_command_token(token: &Token) -> bool {
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
+    errors: Vec<Diagnostic>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, pos: 0 }
+        Parser { tokens, pos: 0, errors: Vec::new() }
+    }
+
+    /// Record `d` and skip ahead to the next `resync` token.
+    fn recover(&mut self, d: Diagnostic, resync: &[&str]) {
+        self.errors.push(d);
+        while let Some(t) = self.current() {
+            if resync.contains(&t.tag.as_str()) {
+                break;
+            }
+            self.advance();
+        }
     }
 
     fn current(&self) -> Option<&Token> {
@@ -66,13 +86,18 @@ impl Parser {
         self.pos += 1;
     }
 
-    pub fn parse(&mut self) -> Result<Machine, ParseError> {
+    /// Parse the whole token stream, recovering at `|function` / `|state`
+    /// boundaries; every diagnostic is returned if any were raised.
+    pub fn parse(&mut self) -> Result<Machine, Diagnostics> {
         let mut name = None;
         let mut entry_point = None;
         let mut types = Vec::new();
         let mut consts = Vec::new();
         let mut functions = Vec::new();
         let mut keywords = Vec::new();
+        let mut templates = Vec::new();
+        let mut terminators = Vec::new();
+        let mut allows = Vec::new();
 
         while let Some(token) = self.current() {
             match token.tag.as_str() {
@@ -85,19 +110,37 @@ impl Parser {
                     self.advance();
                 }
                 "type" => types.push(self.parse_type()),
-                "const" => consts.push(self.parse_const()?),
-                "function" => functions.push(self.parse_function()?),
-                "keywords" => keywords.push(self.parse_keywords()?),
+                "allow" => allows.push(self.parse_allow()),
+                "const" => match self.parse_const() {
+                    Ok(c) => consts.push(c),
+                    Err(d) => self.recover(d, RESYNC_TOP),
+                },
+                "function" => functions.push(self.parse_function()),
+                "keywords" => match self.parse_keywords() {
+                    Ok(k) => keywords.push(k),
+                    Err(d) => self.recover(d, RESYNC_TOP),
+                },
+                "template" => match self.parse_template() {
+                    Ok(t) => templates.push(t),
+                    Err(d) => self.recover(d, RESYNC_TOP),
+                },
+                "terminators" => match self.parse_terminators() {
+                    Ok(t) => terminators.push(t),
+                    Err(d) => self.recover(d, RESYNC_TOP),
+                },
                 _ => {
-                    return Err(ParseError(format!(
-                        "Line {}: Unknown top-level declaration '{}'. Expected: parser, entry-point, type, function, or keywords",
-                        token.lineno, token.tag
-                    )))
+                    let d = Diagnostic::error("E0101", format!("Unknown top-level declaration '{}'", token.tag))
+                        .at(Span::line(token.lineno).find(token.tag.as_str()))
+                        .help("expected parser, entry-point, type, const, function, keywords, template, or terminators");
+                    self.recover(d, RESYNC_TOP);
                 }
             }
         }
 
-        Ok(Machine { name, entry_point, types, consts, functions, keywords })
+        if !self.errors.is_empty() {
+            return Err(Diagnostics(std::mem::take(&mut self.errors)));
+        }
+        Ok(Machine { name, entry_point, types, consts, functions, keywords, templates, terminators, allows })
     }
 
     fn parse_type(&mut self) -> TypeDecl {
@@ -115,33 +158,31 @@ impl Parser {
     /// `|const[NAME] <int>` — named integer constant (mirrors `|type`'s
     /// id-in-brackets, value-in-rest shape). NAME must be SCREAMING_CASE
     /// and not shadow a builtin (COL/LINE/PREV).
-    fn parse_const(&mut self) -> Result<ConstDecl, ParseError> {
+    fn parse_const(&mut self) -> Result<ConstDecl, Diagnostic> {
         let token = self.current().unwrap().clone();
         self.advance();
         let name = token.id.trim().to_string();
         if !re(r"^[A-Z][A-Z0-9_]*$").is_match(&name) {
-            return Err(ParseError(format!(
-                "Line {}: const name '{}' must be SCREAMING_CASE (e.g. |const[OPEN] 1)",
-                token.lineno, name
-            )));
+            return Err(Diagnostic::error("E0102", format!("const name '{name}' must be SCREAMING_CASE"))
+                .at(Span::line(token.lineno).find(name.as_str()))
+                .help("e.g. |const[OPEN] 1"));
         }
         if ["COL", "LINE", "PREV"].contains(&name.as_str()) {
-            return Err(ParseError(format!(
-                "Line {}: const name '{}' shadows a builtin variable",
-                token.lineno, name
-            )));
+            return Err(Diagnostic::error("E0103", format!("const name '{name}' shadows a builtin variable"))
+                .at(Span::line(token.lineno).find(name.as_str())));
         }
         let value_word = token.rest.split_whitespace().next().unwrap_or("");
         let value: i64 = value_word.parse().map_err(|_| {
-            ParseError(format!(
-                "Line {}: const '{}' needs an integer value, got '{}'",
-                token.lineno, name, token.rest.trim()
-            ))
+            Diagnostic::error(
+                "E0104",
+                format!("const '{}' needs an integer value, got '{}'", name, token.rest.trim()),
+            )
+            .at(Span::line(token.lineno).find(token.rest.trim()))
         })?;
         Ok(ConstDecl { name, value, lineno: token.lineno })
     }
 
-    fn parse_keywords(&mut self) -> Result<Keywords, ParseError> {
+    fn parse_keywords(&mut self) -> Result<Keywords, Diagnostic> {
         let token = self.current().unwrap().clone();
         let name = token.id;
         let rest = token.rest;
@@ -176,17 +217,21 @@ impl Parser {
                 });
                 self.advance();
             } else {
-                return Err(ParseError(format!(
-                    "Line {}: Unknown keyword mapping format: '{}' rest='{}'",
-                    t.lineno, t.tag, t.rest
-                )));
+                return Err(Diagnostic::error(
+                    "E0105",
+                    format!("Unknown keyword mapping format: '{}' rest='{}'", t.tag, t.rest),
+                )
+                .at(Span::line(t.lineno))
+                .help("write mappings as `| keyword => EventType`"));
             }
         }
 
         Ok(Keywords { name, fallback, mappings, lineno })
     }
 
-    fn parse_function(&mut self) -> Result<Function, ParseError> {
+    /// Errors inside the body are recorded and skipped to the next `|state`,
+    /// so a function always comes back (minus its broken parts).
+    fn parse_function(&mut self) -> Function {
         let token = self.current().unwrap().clone();
         // Quirk mirror: Ruby `name, rtype = id.split(':')` silently DROPS any
         // third-and-later colon segment ("a:b:c" -> rtype "b"). Ledgered.
@@ -203,49 +248,189 @@ impl Parser {
         let mut states = Vec::new();
         let mut eof_handler = None;
         let mut entry_actions = Vec::new();
+        let mut allows = Vec::new();
+        let mut unclosed: Option<String> = None;
+        let mut mixed = false;
 
         while let Some(t) = self.current() {
-            if ["function", "type", "const", "keywords"].contains(&t.tag.as_str()) {
+            if ["function", "type", "const", "keywords", "template", "terminators"].contains(&t.tag.as_str()) {
                 break;
             }
-            match t.tag.as_str() {
-                "state" => states.push(self.parse_state()?),
-                "eof" => eof_handler = Some(self.parse_eof_handler()?),
-                "if" => entry_actions.push(self.parse_conditional()?),
+            let start = self.pos;
+            let result = match t.tag.as_str() {
+                "state" => self.parse_state().map(|s| states.push(s)),
+                "allow" => {
+                    allows.push(self.parse_allow());
+                    Ok(())
+                }
+                "eof" => self.parse_eof_handler().map(|h| eof_handler = Some(h)),
+                "unclosed" => {
+                    let t = t.clone();
+                    self.advance();
+                    let construct = t.rest.trim();
+                    if !re(r"^[A-Z][0-9A-Za-z]*$").is_match(construct) {
+                        Err(Diagnostic::error("E0109", format!("|unclosed expects a PascalCase construct name, got '{construct}'"))
+                            .at(Span::line(t.lineno))
+                            .help("e.g. |unclosed Embedded (warns UnclosedEmbedded at EOF)"))
+                    } else if unclosed.is_some() {
+                        Err(Diagnostic::error("E0110", format!("function '{name}' declares |unclosed more than once"))
+                            .at(Span::line(t.lineno).find(construct))
+                            .label(Span::line(lineno), "in this function"))
+                    } else {
+                        unclosed = Some(construct.to_string());
+                        Ok(())
+                    }
+                }
+                "mixed" => {
+                    mixed = true;
+                    self.advance();
+                    Ok(())
+                }
+                "if" => self.parse_conditional().map(|c| entry_actions.push(c)),
                 _ => {
                     let t = t.clone();
                     if inline_command_token(&t) {
-                        entry_actions.push(parse_command(&t)?);
-                        self.advance();
+                        parse_command(&t).map(|c| {
+                            entry_actions.push(c);
+                            self.advance();
+                        })
                     } else {
-                        return Err(ParseError(format!(
-                            "Line {}: Unexpected token '{}' inside function. Expected: state, eof, if, or inline command (like 'var = expr' or 'MARK')",
-                            t.lineno, t.tag
-                        )));
+                        Err(Diagnostic::error("E0106", format!("Unexpected token '{}' inside function", t.tag))
+                            .at(Span::line(t.lineno).find(t.tag.as_str()))
+                            .label(Span::line(lineno), "in this function")
+                            .help("expected state, eof, if, unclosed, mixed, or an inline command (like 'var = expr' or 'MARK')"))
                     }
                 }
+            };
+            if let Err(d) = result {
+                if self.pos == start {
+                    self.advance(); // always make progress past the failing token
+                }
+                self.recover(d, RESYNC_FUNCTION);
             }
         }
 
-        Ok(Function {
+        Function {
             name: name.replace('-', "_"),
             return_type: rtype,
             params,
             states,
             eof_handler,
             entry_actions,
+            allows,
+            unclosed,
+            mixed,
             lineno,
-        })
+        }
+    }
+
+    fn parse_allow(&mut self) -> Allow {
+        let token = self.current().unwrap().clone();
+        self.advance();
+        Allow { lint: token.id.trim().to_string(), lineno: token.lineno }
     }
 
-    fn parse_state(&mut self) -> Result<State, ParseError> {
+    fn parse_state(&mut self) -> Result<State, Diagnostic> {
         let token = self.current().unwrap().clone();
         let name = token.id.replace('-', "_").replace(':', "");
         let lineno = token.lineno;
         self.advance();
 
+        let entry_actions = match token.rest.trim() {
+            "" => vec![],
+            rest => vec![parse_state_entry_action(rest, &name, lineno)?],
+        };
+        let Rows { cases, eof_handler, uses, ends } = self.parse_rows(lineno, "in this state")?;
+        Ok(State { name, cases, eof_handler, entry_actions, uses, ends, lineno })
+    }
+
+    /// `|template[name] :T ...` — a state body without a state (params are
+    /// captured like a function's).
+    fn parse_template(&mut self) -> Result<Template, Diagnostic> {
+        let token = self.current().unwrap().clone();
+        let name = token.id.trim().to_string();
+        let params: Vec<String> = re(r":(\w+)").captures_iter(&token.rest).map(|c| c[1].to_string()).collect();
+        let lineno = token.lineno;
+        self.advance();
+
+        let Rows { cases, eof_handler, uses, ends } = self.parse_rows(lineno, "in this template")?;
+        if let Some(e) = ends.first() {
+            return Err(Diagnostic::error("E0112", format!("|ends[{} -> {}] inside template '{name}'", e.set, e.event_type))
+                .at(Span::line(e.lineno).find(e.set.as_str()))
+                .label(Span::line(lineno), "in this template")
+                .help("write |ends in each state that uses the template"));
+        }
+        if let Some(u) = uses.first() {
+            return Err(Diagnostic::error("E0112", format!("|use[{}] inside template '{name}'", u.name))
+                .at(Span::line(u.lineno).find(u.name.as_str()))
+                .label(Span::line(lineno), "in this template")
+                .help("templates don't nest; list the shared rows directly"));
+        }
+        Ok(Template { name, params, cases, eof_handler, lineno })
+    }
+
+    /// `|use[name(Arg, ...)]` (or `|use[name]` for a template without
+    /// params), spliced in before the next row parsed.
+    fn parse_use(&mut self, at: usize) -> Result<TemplateUse, Diagnostic> {
+        let token = self.current().unwrap().clone();
+        self.advance();
+        let Some(c) = re(r"^\s*(\w+)\s*(?:\((.*)\))?\s*$").captures(&token.id) else {
+            return Err(Diagnostic::error("E0111", format!("malformed |use[{}]", token.id))
+                .at(Span::line(token.lineno).find(token.id.as_str()))
+                .help("e.g. |use[val_end(Integer)]"));
+        };
+        let args = c.get(2).map_or(vec![], |a| a.as_str().split(',').map(|s| s.trim().to_string()).collect());
+        Ok(TemplateUse { name: c[1].to_string(), args, at, lineno: token.lineno })
+    }
+
+    /// `|ends[set -> Type]`, expanded against the `|terminators` set by the
+    /// IR builder.
+    fn parse_ends(&mut self) -> Result<StateEnds, Diagnostic> {
+        let token = self.current().unwrap().clone();
+        self.advance();
+        let Some(c) = re(r"^\s*(\w+)\s*->\s*(\w+)\s*$").captures(&token.id) else {
+            return Err(Diagnostic::error("E0114", format!("malformed |ends[{}]", token.id))
+                .at(Span::line(token.lineno).find(token.id.as_str()))
+                .help("e.g. |ends[val_end -> Integer]"));
+        };
+        Ok(StateEnds { set: c[1].to_string(), event_type: c[2].to_string(), lineno: token.lineno })
+    }
+
+    /// `|terminators[name] eof '\n' ' ' :bracket` — members are `eof`,
+    /// quoted chars, `<class>`es or `:param`s, whitespace-separated.
+    fn parse_terminators(&mut self) -> Result<TerminatorSet, Diagnostic> {
+        let token = self.current().unwrap().clone();
+        self.advance();
+        let name = token.id.trim().to_string();
+        let mut members = Vec::new();
+        for m in re(r"'(?:\\.|[^'\\])*'|<[^>]*>|\S+").find_iter(&token.rest) {
+            let m = m.as_str();
+            if !(m == "eof" || m.starts_with('\'') || m.starts_with('<') || re(r"^:\w+$").is_match(m)) {
+                return Err(Diagnostic::error("E0115", format!("'{m}' is not a terminator"))
+                    .at(Span::line(token.lineno).find(m))
+                    .help("members are eof, quoted chars ('\\n' ' '), <classes> or :params"));
+            }
+            members.push(m.to_string());
+        }
+        if members.is_empty() {
+            return Err(Diagnostic::error("E0115", format!("terminator set '{name}' is empty"))
+                .at(Span::line(token.lineno).find(name.as_str()))
+                .help("e.g. |terminators[val_end] eof '\\n' ' ' :bracket"));
+        }
+        Ok(TerminatorSet { name, members, lineno: token.lineno })
+    }
+
+    /// Case rows up to the next structural token: the body of a state or a
+    /// template. `label` names the owner (at `owner_lineno`) in diagnostics.
+    fn parse_rows(
+        &mut self,
+        owner_lineno: usize,
+        label: &str,
+    ) -> Result<Rows, Diagnostic> {
         let mut cases = Vec::new();
         let mut eof_handler = None;
+        let mut uses = Vec::new();
+        let mut ends = Vec::new();
 
         while let Some(t) = self.current() {
             if STRUCTURAL.contains(&t.tag.as_str()) {
@@ -260,25 +445,29 @@ impl Parser {
                 "default" => cases.push(self.parse_case(None)?),
                 "eof" => eof_handler = Some(self.parse_eof_handler()?),
                 "if" => cases.push(self.parse_if_case()?),
+                "use" => uses.push(self.parse_use(cases.len())?),
+                "ends" => ends.push(self.parse_ends()?),
                 _ => {
                     if CHAR_CLASSES.contains(&tag.as_str()) {
                         cases.push(self.parse_case(Some(tag.to_uppercase()))?);
                     } else if command_like(&tag) {
                         cases.push(self.parse_bare_action_case()?);
                     } else {
-                        return Err(ParseError(format!(
-                            "Line {}: Unknown token in state: '{}' (not a case starter or command)",
-                            t.lineno, tag
-                        )));
+                        return Err(Diagnostic::error(
+                            "E0107",
+                            format!("Unknown token in state: '{tag}' (not a case starter or command)"),
+                        )
+                        .at(Span::line(t.lineno).find(tag.as_str()))
+                        .label(Span::line(owner_lineno), label));
                     }
                 }
             }
         }
 
-        Ok(State { name, cases, eof_handler, lineno })
+        Ok(Rows { cases, eof_handler, uses, ends })
     }
 
-    fn parse_case(&mut self, chars_str: Option<String>) -> Result<Case, ParseError> {
+    fn parse_case(&mut self, chars_str: Option<String>) -> Result<Case, Diagnostic> {
         let token = self.current().unwrap().clone();
         let lineno = token.lineno;
         self.advance();
@@ -303,7 +492,7 @@ impl Parser {
     }
 
     /// A bare action case starts with a command; the current token IS the first command.
-    fn parse_bare_action_case(&mut self) -> Result<Case, ParseError> {
+    fn parse_bare_action_case(&mut self) -> Result<Case, Diagnostic> {
         let lineno = self.current().unwrap().lineno;
 
         let mut substate = None;
@@ -325,7 +514,7 @@ impl Parser {
         Ok(Case { chars: None, condition: None, substate, commands, lineno })
     }
 
-    fn parse_if_case(&mut self) -> Result<Case, ParseError> {
+    fn parse_if_case(&mut self) -> Result<Case, Diagnostic> {
         let token = self.current().unwrap().clone();
         let lineno = token.lineno;
         let condition = token.id;
@@ -354,7 +543,7 @@ impl Parser {
         Ok(Case { chars: None, condition: Some(condition), substate: None, commands, lineno })
     }
 
-    fn parse_eof_handler(&mut self) -> Result<EOFHandler, ParseError> {
+    fn parse_eof_handler(&mut self) -> Result<EOFHandler, Diagnostic> {
         let lineno = self.current().unwrap().lineno;
         self.advance();
 
@@ -373,7 +562,7 @@ impl Parser {
         Ok(EOFHandler { commands, lineno })
     }
 
-    fn parse_conditional(&mut self) -> Result<Command, ParseError> {
+    fn parse_conditional(&mut self) -> Result<Command, Diagnostic> {
         let token = self.current().unwrap().clone();
         let lineno = token.lineno;
         let mut clauses: Vec<Clause> = Vec::new();
@@ -419,18 +608,45 @@ impl Parser {
     }
 }
 
-fn parse_command(token: &Token) -> Result<Command, ParseError> {
+/// The text after `|state[:name]` on the state line: one inline command
+/// that neither moves the cursor nor leaves the state.
+fn parse_state_entry_action(rest: &str, state: &str, lineno: usize) -> Result<Command, Diagnostic> {
+    let reject = |what: &str| {
+        Diagnostic::error("E0113", format!("'{rest}' on the |state[:{state}] line {what}"))
+            .at(Span::line(lineno).find(rest))
+            .help("state-line actions run on entry: MARK, TERM, PREPEND, emits, calls or assignments")
+    };
+    let token = match parse_part(rest, lineno, "")? {
+        Some(t) if inline_command_token(&t) => t,
+        _ => return Err(reject("is not a command")),
+    };
+    let cmd = parse_command(&token)?;
+    match &cmd {
+        Command::Cmd {
+            kind:
+                CmdKind::Advance
+                | CmdKind::AdvanceTo(_)
+                | CmdKind::Transition(_)
+                | CmdKind::Return(_)
+                | CmdKind::Scan(_),
+            ..
+        } => Err(reject("moves the cursor or leaves the state")),
+        _ => Ok(cmd),
+    }
+}
+
+fn parse_command(token: &Token) -> Result<Command, Diagnostic> {
     let (kind, lineno) = classify_command(token)?;
     Ok(Command::Cmd { kind, lineno })
 }
 
-fn classify_command(token: &Token) -> Result<(CmdKind, usize), ParseError> {
+fn classify_command(token: &Token) -> Result<(CmdKind, usize), Diagnostic> {
     let tag = token.tag.as_str();
     let rest = token.rest.as_str();
     let lineno = token.lineno;
 
     let kind = if tag.is_empty() {
-        parse_inline_command(rest)?
+        parse_inline_command(rest, lineno)?
     } else if tag == "->" {
         if token.id.is_empty() {
             CmdKind::Advance
@@ -482,7 +698,7 @@ fn classify_command(token: &Token) -> Result<(CmdKind, usize), ParseError> {
     } else {
         // Maybe tag + rest forms an assignment (e.g. tag="depth", rest="= 1")
         let full_cmd = format!("{} {}", tag, rest);
-        parse_inline_command(full_cmd.trim())?
+        parse_inline_command(full_cmd.trim(), lineno)?
     };
 
     Ok((kind, lineno))
@@ -499,7 +715,7 @@ fn prepend_kind(content: &str) -> CmdKind {
     }
 }
 
-fn parse_inline_command(cmd: &str) -> Result<CmdKind, ParseError> {
+fn parse_inline_command(cmd: &str, lineno: usize) -> Result<CmdKind, Diagnostic> {
     let cmd = cmd.trim();
     if cmd.is_empty() {
         return Ok(CmdKind::Noop);
@@ -552,10 +768,34 @@ fn parse_inline_command(cmd: &str) -> Result<CmdKind, ParseError> {
     } else if let Some(c) = re(r"^([A-Z]\w*)$").captures(cmd) {
         CmdKind::InlineEmitBare(c[1].to_string())
     } else {
-        return Err(ParseError(format!(
-            "Unrecognized command: '{}'. Expected: MARK, TERM, PREPEND, return, ->, /call, assignment, or TypeName",
-            cmd
-        )));
+        return Err(Diagnostic::error("E0108", format!("Unrecognized command: '{cmd}'"))
+            .at(Span::line(lineno).find(cmd))
+            .help("expected MARK, TERM, PREPEND, return, ->, /call, assignment, or TypeName"));
     };
     Ok(kind)
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn recovers_at_function_and_state_boundaries() {
+        let desc = r#"
+|parser r
+|entry-point /doc
+|bogus thing
+|function[doc]
+  |state[:main]
+    |frob      | ->  |>>
+    |default   | ->  |>>
+  |state[:two]
+    |default   | ->  |>>
+|function[other]
+  |state[:main]
+    |frob      | ->  |>>
+"#;
+        let (tokens, _) = crate::tokenize(desc, "t.desc", crate::Frontend::OracleLexer).unwrap();
+        let errs = super::Parser::new(tokens).parse().unwrap_err();
+        let found: Vec<(&str, Option<usize>)> = errs.0.iter().map(|d| (d.code, d.line())).collect();
+        assert_eq!(found, [("E0101", Some(4)), ("E0107", Some(7)), ("E0107", Some(13))]);
+    }
+}
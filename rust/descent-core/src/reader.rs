@@ -26,8 +26,15 @@
 //! Known udon-core irregularity (ledgered): Text spans on pipe-leading runs
 //! are one byte short of `content.len()`; we therefore trust `span.start` +
 //! `content` and treat span ends as estimates.
+//!
+//! What the reader can't map is reported as a warning, not an error — the
+//! tokens are still produced: W0001 an udon-core parse error, W0002 a UDON
+//! construct with no .desc meaning (dropped). udon-core's own warning,
+//! "Inconsistent indentation", is not passed on: parts are rebuilt in event
+//! order, so UDON nesting never reaches the tokens.
 
-use crate::lexer::{parse_part, LexerError, Token};
+use crate::diagnostic::{Diagnostic, Span};
+use crate::lexer::{parse_part, Token};
 use udon_core::Event;
 
 /// Sentinels substituted for .desc micro-syntax bytes before udon-core sees
@@ -82,7 +89,7 @@ pub struct Reader<'a> {
     /// Max source offset accounted for by structural events — used to detect
     /// pipes udon consumed without emitting structure (see `on_text`).
     consumed: usize,
-    pub warnings: Vec<String>,
+    pub warnings: Vec<Diagnostic>,
     /// Count of quote-protected pipes bridged via sentinel (spike metric).
     pub bridged_pipes: usize,
 }
@@ -102,15 +109,14 @@ impl<'a> Reader<'a> {
         }
     }
 
-    pub fn tokens(raw: &[u8], source_file: &str) -> Result<(Vec<Token>, Vec<String>), LexerError> {
+    /// Tokens of `raw`, with the W00xx warnings for what udon-core flagged
+    /// or the reader dropped.
+    pub fn tokens(raw: &[u8], source_file: &str) -> Result<(Vec<Token>, Vec<Diagnostic>), Diagnostic> {
         let (source, bridged) = protect_desc_microsyntax(raw);
         let mut r = Reader::new(&source, raw);
         r.bridged_pipes = bridged;
         // udon-core's callback parse; events borrow from `source`.
         udon_core::Parser::new(&source).parse(|ev| r.on_event(&ev));
-        if bridged > 0 {
-            r.warnings.push(format!("bridged {bridged} micro-syntax byte(s) via sentinels"));
-        }
         let mut tokens = Vec::new();
         for part in &r.parts {
             let lineno = lineno_at(&source, part.start);
@@ -238,34 +244,33 @@ impl<'a> Reader<'a> {
                 self.on_text(&text, span.start);
             }
             BlankLine { .. } => {}
-            Warning { content, span } => {
-                self.warnings.push(format!(
-                    "udon warning @{}: {}",
-                    span.start,
-                    String::from_utf8_lossy(content)
-                ));
-            }
+            Warning { .. } => {}
             Error { code, span } => {
-                self.warnings.push(format!("udon PARSE ERROR @{}: {:?}", span.start, code));
-            }
-            DirectiveStart { span } => {
-                self.warnings.push(format!("unhandled DirectiveStart @{}", span.start));
+                self.warn("W0001", format!("udon-core parse error {code:?}"), span.start);
             }
+            DirectiveStart { span } => self.unmapped("directive", span.start),
             DirectiveEnd { .. } => {}
-            ArrayStart { span } | FreeformStart { span } => {
-                self.warnings.push(format!("unhandled Array/FreeformStart @{}", span.start));
-            }
+            ArrayStart { span } => self.unmapped("array", span.start),
+            FreeformStart { span } => self.unmapped("freeform block", span.start),
             ArrayEnd { .. } | FreeformEnd { .. } => {}
-            Interpolation { content, span } => {
-                self.warnings.push(format!(
-                    "unhandled Interpolation @{}: {}",
-                    span.start,
-                    String::from_utf8_lossy(content)
-                ));
-            }
+            Interpolation { span, .. } => self.unmapped("interpolation", span.start),
         }
     }
 
+    /// A warning at byte `pos` of the source.
+    fn warn(&mut self, code: &'static str, message: String, pos: usize) {
+        let pos = pos.min(self.original.len());
+        let line_start = self.original[..pos].iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        let column = String::from_utf8_lossy(&self.original[line_start..pos]).chars().count() + 1;
+        let span = Span::line(lineno_at(self.original, pos)).column(column, 1);
+        self.warnings.push(Diagnostic::warning(code, message).at(span));
+    }
+
+    fn unmapped(&mut self, what: &str, pos: usize) {
+        let message = format!("UDON {what} has no .desc meaning; the reader drops it");
+        self.warn("W0002", message, pos);
+    }
+
     /// Split a text run on pipes (same rules as the oracle splitter); piece 0
     /// continues the last open part, later pieces open new parts.
     ///
@@ -533,3 +538,28 @@ fn protect_desc_microsyntax(raw: &[u8]) -> (Vec<u8>, usize) {
     }
     (out, count)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropped_constructs_are_located_warnings() {
+        let desc = "|parser t\n|entry-point /doc\n|function[doc] :a [1 2]\n  |state[:main]\n    |default | -> |return\n";
+        let (tokens, warnings) = Reader::tokens(desc.as_bytes(), "t.desc").unwrap();
+        assert!(tokens.iter().any(|t| t.tag == "function"));
+        let found: Vec<(&str, Option<usize>)> = warnings.iter().map(|d| (d.code, d.line())).collect();
+        assert_eq!(found, [("W0002", Some(3))]);
+        assert!(!warnings[0].is_error());
+        assert!(warnings[0].message.contains("array"), "{}", warnings[0].message);
+    }
+
+    #[test]
+    fn indentation_is_not_reported() {
+        // udon-core warns on the `|state` under a function with a sameline
+        // attr (`:term`).
+        let desc = "|parser t\n|entry-point /doc\n|function[doc]\n  |state[:main]\n    |default | /w(';') |return\n\n; w\n|function[w] :term\n  |state[:main]\n    |c[:term] |return\n    |default | -> |>>\n";
+        let (_, warnings) = Reader::tokens(desc.as_bytes(), "t.desc").unwrap();
+        assert_eq!(warnings, []);
+    }
+}
@@ -0,0 +1,527 @@
+//! Grammar-directed input sampling, with case-row coverage.
+//!
+//! Each function is a byte-dispatching state machine, so the IR says which
+//! bytes every `ir::Case` row accepts. A sample is a random walk from the
+//! entry point: at each state pick a row, write a byte only that row takes
+//! (the earlier rows in the same state would claim it otherwise), run the
+//! row's commands, and follow its call / transition / return. Rows no
+//! sample has hit yet are preferred, and so are bytes they list when a byte
+//! is left for a later state to dispatch on; rows that stay unhit across
+//! many samples are what `Coverage` reports — often a row no input can
+//! reach.
+//!
+//! A row that doesn't consume (`->`) leaves its byte current, as the parser
+//! does: the next state (or callee, or caller after a return) dispatches on
+//! that same byte rather than choosing one. A sample ends at EOF, which the
+//! walk picks now and then, always once a sample reaches its length budget,
+//! and when the call stack gets too deep or the walk stops consuming.
+//!
+//! The walk does not evaluate expressions. `|if[...]` rows and conditional
+//! clauses are taken at random, as if their condition held, so coverage is
+//! what the walk drove, not a guarantee the parser agrees; `KEYWORDS` maps
+//! are followed to their fallback only. Byte params (`|c[:p]`) are tracked
+//! through call arguments; a row on a param whose value is unknown is never
+//! chosen.
+
+use crate::charclass;
+use crate::ir::{Case, Command, Function, ParamType, ParserIR};
+use crate::ir_builder::tokenize_call_args;
+use crate::validate::normalize_state_name;
+
+/// Deeper call stacks end the sample (recursion the walk keeps choosing).
+const MAX_DEPTH: usize = 48;
+/// Row dispatches in a row without writing a byte before the sample ends.
+const MAX_IDLE: usize = 256;
+
+/// The sample ended (EOF); unwinds the walk.
+struct End;
+
+/// Where a command list left the current function.
+enum Flow {
+    Continue,
+    Goto(usize),
+    Return,
+}
+
+/// SplitMix64: deterministic per seed, no dependency.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// True one time in `n`.
+    fn one_in(&mut self, n: u64) -> bool {
+        self.next().is_multiple_of(n)
+    }
+}
+
+/// Mirrors the generated `is_<class>` matchers (non-ASCII xid bytes are
+/// accepted as the generated code accepts them: by lead / continuation byte).
+fn class_matches(class: &str, b: u8) -> bool {
+    let xid_start = |b: u8| if b < 0x80 { b.is_ascii_alphabetic() } else { (0xC2..=0xF4).contains(&b) };
+    let xid_cont = |b: u8| if b < 0x80 { b.is_ascii_alphanumeric() || b == b'_' } else { true };
+    match class {
+        "letter" => b.is_ascii_alphabetic(),
+        "label_cont" => b.is_ascii_alphanumeric() || b == b'_' || b == b'-',
+        "digit" => b.is_ascii_digit(),
+        "hex_digit" => b.is_ascii_hexdigit(),
+        "ws" => b == b' ' || b == b'\t',
+        "nl" => b == b'\n',
+        "xid_start" | "xlbl_start" => xid_start(b),
+        "xid_cont" => xid_cont(b),
+        "xlbl_cont" => b == b'-' || xid_cont(b),
+        _ => false,
+    }
+}
+
+/// A DSL char as the byte the generated code compares against.
+fn char_byte(c: &str) -> Option<u8> {
+    c.chars().next().map(|c| c as u32).filter(|&c| c <= 0xFF).map(|c| c as u8)
+}
+
+/// Bytes the walk writes by preference: what grammars are written against.
+fn printable(b: u8) -> bool {
+    (0x20..0x7F).contains(&b) || b == b'\n' || b == b'\t'
+}
+
+/// Known byte-param values of the running function.
+type Env<'a> = Vec<(&'a str, u8)>;
+
+fn case_matches(case: &Case, b: u8, env: &Env) -> bool {
+    if let Some(p) = &case.param_ref {
+        return env.iter().any(|(n, v)| n == p && *v == b);
+    }
+    let listed = case.chars.iter().flatten().any(|c| char_byte(c) == Some(b));
+    listed || case.special_class.as_deref().is_some_and(|class| class_matches(class, b))
+}
+
+/// One sample in progress.
+struct Walk {
+    out: Vec<u8>,
+    /// The byte dispatched on but not yet consumed.
+    cur: Option<u8>,
+    budget: usize,
+    idle: usize,
+}
+
+/// Generates samples from one grammar, accumulating row coverage across them.
+pub struct Sampler<'a> {
+    ir: &'a ParserIR,
+    rng: Rng,
+    /// Hit counts, `[function][state][case]`.
+    hits: Vec<Vec<Vec<usize>>>,
+    max_len: usize,
+    samples: usize,
+    /// Bytes an unhit row lists, so a byte left current for some later
+    /// state favours them; rebuilt when a row is first hit.
+    wanted: Option<[bool; 256]>,
+}
+
+impl<'a> Sampler<'a> {
+    pub fn new(ir: &'a ParserIR, seed: u64) -> Self {
+        let hits = ir.functions.iter().map(|f| f.states.iter().map(|s| vec![0; s.cases.len()]).collect()).collect();
+        Sampler { ir, rng: Rng(seed), hits, max_len: 256, samples: 0, wanted: None }
+    }
+
+    /// Upper bound on a sample's length (default 256); each sample draws
+    /// its own budget below it. A walk can overshoot by the filler of one
+    /// `->[...]`.
+    pub fn max_len(mut self, n: usize) -> Self {
+        self.max_len = n.max(1);
+        self
+    }
+
+    /// Walk once from the entry point.
+    pub fn sample(&mut self) -> Vec<u8> {
+        self.samples += 1;
+        let budget = 1 + self.rng.below(self.max_len);
+        let mut w = Walk { out: Vec::new(), cur: None, budget, idle: 0 };
+        let entry = self.ir.entry_point.as_deref().map(|e| e.trim_start_matches('/'));
+        let fi = entry.and_then(|e| self.function_index(e)).unwrap_or(0);
+        if fi < self.ir.functions.len() {
+            let _ = self.function(&mut w, fi, Vec::new(), 0);
+        }
+        w.out
+    }
+
+    /// Row coverage over every sample taken so far.
+    pub fn coverage(&self) -> Coverage<'a> {
+        let mut rows = Vec::new();
+        for (fi, func) in self.ir.functions.iter().enumerate() {
+            for (si, state) in func.states.iter().enumerate() {
+                for (ci, case) in state.cases.iter().enumerate() {
+                    rows.push(Row { function: &func.name, state: &state.name, case, hits: self.hits[fi][si][ci] });
+                }
+            }
+        }
+        Coverage { rows, samples: self.samples }
+    }
+
+    fn function_index(&self, name: &str) -> Option<usize> {
+        self.ir.functions.iter().position(|f| f.name == name)
+    }
+
+    fn function(&mut self, w: &mut Walk, fi: usize, env: Env<'a>, depth: usize) -> Result<(), End> {
+        let func: &'a Function = &self.ir.functions[fi];
+        let mut si = match self.run(w, func, &func.entry_actions, &env, depth)? {
+            Flow::Return => return Ok(()),
+            Flow::Goto(si) => si,
+            Flow::Continue => 0,
+        };
+        if func.states.is_empty() {
+            return Ok(());
+        }
+        loop {
+            let ci = self.pick(w, fi, si, &env)?;
+            if self.hits[fi][si][ci] == 0 {
+                self.wanted = None;
+            }
+            self.hits[fi][si][ci] += 1;
+            let case = &func.states[si].cases[ci];
+            match self.run(w, func, &case.commands, &env, depth)? {
+                Flow::Return => return Ok(()),
+                Flow::Goto(next) => si = next,
+                Flow::Continue => {}
+            }
+        }
+    }
+
+    /// The row dispatch takes next, writing its byte when none is current.
+    fn pick(&mut self, w: &mut Walk, fi: usize, si: usize, env: &Env) -> Result<usize, End> {
+        let state = &self.ir.functions[fi].states[si];
+        w.idle += 1;
+        if w.idle > MAX_IDLE {
+            return Err(End);
+        }
+        if let Some(b) = w.cur {
+            for (ci, case) in state.cases.iter().enumerate() {
+                if case.is_conditional() {
+                    let odds = if self.hits[fi][si][ci] == 0 { 2 } else { 4 };
+                    if self.rng.one_in(odds) {
+                        return Ok(ci);
+                    }
+                } else if case.is_default() || case_matches(case, b, env) {
+                    return Ok(ci);
+                }
+            }
+            return Err(End);
+        }
+        if w.out.len() >= w.budget || self.rng.one_in(64) {
+            return Err(End);
+        }
+
+        // Bytes each row would win the dispatch on (first match in order).
+        let mut takes: Vec<Vec<u8>> = vec![Vec::new(); state.cases.len()];
+        for b in 0..=255u8 {
+            let owner = state
+                .cases
+                .iter()
+                .position(|c| !c.is_conditional() && (c.is_default() || case_matches(c, b, env)));
+            if let Some(ci) = owner {
+                takes[ci].push(b);
+            }
+        }
+        let candidates: Vec<usize> =
+            (0..state.cases.len()).filter(|&ci| state.cases[ci].is_conditional() || !takes[ci].is_empty()).collect();
+        if candidates.is_empty() {
+            return Err(End);
+        }
+        let unhit: Vec<usize> = candidates.iter().copied().filter(|&ci| self.hits[fi][si][ci] == 0).collect();
+        let ci = if !unhit.is_empty() && !self.rng.one_in(4) {
+            unhit[self.rng.below(unhit.len())]
+        } else {
+            candidates[self.rng.below(candidates.len())]
+        };
+        if !state.cases[ci].is_conditional() {
+            let wanted = self.wanted();
+            let want: Vec<u8> = takes[ci].iter().copied().filter(|&b| wanted[b as usize]).collect();
+            let nice: Vec<u8> = takes[ci].iter().copied().filter(|&b| printable(b)).collect();
+            let pool = if !want.is_empty() && self.rng.one_in(2) {
+                &want
+            } else if nice.is_empty() || self.rng.one_in(16) {
+                &takes[ci]
+            } else {
+                &nice
+            };
+            let b = pool[self.rng.below(pool.len())];
+            self.write(w, b);
+            w.cur = Some(b);
+        }
+        Ok(ci)
+    }
+
+    fn wanted(&mut self) -> [bool; 256] {
+        if let Some(wanted) = self.wanted {
+            return wanted;
+        }
+        let mut wanted = [false; 256];
+        for (fi, func) in self.ir.functions.iter().enumerate() {
+            for (si, state) in func.states.iter().enumerate() {
+                for (ci, case) in state.cases.iter().enumerate() {
+                    if self.hits[fi][si][ci] == 0 {
+                        for b in case.chars.iter().flatten().filter_map(|c| char_byte(c)) {
+                            wanted[b as usize] = true;
+                        }
+                    }
+                }
+            }
+        }
+        self.wanted = Some(wanted);
+        wanted
+    }
+
+    fn write(&mut self, w: &mut Walk, b: u8) {
+        w.out.push(b);
+        w.idle = 0;
+    }
+
+    /// A printable byte outside `avoid`.
+    fn filler(&mut self, avoid: &[u8]) -> Option<u8> {
+        let pool: Vec<u8> = (0..=255u8).filter(|&b| printable(b) && !avoid.contains(&b)).collect();
+        (!pool.is_empty()).then(|| pool[self.rng.below(pool.len())])
+    }
+
+    fn run(&mut self, w: &mut Walk, func: &'a Function, cmds: &'a [Command], env: &Env<'a>, depth: usize) -> Result<Flow, End> {
+        for cmd in cmds {
+            match cmd.ctype.as_str() {
+                "advance" => {
+                    if w.cur.is_none() {
+                        let b = self.filler(&[]).unwrap_or(b' ');
+                        self.write(w, b);
+                    }
+                    w.cur = None;
+                }
+                "advance_to" => {
+                    let targets: Vec<u8> = cmd.arg_str("value").unwrap_or("").chars().filter_map(|c| char_byte(&c.to_string())).collect();
+                    if w.cur.is_some_and(|b| targets.contains(&b)) {
+                        continue;
+                    }
+                    w.cur = None;
+                    for _ in 0..self.rng.below(8) {
+                        if let Some(b) = self.filler(&targets) {
+                            self.write(w, b);
+                        }
+                    }
+                    if targets.is_empty() || self.rng.one_in(16) {
+                        return Err(End);
+                    }
+                    let b = targets[self.rng.below(targets.len())];
+                    self.write(w, b);
+                    w.cur = Some(b);
+                }
+                "return" => return Ok(Flow::Return),
+                "transition" => {
+                    let target = cmd.arg_str("value").unwrap_or("");
+                    if target.is_empty() {
+                        return Ok(Flow::Continue);
+                    }
+                    let name = normalize_state_name(target);
+                    return func.states.iter().position(|s| s.name == name).map(Flow::Goto).ok_or(End);
+                }
+                "conditional" => {
+                    let clauses = cmd.clauses.as_deref().unwrap_or_default();
+                    let has_else = clauses.iter().any(|c| c.condition.is_none());
+                    let n = clauses.len() + usize::from(!has_else);
+                    if let Some(clause) = clauses.get(self.rng.below(n.max(1))) {
+                        match self.run(w, func, &clause.commands, env, depth)? {
+                            Flow::Continue => {}
+                            flow => return Ok(flow),
+                        }
+                    }
+                }
+                "keywords_lookup" => {
+                    let map = cmd.arg_str("name").unwrap_or("");
+                    let fallback = self.ir.keywords.iter().find(|k| k.name == map);
+                    if let Some(kw) = fallback.filter(|_| self.rng.one_in(2)) {
+                        if let Some(f) = &kw.fallback_func {
+                            self.call(w, f, kw.fallback_args.as_deref(), env, depth)?;
+                        }
+                    }
+                }
+                _ => {
+                    if let Some((name, args)) = callee(cmd) {
+                        self.call(w, name, args, env, depth)?;
+                    }
+                }
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn call(&mut self, w: &mut Walk, name: &str, args: Option<&str>, env: &Env<'a>, depth: usize) -> Result<(), End> {
+        let Some(fi) = self.function_index(name) else {
+            return Ok(());
+        };
+        if depth >= MAX_DEPTH {
+            return Err(End);
+        }
+        let callee: &'a Function = &self.ir.functions[fi];
+        let args = args.map(tokenize_call_args).unwrap_or_default();
+        let mut inner = Env::new();
+        for ((param, ty), arg) in callee.param_types.iter().zip(&args) {
+            if *ty != ParamType::Byte {
+                continue;
+            }
+            let value = match arg.strip_prefix(':') {
+                Some(p) => env.iter().find(|(n, _)| *n == p).map(|(_, v)| *v),
+                None => charclass::parse(arg).bytes.as_deref().and_then(char_byte),
+            };
+            if let Some(v) = value {
+                inner.push((param.as_str(), v));
+            }
+        }
+        self.function(w, fi, inner, depth + 1)
+    }
+}
+
+/// `/name(args)` as a command or on the right of an assignment; error
+/// "calls" (`/error(Code)`) are not functions.
+fn callee(cmd: &Command) -> Option<(&str, Option<&str>)> {
+    match cmd.ctype.as_str() {
+        "call" if cmd.args.get("is_error").and_then(|v| v.as_bool()) != Some(true) => {
+            Some((cmd.arg_str("name")?, cmd.arg_str("call_args")))
+        }
+        "assign" | "add_assign" | "sub_assign" => {
+            let expr = cmd.arg_str("expr")?.trim().strip_prefix('/')?;
+            let (name, args) = match expr.split_once('(') {
+                Some((name, rest)) => (name, Some(rest.strip_suffix(')').unwrap_or(rest))),
+                None => (expr, None),
+            };
+            Some((name, args.filter(|a| !a.is_empty())))
+        }
+        _ => None,
+    }
+}
+
+/// One case row and how many times the walk took it.
+#[derive(Debug, Clone)]
+pub struct Row<'a> {
+    pub function: &'a str,
+    pub state: &'a str,
+    pub case: &'a Case,
+    pub hits: usize,
+}
+
+impl Row<'_> {
+    /// The row's trigger in DSL form: `|c['\n']`, `|letter`, `|if[...]`, ...
+    pub fn trigger(&self) -> String {
+        let case = self.case;
+        if let Some(cond) = &case.condition {
+            return format!("|if[{cond}]");
+        }
+        if let Some(p) = &case.param_ref {
+            return format!("|c[:{p}]");
+        }
+        let chars: String = case.chars.iter().flatten().map(|c| c.escape_debug().to_string()).collect();
+        match (&case.special_class, chars.is_empty()) {
+            (Some(class), true) => format!("|{class}"),
+            (Some(class), false) => format!("|{class} + c['{chars}']"),
+            (None, false) => format!("|c['{chars}']"),
+            (None, true) => "|default".to_string(),
+        }
+    }
+}
+
+/// Case-row coverage of a `Sampler`'s samples.
+#[derive(Debug, Clone)]
+pub struct Coverage<'a> {
+    /// Every row, in grammar order.
+    pub rows: Vec<Row<'a>>,
+    pub samples: usize,
+}
+
+impl Coverage<'_> {
+    pub fn missed(&self) -> impl Iterator<Item = &Row<'_>> {
+        self.rows.iter().filter(|r| r.hits == 0)
+    }
+
+    pub fn report(&self) -> String {
+        use std::fmt::Write;
+        let hit = self.rows.iter().filter(|r| r.hits > 0).count();
+        let mut b = String::new();
+        let _ = writeln!(b, "# descent sample coverage — {hit}/{} case rows over {} samples", self.rows.len(), self.samples);
+        for r in self.missed() {
+            let _ = writeln!(b, "missed  L{:<5} {:<32} {}", r.case.lineno, format!("{}:{}", r.function, r.state), r.trigger());
+        }
+        b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ir(desc: &str) -> ParserIR {
+        crate::build_ir_with(desc, "t.desc", crate::Frontend::OracleLexer).unwrap()
+    }
+
+    const LIST: &str = "\
+|parser t
+|type[Item] CONTENT
+|entry-point /doc
+|function[doc]
+  |state[:main]
+    |c['(']   | -> | /list(')')  |>>
+    |c['[']   | -> | /list(']')  |>>
+    |default  | ->               |>>
+|function[list] :close
+  |state[:main]
+    |c[:close] | ->        |return
+    |c[' ']    | ->        |>>
+    |default   | /item     |>>
+|function[item:Item]
+  |state[:main]
+    |letter    | ->        |>>
+    |default   |return
+";
+
+    #[test]
+    fn samples_reach_every_row_and_track_byte_params() {
+        let ir = ir(LIST);
+        let mut s = Sampler::new(&ir, 7);
+        for _ in 0..64 {
+            s.sample();
+        }
+        // `|c[:close]` is only reachable with the call argument tracked.
+        let cov = s.coverage();
+        assert_eq!(cov.missed().count(), 0, "{}", cov.report());
+    }
+
+    #[test]
+    fn same_seed_same_samples() {
+        let ir = ir(LIST);
+        let (mut a, mut b) = (Sampler::new(&ir, 3), Sampler::new(&ir, 3));
+        for _ in 0..8 {
+            assert_eq!(a.sample(), b.sample());
+        }
+    }
+
+    #[test]
+    fn shadowed_rows_are_reported_missed() {
+        let ir = ir("\
+|parser t
+|entry-point /doc
+|function[doc]
+  |state[:main]
+    |letter   | ->  |>>
+    |c['a']   | ->  |>>
+    |default  | ->  |>>
+");
+        let mut s = Sampler::new(&ir, 1);
+        for _ in 0..32 {
+            s.sample();
+        }
+        let missed: Vec<String> = s.coverage().missed().map(Row::trigger).collect();
+        assert_eq!(missed, ["|c['a']"]);
+    }
+}
@@ -0,0 +1,618 @@
+//! Semantic validation over the IR, run after `IRBuilder::build` and before
+//! emission. Port of Ruby descent's `Validator` (lib/descent/validator.rb),
+//! which the Ruby pipeline never actually wired in, extended with the
+//! reference checks whose failures otherwise surface as rustc errors in the
+//! generated code, far from the `.desc` line that caused them:
+//!
+//! - `/call` (and `x = /call(...)`, keyword fallbacks) naming no function;
+//! - `/call(args)` whose argument count differs from the callee's params;
+//! - `KEYWORDS(map)` naming no `|keywords` block;
+//! - `|>> :state` naming no state of the enclosing function;
+//! - `|entry-point` missing or naming no function;
+//! - two `|function[name]` definitions (duplicate methods / C functions);
+//! - locals and params named like a Rust keyword or an identifier a backend
+//!   reserves for its own generated code (`emit::RESERVED_NAMES`);
+//! - functions `classify` finds **mixed** — positional (geometric) and
+//!   delimited (Unclosed-warning) exits in one function — unless `|mixed`
+//!   declares it intentional;
+//! - recursion that re-enters a function before consuming a byte
+//!   (`callgraph`), which overflows the stack on the first input that takes it;
+//! - captures from MARK (`Type(USE_MARK)`, `emit(Type)`, `TERM`) in a
+//!   non-CONTENT function that never MARKs (W0305; see `validate_mark_usage`
+//!   for why it isn't the spec's "CONTENT function that never MARKs").
+//!
+//! Errors (E03xx) block generation (`build_ir_with`); warnings (W03xx) are
+//! advisory and only surface through `descent-rs validate`. Spans point at
+//! the nearest `.desc` line the IR still carries (case, state, function or
+//! declaration — commands have no line of their own), with the offending
+//! text as the needle.
+
+use crate::diagnostic::{Diagnostic, Severity, Span};
+use crate::ir::{Command, Function, ParserIR};
+use crate::ir_builder::tokenize_call_args;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+pub struct Validator<'a> {
+    ir: &'a ParserIR,
+    pub issues: Vec<Diagnostic>,
+}
+
+impl<'a> Validator<'a> {
+    pub fn new(ir: &'a ParserIR) -> Self {
+        Validator { ir, issues: Vec::new() }
+    }
+
+    pub fn validate(mut self) -> Self {
+        self.validate_parser_name();
+        self.validate_types();
+        self.validate_functions();
+        self.validate_keywords();
+        self.validate_entry_point();
+        self.validate_recursion();
+        self.validate_mixed_exits();
+        self
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.issues.iter().filter(|i| i.severity == Severity::Warning)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// Errors first, then warnings, rendered against the grammar source
+    /// (Ruby: Validator#report).
+    pub fn report(&self, source_file: &str, source: &str) -> String {
+        let mut out = String::new();
+        for sev in [Severity::Error, Severity::Warning] {
+            for d in self.issues.iter().filter(|d| d.severity == sev) {
+                let _ = writeln!(out, "{}", d.clone().in_file(source_file).render(source));
+            }
+        }
+        out
+    }
+
+    fn push(&mut self, d: Diagnostic) {
+        self.issues.push(d);
+    }
+
+    // ========== Parser name ==========
+
+    fn validate_parser_name(&mut self) {
+        if self.ir.name.as_deref().is_none_or(str::is_empty) {
+            self.push(
+                Diagnostic::error("E0301", "Missing parser name")
+                    .at(Span::line(1))
+                    .help("add a |parser <name> directive"),
+            );
+        }
+    }
+
+    // ========== Types ==========
+
+    fn validate_types(&mut self) {
+        let mut seen = HashMap::new();
+        for t in &self.ir.types {
+            if let Some(first) = seen.insert(t.name.as_str(), t.lineno) {
+                self.push(
+                    Diagnostic::error("E0302", format!("Duplicate type declaration: {}", t.name))
+                        .at(Span::line(t.lineno).find(t.name.as_str()))
+                        .label(Span::line(first).find(t.name.as_str()), "first declared here"),
+                );
+            }
+            if !["bracket", "content", "internal"].contains(&t.kind.as_str()) {
+                let kind = t.kind.to_uppercase();
+                self.push(
+                    Diagnostic::error("E0303", format!("Unknown type kind '{kind}' for {}", t.name))
+                        .at(Span::line(t.lineno).find(kind))
+                        .help("expected BRACKET, CONTENT, or INTERNAL"),
+                );
+            }
+        }
+    }
+
+    // ========== Functions ==========
+
+    fn validate_functions(&mut self) {
+        let mut seen = HashMap::new();
+        for func in &self.ir.functions {
+            if let Some(first) = seen.insert(func.name.as_str(), func.lineno) {
+                self.push(
+                    Diagnostic::error("E0314", format!("Duplicate function definition: {}", func.name))
+                        .at(Span::line(func.lineno))
+                        .label(Span::line(first), "first defined here"),
+                );
+            }
+            if let Some(rt) = &func.return_type {
+                if !self.type_exists(rt) {
+                    self.push(
+                        Diagnostic::warning("W0302", format!("Return type '{rt}' not declared"))
+                            .at(Span::line(func.lineno).find(rt.as_str()))
+                            .help(format!("declare it with |type[{rt}] CONTENT (or BRACKET/INTERNAL)")),
+                    );
+                }
+            }
+
+            self.validate_identifiers(func);
+            self.validate_commands(func, &func.entry_actions, func.lineno);
+            if let Some(h) = &func.eof_handler {
+                self.validate_commands(func, h, func.lineno);
+            }
+            for state in &func.states {
+                if state.cases.is_empty() && state.eof_handler.is_none() {
+                    self.push(
+                        Diagnostic::warning("W0303", format!("State '{}' in {} has no cases", state.name, func.name))
+                            .at(Span::line(state.lineno)),
+                    );
+                }
+                if let Some(h) = &state.eof_handler {
+                    self.validate_commands(func, h, state.lineno);
+                }
+                for kase in &state.cases {
+                    self.validate_commands(func, &kase.commands, kase.lineno);
+                }
+            }
+
+            self.validate_mark_usage(func);
+        }
+    }
+
+    fn validate_commands(&mut self, func: &Function, commands: &[Command], lineno: usize) {
+        for cmd in commands {
+            match cmd.ctype.as_str() {
+                "call" => {
+                    if cmd.args.get("is_error").and_then(|v| v.as_bool()) == Some(true) {
+                        continue;
+                    }
+                    let name = cmd.arg_str("name").unwrap_or("");
+                    self.check_call(name, cmd.arg_str("call_args"), lineno);
+                }
+                "assign" => {
+                    // `x = /fn(args)` — an assignment-from-call is a call site too.
+                    let expr = cmd.arg_str("expr").unwrap_or("").trim();
+                    if let Some(caps) = crate::lexer::re(r"^/(\w+)(?:\((.*)\))?$").captures(expr) {
+                        let args = caps.get(2).map(|m| m.as_str());
+                        self.check_call(&caps[1], args, lineno);
+                    }
+                }
+                "keywords_lookup" | "keywords_try" => {
+                    let name = cmd.arg_str("name").unwrap_or("");
+                    if !self.ir.keywords.iter().any(|k| k.name == name) {
+                        self.push(
+                            Diagnostic::error("E0306", format!("KEYWORDS({name}) references undefined |keywords map"))
+                                .at(Span::line(lineno).find(name)),
+                        );
+                    }
+                }
+                "transition" => {
+                    let target = cmd.arg_str("value").unwrap_or("");
+                    if target.is_empty() {
+                        continue; // self-loop
+                    }
+                    let state_name = normalize_state_name(target);
+                    if !func.states.iter().any(|s| s.name == state_name) {
+                        self.push(
+                            Diagnostic::error(
+                                "E0307",
+                                format!("Transition to undefined state '{target}' in function '{}'", func.name),
+                            )
+                            .at(Span::line(lineno).find(target))
+                            .label(Span::line(func.lineno), "in this function"),
+                        );
+                    }
+                }
+                "emit" => {
+                    let emitted = cmd.arg_str("value").unwrap_or("");
+                    let base = emitted
+                        .strip_suffix("Start")
+                        .or_else(|| emitted.strip_suffix("End"))
+                        .unwrap_or(emitted);
+                    if !self.type_exists(base) && !["Error", "Warning"].contains(&emitted) {
+                        self.push(
+                            Diagnostic::warning("W0304", format!("Emit of undefined type '{emitted}'"))
+                                .at(Span::line(lineno).find(emitted)),
+                        );
+                    }
+                }
+                "conditional" => {
+                    for clause in cmd.clauses.iter().flatten() {
+                        self.validate_commands(func, &clause.commands, lineno);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Params and locals become Rust identifiers in generated code (fn args,
+    /// `let` bindings, frame fields), so they must not be Rust keywords or a
+    /// name some backend already binds.
+    fn validate_identifiers(&mut self, func: &Function) {
+        let params = func.params.iter().map(|p| (p, "param", func.lineno));
+        let locals = func
+            .locals
+            .iter()
+            .filter(|l| !func.params.contains(l))
+            .map(|l| (l, "local", local_lineno(func, l)));
+        for (name, what, lineno) in params.chain(locals) {
+            if RUST_KEYWORDS.contains(&name.as_str()) {
+                self.push(
+                    Diagnostic::error("E0310", format!("{what} '{name}' in function '{}' is a Rust keyword", func.name))
+                        .at(Span::line(lineno).find(name.as_str()))
+                        .help("rename it; grammar variables become Rust identifiers"),
+                );
+                continue;
+            }
+            let backends: Vec<&str> = crate::emit::RESERVED_NAMES
+                .iter()
+                .filter(|(_, names)| names.contains(&name.as_str()))
+                .map(|(backend, _)| *backend)
+                .collect();
+            if !backends.is_empty() {
+                self.push(
+                    Diagnostic::error(
+                        "E0311",
+                        format!(
+                            "{what} '{name}' in function '{}' collides with an identifier the {} backend generates",
+                            func.name,
+                            backends.join(" and ")
+                        ),
+                    )
+                    .at(Span::line(lineno).find(name.as_str()))
+                    .help("rename it"),
+                );
+            }
+        }
+    }
+
+    /// Callee must exist and receive exactly as many args as it declares.
+    fn check_call(&mut self, name: &str, call_args: Option<&str>, lineno: usize) {
+        let Some(callee) = self.ir.functions.iter().find(|f| f.name == name) else {
+            self.push(
+                Diagnostic::error("E0304", format!("Call to undefined function '/{name}'"))
+                    .at(Span::line(lineno).find(format!("/{name}"))),
+            );
+            return;
+        };
+        let given = call_args.map_or(0, |a| tokenize_call_args(a).len());
+        let expected = callee.params.len();
+        if given != expected {
+            let params: Vec<String> = callee.params.iter().map(|p| format!(":{p}")).collect();
+            let list = if params.is_empty() { "none".to_string() } else { params.join(" ") };
+            self.push(
+                Diagnostic::error(
+                    "E0305",
+                    format!("/{name} takes {expected} argument(s) ({list}) but is called with {given}"),
+                )
+                .at(Span::line(lineno).find(format!("/{name}")))
+                .label(Span::line(callee.lineno), format!("/{name} defined here")),
+            );
+        }
+    }
+
+    /// A Mixed function closes on geometry in some exits and warns
+    /// `Unclosed…` in others, so neither EOF policy (newline+dedent, or
+    /// force-unwind) is right for all of it. Usually the delimited region
+    /// wants its own function.
+    fn validate_mixed_exits(&mut self) {
+        for c in crate::classify::classify(self.ir) {
+            if c.kind != crate::classify::Kind::Mixed {
+                continue;
+            }
+            let Some(func) = self.ir.functions.iter().find(|f| f.name == c.name) else {
+                continue;
+            };
+            if func.allow_mixed {
+                continue;
+            }
+            let mut d = Diagnostic::error(
+                "E0313",
+                format!("function '{}' mixes positional and delimited exits", c.name),
+            )
+            .at(Span::line(func.lineno).find(c.name.as_str()))
+            .help("move the delimited region into its own function, or add `|mixed` to the function to keep it");
+            for row in &c.geometric_rows {
+                d = d.label(Span::line(*row), "positional exit: closes on geometry");
+            }
+            for row in &c.delim_failure_rows {
+                d = d.label(Span::line(*row), "delimited exit: warns Unclosed at EOF");
+            }
+            self.push(d);
+        }
+    }
+
+    /// Reported at the call that closes the cycle, with every other call on
+    /// it labelled.
+    fn validate_recursion(&mut self) {
+        for cycle in crate::callgraph::non_advancing_cycles(self.ir) {
+            let (last, rest) = cycle.edges.split_last().expect("cycles are non-empty");
+            let mut d = Diagnostic::error(
+                "E0312",
+                format!("non-advancing recursion: {}", cycle.path()),
+            )
+            .at(Span::line(last.lineno).find(format!("/{}", last.callee)))
+            .help("consume input (`->`) before the recursive call; as written every cycle re-enters on the same byte");
+            for e in rest {
+                d = d.label(
+                    Span::line(e.lineno).find(format!("/{}", e.callee)),
+                    format!("/{} calls /{} before consuming input", e.caller, e.callee),
+                );
+            }
+            self.push(d);
+        }
+    }
+
+    /// Captures read `mark_pos`. CONTENT-typed functions MARK on entry; any
+    /// other function that emits from the mark without ever MARKing reads
+    /// whatever mark its caller left behind — legal (callers do MARK before
+    /// delegating), but usually a forgotten `| MARK`, so warn.
+    /// The spec's type-consistency item asks for "CONTENT functions that
+    /// never MARK"; with the auto-MARK that check can never fire, so the
+    /// warning covers the case that does lose the span start instead.
+    /// Keyword fallbacks are exempt: they exist to emit the caller's
+    /// already-MARKed word.
+    fn validate_mark_usage(&mut self, func: &Function) {
+        if self.return_kind(func) == Some("content") {
+            return;
+        }
+        if self.ir.keywords.iter().any(|k| k.fallback_func.as_deref() == Some(func.name.as_str())) {
+            return;
+        }
+        let mut marks = false;
+        let mut capture_line = None;
+        let mut visit = |cmds: &[Command], lineno: usize| {
+            walk(cmds, &mut |c| match c.ctype.as_str() {
+                "mark" => marks = true,
+                "emit" | "inline_emit_mark" => {
+                    capture_line.get_or_insert(lineno);
+                }
+                "return" if c.arg_str("emit_mode") == Some("mark") => {
+                    capture_line.get_or_insert(lineno);
+                }
+                _ => {}
+            });
+        };
+        visit(&func.entry_actions, func.lineno);
+        if let Some(h) = &func.eof_handler {
+            visit(h, func.lineno);
+        }
+        for state in &func.states {
+            if let Some(h) = &state.eof_handler {
+                visit(h, state.lineno);
+            }
+            for kase in &state.cases {
+                visit(&kase.commands, kase.lineno);
+            }
+        }
+        if let (false, Some(line)) = (marks, capture_line) {
+            self.push(
+                Diagnostic::warning("W0305", format!("MARK capture in non-CONTENT function '{}', which never MARKs", func.name))
+                    .at(Span::line(line))
+                    .label(Span::line(func.lineno), "not CONTENT-typed, so no auto-MARK on entry")
+                    .help("add `| MARK` where the captured text starts"),
+            );
+        }
+    }
+
+    // ========== Keywords ==========
+
+    fn validate_keywords(&mut self) {
+        for kw in &self.ir.keywords {
+            if let Some(fb) = &kw.fallback_func {
+                self.check_call(fb, kw.fallback_args.as_deref().filter(|a| !a.is_empty()), kw.lineno);
+            }
+        }
+    }
+
+    // ========== Entry point ==========
+
+    fn validate_entry_point(&mut self) {
+        let Some(ep) = &self.ir.entry_point else {
+            self.push(
+                Diagnostic::error("E0308", "Missing entry point").help("add an |entry-point /<function> directive"),
+            );
+            return;
+        };
+        let func_name = ep.trim_start_matches('/');
+        if !self.ir.functions.iter().any(|f| f.name == func_name) {
+            self.push(Diagnostic::error(
+                "E0309",
+                format!("Entry point references undefined function '{func_name}'"),
+            ));
+        }
+    }
+
+    // ========== Helpers ==========
+
+    fn type_exists(&self, name: &str) -> bool {
+        self.ir.types.iter().any(|t| t.name.eq_ignore_ascii_case(name))
+    }
+
+    fn return_kind(&self, func: &Function) -> Option<&'a str> {
+        let rt = func.return_type.as_deref()?;
+        self.ir.types.iter().find(|t| t.name == rt).map(|t| t.kind.as_str())
+    }
+}
+
+/// Strict and reserved keywords (2021 edition) — none can be a binding.
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self", "Self", "static",
+    "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// The line of a local's first assignment (entry actions count as the
+/// function line).
+fn local_lineno(func: &Function, local: &str) -> usize {
+    let assigns = |cmds: &[Command]| {
+        let mut hit = false;
+        walk(cmds, &mut |c| {
+            hit |= c.ctype.ends_with("assign") && c.arg_str("var") == Some(local);
+        });
+        hit
+    };
+    if assigns(&func.entry_actions) {
+        return func.lineno;
+    }
+    func.states
+        .iter()
+        .flat_map(|s| &s.cases)
+        .find(|k| assigns(&k.commands))
+        .map_or(func.lineno, |k| k.lineno)
+}
+
+/// `|>> :state-name` -> the IR's state name (`state_name`: colon dropped,
+/// dashes folded to underscores exactly as `Parser::parse_state` does).
+pub(crate) fn normalize_state_name(target: &str) -> String {
+    target.replace(':', "").replace('-', "_")
+}
+
+/// Visit every command, recursing into conditional clauses.
+pub(crate) fn walk(cmds: &[Command], f: &mut dyn FnMut(&Command)) {
+    for c in cmds {
+        f(c);
+        for clause in c.clauses.iter().flatten() {
+            walk(&clause.commands, f);
+        }
+    }
+}
+
+/// Convenience: validate and return the findings.
+pub fn validate(ir: &ParserIR) -> Vec<Diagnostic> {
+    Validator::new(ir).validate().issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issues(desc: &str) -> Vec<Diagnostic> {
+        let m = crate::parse_with(desc, "t.desc", crate::Frontend::OracleLexer).unwrap();
+        let ir = crate::IRBuilder::new(&m).build().unwrap();
+        validate(&ir)
+    }
+
+    #[test]
+    fn reports_undefined_references_and_arity() {
+        let found = issues(
+            r#"
+|parser v
+|type[Text] CONTENT
+|entry-point /missing
+
+|function[doc]
+  |state[:main]
+    |c['a']     | /nope               |>>
+    |c['b']     | /line(1)            |>>
+    |c['c']     |                     |>> :nowhere
+    |default    | ->                  |>>
+
+|function[line:Text] :x :y
+  |state[:main]
+    |default    | ->                  |return
+"#,
+        );
+        let errors: Vec<&str> = found
+            .iter()
+            .filter(|i| i.severity == Severity::Error)
+            .map(|i| i.message.as_str())
+            .collect();
+        assert!(errors.iter().any(|m| m.contains("undefined function '/nope'")), "{errors:?}");
+        assert!(errors.iter().any(|m| m.contains("/line takes 2 argument(s)")), "{errors:?}");
+        assert!(errors.iter().any(|m| m.contains("undefined state ':nowhere'")), "{errors:?}");
+        assert!(errors.iter().any(|m| m.contains("undefined function 'missing'")), "{errors:?}");
+    }
+
+    #[test]
+    fn rejects_keyword_and_reserved_identifiers() {
+        let found = issues(
+            r#"
+|parser v
+|entry-point /doc
+
+|function[doc] :loop
+  |state[:main]
+    |c['a']     | st = 1              |>>
+    |c['b']     | max_depth = 1       |>>
+    |c['c']     | line = 1            |>>
+    |c['d']     | int = 1             |>>
+    |default    | ->                  |return
+"#,
+        );
+        let errs: Vec<(&str, Option<usize>)> = found.iter().filter(|d| d.is_error()).map(|d| (d.code, d.line())).collect();
+        // `max_depth` is only a pushdown field; `int` only a C keyword, and
+        // the C backend prefixes variables.
+        assert_eq!(errs, [("E0310", Some(5)), ("E0311", Some(7))]);
+        assert!(found[1].message.contains("pushdown backend"), "{}", found[1].message);
+    }
+
+    #[test]
+    fn rejects_mixed_exits_unless_declared() {
+        let desc = r#"|parser t
+|type[Text] CONTENT
+|entry-point /val
+|function[val:Text]
+  |state[:main]
+    |c['\n']  |                          |return
+    |c['<']   | ->                       |>> :env
+    |default  | ->                       |>>
+  |state[:env]
+    |eof      | Warning(UnclosedEnvelope) |return
+    |c['>']   | ->                       |>> :main
+    |default  | ->                       |>>
+"#;
+        let found = issues(desc);
+        let mixed = found.iter().find(|d| d.code == "E0313").expect("E0313");
+        assert_eq!(mixed.line(), Some(4));
+        let rows: Vec<usize> = mixed.labels.iter().map(|l| l.span.line).collect();
+        assert_eq!(rows, [6, 9]);
+        let declared = desc.replace("|function[val:Text]\n", "|function[val:Text]\n  |mixed\n");
+        assert!(issues(&declared).iter().all(|d| d.code != "E0313"));
+    }
+
+    #[test]
+    fn duplicate_functions_are_errors_and_unmarked_captures_warn() {
+        let found = issues(
+            r#"
+|parser v
+|type[Text] CONTENT
+|type[Name] INTERNAL
+|entry-point /doc
+
+|function[doc]
+  |state[:main]
+    |c['a']     | /name               |>>
+    |default    | ->                  |return
+
+|function[name:Name]
+  |state[:main]
+    |c[' ']     | emit(Text)          |return
+    |default    | ->                  |>>
+
+|function[doc]
+  |state[:main]
+    |default    | ->                  |return
+"#,
+        );
+        let codes: Vec<(&str, Option<usize>)> = found.iter().map(|d| (d.code, d.line())).collect();
+        assert!(codes.contains(&("E0314", Some(17))), "{codes:?}");
+        assert!(found.iter().find(|d| d.code == "E0314").unwrap().is_error());
+        assert!(codes.contains(&("W0305", Some(14))), "{codes:?}");
+    }
+
+    #[test]
+    fn corpus_grammars_have_no_errors() {
+        for path in ["../tests/fixtures/combined.desc", "../tests/fixtures/udon_complete.desc"] {
+            let desc = std::fs::read_to_string(path).unwrap();
+            assert!(crate::build_ir_with(&desc, path, crate::Frontend::OracleLexer).is_ok(), "{path}");
+        }
+    }
+}
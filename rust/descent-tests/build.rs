@@ -0,0 +1,95 @@
+//! Generate the parsers `src/lib.rs` includes. For each grammar, into
+//! OUT_DIR: `<name>.rs`, the recursive parser with its nested pushdown
+//! machine (`emit::rust` with `streaming`, `descent-rs generate
+//! --streaming`), and
+//! `<name>_samples.rs`, inputs walked from the grammar (`sample::Sampler`)
+//! and the indices of those in `HANGS`.
+//! Traced grammars also get `<name>_trace.rs`, the same with `trace` on,
+//! and those in `ASYNC` `<name>_async.rs`, a standalone pushdown build with
+//! the async adapter (`EventStream`) over the recursive module's events.
+//! Grammars in `C` get the C backend's pair in `c/<name>/` (`parser.h`,
+//! `parser.c`), which `tests/c_backend.rs` compiles.
+//!
+//! The generated files open with `//!` docs, which `include!` inside a
+//! `mod { }` rejects, so those lines are dropped.
+
+use descent_core::emit::{c, rust, rust_pushdown};
+use descent_core::sample::Sampler;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Module name, grammar path (relative to this crate), traced.
+const GRAMMARS: &[(&str, &str, bool)] = &[
+    ("borderline", "grammars/borderline.desc", false),
+    ("udon_complete", "../tests/fixtures/udon_complete.desc", true),
+    ("markdown", "../tests/fixtures/markdown.desc", true),
+    ("elements", "../tests/fixtures/elements.desc", true),
+    ("brackets", "../tests/fixtures/brackets.desc", false),
+    ("tiny", "grammars/tiny.desc", false),
+];
+
+/// Grammars that also get the async adapter build.
+const ASYNC: &[&str] = &["markdown"];
+
+/// Grammars that also get the C backend's output.
+const C: &[&str] = &["borderline", "udon_complete", "markdown", "elements", "brackets", "tiny"];
+
+/// Samples the parsers never finish, in either backend: grammar loops, not
+/// sampler bugs. udon_complete's `sameline_raw:kind` calls `/name` on every
+/// byte but ':', and `/name` consumes nothing unless it sees XLBL_CONT, so
+/// `!{:Os,` spins at the ','. `tests/trace.rs` checks they still spin; the
+/// other tests skip them.
+const HANGS: &[(&str, &[usize])] = &[("udon_complete", &[24])];
+
+/// Samples per grammar, and their length bound.
+const SAMPLES: usize = 32;
+const SAMPLE_LEN: usize = 256;
+
+fn main() {
+    let out = PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
+    for &(name, path, traced) in GRAMMARS {
+        println!("cargo:rerun-if-changed={path}");
+        let src = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("{path}: {e}"));
+        let ir = descent_core::build_ir(&src, path).unwrap_or_else(|e| panic!("{path}:\n{e}"));
+        let generate = |trace| {
+            let opts = rust::Options { trace, streaming: true, ..Default::default() };
+            rust::generate(&ir, &opts).unwrap_or_else(|e| panic!("{path}: {}", e.render(&src)))
+        };
+        write(&out, &format!("{name}.rs"), &generate(false));
+        if traced {
+            write(&out, &format!("{name}_trace.rs"), &generate(true));
+        }
+        if ASYNC.contains(&name) {
+            let opts = rust_pushdown::PdOptions {
+                event_path: format!("crate::{name}::parser"),
+                async_adapter: true,
+                ..Default::default()
+            };
+            let code = rust_pushdown::generate(&ir, &opts).unwrap_or_else(|e| panic!("{path}: {}", e.render(&src)));
+            write(&out, &format!("{name}_async.rs"), &code);
+        }
+        if C.contains(&name) {
+            let dir = out.join("c").join(name);
+            std::fs::create_dir_all(&dir).unwrap();
+            let files = c::generate(&ir, &c::COptions::default()).unwrap_or_else(|e| panic!("{path}: {}", e.render(&src)));
+            for (file, text) in files {
+                std::fs::write(dir.join(file), text).unwrap();
+            }
+        }
+
+        let mut sampler = Sampler::new(&ir, 1).max_len(SAMPLE_LEN);
+        let mut samples = String::from("pub const SAMPLES: &[&[u8]] = &[\n");
+        for _ in 0..SAMPLES {
+            let _ = writeln!(samples, "    b\"{}\",", sampler.sample().escape_ascii());
+        }
+        samples.push_str("];\n");
+        let hangs = HANGS.iter().find(|(g, _)| *g == name).map_or(&[][..], |(_, h)| h);
+        let _ = writeln!(samples, "pub const HANGS: &[usize] = &{hangs:?};");
+        write(&out, &format!("{name}_samples.rs"), &samples);
+    }
+}
+
+fn write(dir: &Path, file: &str, code: &str) {
+    let body: String = code.lines().filter(|l| !l.starts_with("//!")).flat_map(|l| [l, "\n"]).collect();
+    std::fs::write(dir.join(file), body).unwrap();
+}
@@ -0,0 +1,24 @@
+//! `trace <grammar> <recursive|pushdown> <sample index>`: run one of a
+//! grammar's `SAMPLES` through its traced parser, trace on stderr.
+
+use descent_tests::*;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let (Some(grammar), Some(backend), Some(i)) = (args.get(1), args.get(2), args.get(3).and_then(|i| i.parse().ok()))
+    else {
+        eprintln!("usage: trace <grammar> <recursive|pushdown> <sample index>");
+        std::process::exit(2);
+    };
+    let i: usize = i;
+    let pushdown = backend == "pushdown";
+    match grammar.as_str() {
+        "udon_complete" => udon_complete::trace(pushdown, udon_complete::SAMPLES[i]),
+        "markdown" => markdown::trace(pushdown, markdown::SAMPLES[i]),
+        "elements" => elements::trace(pushdown, elements::SAMPLES[i]),
+        _ => {
+            eprintln!("no traced grammar '{grammar}'");
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Parsers generated by `build.rs`, one module per grammar: `<name>::parser`
+//! is the recursive parser, `<name>::parser::pushdown` its nested pushdown
+//! machine, `<name>::SAMPLES` inputs walked from the grammar (those at
+//! `<name>::HANGS` never finish parsing, so most tests skip them). Each module
+//! also gets `recursive` / `chunked`, the two event logs the tests in
+//! `tests/` compare, one `format_line` per event. Traced grammars add
+//! `<name>::traced` (the `trace` build) and `trace`, which `src/bin/trace.rs`
+//! runs so a test can capture the stderr trace.
+//!
+//! `markdown_async` is markdown's pushdown machine built standalone with
+//! the async adapter, emitting `markdown::parser`'s events. `C_DIR` holds
+//! the C backend's output, one `<name>/` directory per grammar.
+
+macro_rules! grammar {
+    ($name:ident) => {
+        grammar!(@module $name {});
+    };
+    ($name:ident, traced) => {
+        grammar!(@module $name {
+            #[allow(clippy::all, dead_code, unused)]
+            pub mod traced {
+                include!(concat!(env!("OUT_DIR"), "/", stringify!($name), "_trace.rs"));
+            }
+
+            /// Parse `input` with the traced recursive parser, or the traced
+            /// pushdown machine fed in one chunk; the trace goes to stderr.
+            pub fn trace(pushdown: bool, input: &[u8]) {
+                if pushdown {
+                    let mut p = traced::pushdown::PushdownParser::new();
+                    p.push_chunk(input, &mut |_| {});
+                    p.finish(&mut |_| {});
+                } else {
+                    traced::Parser::new(input).parse(|_| {});
+                }
+            }
+        });
+    };
+    (@module $name:ident { $($traced:tt)* }) => {
+        pub mod $name {
+            #[allow(clippy::all, dead_code, unused)]
+            pub mod parser {
+                include!(concat!(env!("OUT_DIR"), "/", stringify!($name), ".rs"));
+            }
+
+            include!(concat!(env!("OUT_DIR"), "/", stringify!($name), "_samples.rs"));
+
+            /// A pushdown event as the recursive parser's `format_line`
+            /// renders it.
+            pub fn stream_line(e: parser::StreamEvent<'_>) -> String {
+                e.into_event(|c| std::borrow::Cow::Owned(c.into_owned())).format_line()
+            }
+
+            /// One-shot parse with the recursive parser.
+            pub fn recursive(input: &[u8]) -> Vec<String> {
+                let mut log = Vec::new();
+                parser::Parser::new(input).parse(|e| log.push(e.format_line()));
+                log
+            }
+
+            /// Parse with the pushdown machine, `input` pushed `chunk` bytes
+            /// at a time.
+            pub fn chunked(input: &[u8], chunk: usize) -> Vec<String> {
+                let mut log = Vec::new();
+                let mut on_event = |e: parser::StreamEvent<'_>| log.push(stream_line(e));
+                let mut p = parser::pushdown::PushdownParser::new();
+                for piece in input.chunks(chunk.max(1)) {
+                    p.push_chunk(piece, &mut on_event);
+                }
+                p.finish(&mut on_event);
+                log
+            }
+
+            $($traced)*
+        }
+    };
+}
+
+grammar!(borderline);
+grammar!(udon_complete, traced);
+grammar!(markdown, traced);
+grammar!(elements, traced);
+grammar!(brackets);
+grammar!(tiny);
+
+/// The generated C pairs: `<C_DIR>/<name>/parser.{h,c}`.
+pub const C_DIR: &str = concat!(env!("OUT_DIR"), "/c");
+
+#[allow(clippy::all, dead_code, unused, unexpected_cfgs)]
+pub mod markdown_async {
+    include!(concat!(env!("OUT_DIR"), "/markdown_async.rs"));
+}
@@ -0,0 +1,14 @@
+//! Grammar names that match generated fields (`grammars/borderline.desc`)
+//! compile, and both backends run them alike.
+
+use descent_tests::borderline::{chunked, recursive};
+
+#[test]
+fn field_named_locals_compile_and_parse() {
+    let input = b"ab  abcdef\nx y\n";
+    let events = recursive(input);
+    assert_eq!(events, ["Word \"ab\" @ 0..2", "Word \"abcdef\" @ 4..10", "Word \"x\" @ 11..12", "Word \"y\" @ 13..14"]);
+    for chunk in [1, 3, input.len()] {
+        assert_eq!(chunked(input, chunk), events, "chunk size {chunk}");
+    }
+}
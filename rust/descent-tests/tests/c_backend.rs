@@ -0,0 +1,87 @@
+//! The C backend against the recursive Rust one, by event stream: each
+//! grammar's generated C pair (`C_DIR`), compiled with the system `cc`
+//! (the test is skipped when there is none), prints
+//! `parser_format_event` lines that must equal the Rust `format_line` log
+//! for the same input. Samples in `HANGS` are skipped.
+
+use descent_tests::{borderline, brackets, elements, markdown, tiny, udon_complete, C_DIR};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// A grammar's name, samples, hanging samples and recursive-parser log.
+type Grammar = (&'static str, &'static [&'static [u8]], &'static [usize], fn(&[u8]) -> Vec<String>);
+
+const DRIVER: &str = r#"#include "parser.h"
+#include <stdio.h>
+
+static void print(const parser_event *ev, void *user)
+{
+    static char line[1 << 16];
+    (void)user;
+    parser_format_event(ev, line, sizeof line);
+    puts(line);
+}
+
+int main(void)
+{
+    static uint8_t buf[1 << 20];
+    size_t n = fread(buf, 1, sizeof buf, stdin);
+    return parser_parse(buf, n, print, NULL) != 0;
+}
+"#;
+
+/// Compile `<C_DIR>/<grammar>` with the driver into `work`; the binary.
+fn compile(grammar: &str, work: &Path) -> PathBuf {
+    let src = Path::new(C_DIR).join(grammar);
+    let driver = work.join("driver.c");
+    std::fs::write(&driver, DRIVER).unwrap();
+    let bin = work.join(grammar);
+    let out = Command::new("cc")
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-I"])
+        .arg(&src)
+        .arg("-o")
+        .arg(&bin)
+        .arg(&driver)
+        .arg(src.join("parser.c"))
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{grammar}: cc failed\n{}", String::from_utf8_lossy(&out.stderr));
+    bin
+}
+
+fn c_events(bin: &Path, input: &[u8]) -> Vec<String> {
+    let mut child = Command::new(bin).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let out = child.wait_with_output().unwrap();
+    String::from_utf8_lossy(&out.stdout).lines().map(str::to_string).collect()
+}
+
+#[test]
+fn compiles_and_matches_rust_events() {
+    if Command::new("cc").arg("--version").output().is_err() {
+        return;
+    }
+    let work = std::env::temp_dir().join(format!("descent-c-{}", std::process::id()));
+    std::fs::create_dir_all(&work).unwrap();
+    let grammars: [Grammar; 6] = [
+        ("borderline", borderline::SAMPLES, borderline::HANGS, borderline::recursive),
+        ("udon_complete", udon_complete::SAMPLES, udon_complete::HANGS, udon_complete::recursive),
+        ("markdown", markdown::SAMPLES, markdown::HANGS, markdown::recursive),
+        ("elements", elements::SAMPLES, elements::HANGS, elements::recursive),
+        ("brackets", brackets::SAMPLES, brackets::HANGS, brackets::recursive),
+        ("tiny", tiny::SAMPLES, tiny::HANGS, tiny::recursive),
+    ];
+    for (name, samples, hangs, recursive) in grammars {
+        let bin = compile(name, &work);
+        for (i, input) in samples.iter().enumerate().filter(|(i, _)| !hangs.contains(i)) {
+            assert_eq!(c_events(&bin, input), recursive(input), "{name} sample {i}");
+        }
+        // Keywords, the fallback and an error case on one line.
+        if name == "tiny" {
+            let input = b"if x1 else foo";
+            assert_eq!(c_events(&bin, input), recursive(input));
+        }
+    }
+    std::fs::remove_dir_all(&work).ok();
+}
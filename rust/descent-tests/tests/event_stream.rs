@@ -0,0 +1,46 @@
+//! `EventStream` over an in-memory `AsyncRead` yields the events of the
+//! sync parser, however the reads are cut.
+
+use descent_tests::markdown::{recursive, stream_line, SAMPLES};
+use descent_tests::markdown_async::{EventStream, PushdownParser};
+use futures::io::{AsyncRead, Cursor};
+use futures::StreamExt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Reads at most `max` bytes at a time, and is `Pending` (after waking the
+/// task) before every other read.
+struct Trickle {
+    inner: Cursor<Vec<u8>>,
+    max: usize,
+    stall: bool,
+}
+
+impl AsyncRead for Trickle {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<std::io::Result<usize>> {
+        self.stall = !self.stall;
+        if self.stall {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        let n = buf.len().min(self.max);
+        Pin::new(&mut self.inner).poll_read(cx, &mut buf[..n])
+    }
+}
+
+fn streamed(reader: impl AsyncRead + Unpin, chunk_size: usize) -> Vec<String> {
+    let stream = EventStream::with_parser(reader, PushdownParser::new(), chunk_size);
+    futures::executor::block_on(stream.map(stream_line).collect())
+}
+
+#[test]
+fn small_reads_match_the_sync_parser() {
+    for (i, input) in SAMPLES.iter().enumerate() {
+        let whole = recursive(input);
+        for size in [1, 2, 5] {
+            assert_eq!(streamed(Cursor::new(input.to_vec()), size), whole, "sample {i}, {size}-byte reads");
+            let trickle = Trickle { inner: Cursor::new(input.to_vec()), max: size, stall: false };
+            assert_eq!(streamed(trickle, 64), whole, "sample {i}, trickled {size} bytes at a time");
+        }
+    }
+}
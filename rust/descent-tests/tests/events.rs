@@ -0,0 +1,71 @@
+//! `Parser::events()` (the pull iterator over the pushdown machine) yields
+//! exactly what `Parser::parse` hands its callback.
+
+use descent_tests::{brackets, elements, markdown, udon_complete};
+
+macro_rules! pulled {
+    ($g:ident, $input:expr) => {
+        $g::parser::Parser::new($input).events().map(|e| e.format_line()).collect::<Vec<_>>()
+    };
+}
+
+#[test]
+fn samples_pull_the_callback_events() {
+    for (i, input) in markdown::SAMPLES.iter().enumerate() {
+        assert_eq!(pulled!(markdown, input), markdown::recursive(input), "markdown sample {i}");
+    }
+    for (i, input) in elements::SAMPLES.iter().enumerate() {
+        assert_eq!(pulled!(elements, input), elements::recursive(input), "elements sample {i}");
+    }
+    for (i, input) in udon_complete::SAMPLES.iter().enumerate().filter(|(i, _)| !udon_complete::HANGS.contains(i)) {
+        assert_eq!(pulled!(udon_complete, input), udon_complete::recursive(input), "udon_complete sample {i}");
+    }
+    for (i, input) in brackets::SAMPLES.iter().enumerate() {
+        assert_eq!(pulled!(brackets, input), brackets::recursive(input), "brackets sample {i}");
+    }
+}
+
+#[test]
+fn errors_and_eof_unwinding_pull_the_callback_events() {
+    // An unclosed fence: an Error at EOF.
+    let fence = b"para\n\n```\ncode\n";
+    let events = pulled!(markdown, fence);
+    assert!(events.iter().any(|e| e.starts_with("Error UnclosedCodeBlock")), "{events:?}");
+    assert_eq!(events, markdown::recursive(fence));
+
+    // Nested open constructs closed by EOF, innermost first.
+    let open = b"> - *a ~~b __c";
+    let events = pulled!(markdown, open);
+    assert!(events.last().is_some_and(|e| e.starts_with("BlockquoteEnd")), "{events:?}");
+    assert_eq!(events, markdown::recursive(open));
+
+    // Unbalanced brackets: an error, and frames unwound at EOF.
+    for input in [&b"[[]"[..], b"]", b"[[[("] {
+        assert_eq!(pulled!(brackets, input), brackets::recursive(input), "{:?}", input.escape_ascii().to_string());
+    }
+}
+
+#[test]
+fn inputs_past_one_refill_pull_the_callback_events() {
+    // Several PD_EVENTS_CHUNK (4 KiB) refills, with constructs across seams.
+    let all: Vec<u8> = markdown::SAMPLES.concat();
+    let input = all.repeat(3 * 4096 / all.len() + 1);
+    assert_eq!(pulled!(markdown, &input), markdown::recursive(&input));
+}
+
+#[test]
+fn captured_content_borrows_the_input() {
+    // Text across a refill seam too: `input[span]`, not a copy.
+    let input = b"word ".repeat(4096 / 5 + 100);
+    let range = input.as_ptr_range();
+    let mut texts = 0;
+    for e in markdown::parser::Parser::new(&input).events() {
+        if let markdown::parser::Event::Text { content, span } = e {
+            let std::borrow::Cow::Borrowed(s) = content else { panic!("owned Text at {span:?}") };
+            assert!(range.contains(&s.as_ptr()), "Text at {span:?} is not in the input");
+            assert_eq!(s, &input[span]);
+            texts += 1;
+        }
+    }
+    assert!(texts > 0);
+}
@@ -0,0 +1,82 @@
+//! `IncrementalParser::edit` leaves exactly the events a fresh parse of the
+//! edited document emits, whether or not the reparse re-converges.
+
+use descent_tests::markdown::{self, recursive, stream_line, SAMPLES};
+use markdown::parser::pushdown::{EventSplice, IncrementalParser};
+
+fn lines(ip: &IncrementalParser) -> Vec<String> {
+    ip.events().iter().cloned().map(stream_line).collect()
+}
+
+/// Apply the edit to a copy of the text and parse that from scratch too.
+fn edit_and_check(ip: &mut IncrementalParser, range: std::ops::Range<usize>, replacement: &[u8]) -> EventSplice {
+    let mut text = ip.text().to_vec();
+    text.splice(range.clone(), replacement.iter().copied());
+    let splice = ip.edit(range, replacement);
+    assert_eq!(ip.text(), text);
+    assert_eq!(lines(ip), recursive(&text), "after editing to {:?}", text.escape_ascii().to_string());
+    splice
+}
+
+const DOC: &[u8] = b"# Title\n\nSome *text* here.\n\n- one\n- two\n\nA last `code` paragraph.\n";
+
+#[test]
+fn a_local_edit_reconverges() {
+    let mut ip = IncrementalParser::new(DOC.to_vec(), 8);
+    assert_eq!(lines(&ip), recursive(DOC));
+    let total = ip.events().len();
+
+    // "text" -> "words", inside the second paragraph.
+    let splice = edit_and_check(&mut ip, 15..19, b"words");
+    assert!(splice.new.end < ip.events().len(), "{splice:?} reparsed to the end");
+    assert_eq!(ip.events().len(), total);
+
+    // A line added and one removed: later spans and lines shift.
+    let splice = edit_and_check(&mut ip, 9..9, b"New line.\n\n");
+    assert!(splice.new.end < ip.events().len(), "{splice:?} reparsed to the end");
+    edit_and_check(&mut ip, 9..20, b"");
+}
+
+#[test]
+fn an_edit_that_changes_the_rest_reparses_to_the_end() {
+    let mut ip = IncrementalParser::new(DOC.to_vec(), 8);
+    // An unclosed fence swallows everything after it.
+    let splice = edit_and_check(&mut ip, 9..9, b"```\n");
+    assert_eq!(splice.new.end, ip.events().len());
+    // Closing it again reconverges on the old tail's checkpoints.
+    edit_and_check(&mut ip, 9..13, b"");
+}
+
+#[test]
+fn edits_anywhere_match_a_fresh_parse() {
+    for sample in SAMPLES.iter().step_by(3) {
+        for interval in [4, 16] {
+            let mut ip = IncrementalParser::new(sample.to_vec(), interval);
+            for at in (0..sample.len()).step_by(11) {
+                let end = (at + 3).min(ip.text().len());
+                let replacement: &[u8] = match at % 3 {
+                    0 => b"*a* ",
+                    1 => b"\n\n",
+                    _ => b"",
+                };
+                edit_and_check(&mut ip, at.min(end)..end, replacement);
+            }
+        }
+    }
+}
+
+#[test]
+fn out_of_range_edits_are_clamped() {
+    let mut ip = IncrementalParser::new(DOC.to_vec(), 8);
+    let len = DOC.len();
+    ip.edit(len - 1..len + 10, b" end\n");
+    let mut text = DOC[..len - 1].to_vec();
+    text.extend_from_slice(b" end\n");
+    assert_eq!(ip.text(), text);
+    assert_eq!(lines(&ip), recursive(&text));
+
+    // Inverted: an insertion at the (clamped) end.
+    ip.edit(std::ops::Range { start: usize::MAX, end: 3 }, b"!");
+    text.insert(3, b'!');
+    assert_eq!(lines(&ip), recursive(&text));
+}
@@ -0,0 +1,34 @@
+//! A `|keywords` fallback that takes arguments (`grammars/tiny.desc`,
+//! `:fallback /word(1)`): the pushdown machine binds them like any other
+//! call, and both backends hand them to the fallback.
+
+use descent_tests::tiny::{chunked, recursive, SAMPLES};
+
+#[test]
+fn fallback_args_reach_the_fallback() {
+    let input = b"if x1 else foo";
+    let events = recursive(input);
+    // `x` and `foo` miss the map; `word` errors unless it got `n == 1`.
+    // (The one error is `doc` rejecting the digit.)
+    assert_eq!(
+        events,
+        [
+            "ListStart @ 0..0",
+            "Kw \"if\" @ 0..2",
+            "Word \"\" @ 4..4",
+            "Error UnexpectedChar @ 4..4",
+            "Kw \"else\" @ 6..10",
+            "Word \"\" @ 14..14",
+            "ListEnd @ 14..14",
+        ]
+    );
+    for chunk in [1, 2, input.len()] {
+        assert_eq!(chunked(input, chunk), events, "chunk size {chunk}");
+    }
+    for (i, input) in SAMPLES.iter().enumerate() {
+        let events = recursive(input);
+        for chunk in [1, 2, input.len()] {
+            assert_eq!(chunked(input, chunk), events, "sample {i}, chunk size {chunk}");
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! `PushdownParser::with_limits`: tripping either limit reports an
+//! `Error(LimitExceeded)` event at the offset it returns and kills the parser.
+
+use descent_tests::{borderline, brackets};
+
+#[test]
+fn buffer_limit_trips_at_the_retained_capture() {
+    use borderline::parser::{pushdown::PushdownParser, LimitKind, ParseResult};
+    let mut log = Vec::new();
+    let mut on_event = |e: borderline::parser::StreamEvent<'_>| log.push(borderline::stream_line(e));
+    let mut p = PushdownParser::with_limits(8, usize::MAX);
+    // One word across chunks: the open capture is retained whole.
+    assert_eq!(p.push_chunk(b"ab cdef", &mut on_event), ParseResult::NeedMoreData);
+    assert_eq!(p.push_chunk(b"ghij", &mut on_event), ParseResult::NeedMoreData);
+    let tripped = p.push_chunk(b"klm", &mut on_event);
+    assert_eq!(tripped, ParseResult::LimitExceeded { kind: LimitKind::Buffer, offset: 14 });
+    // Dead from then on, and finish reports the same.
+    assert_eq!(p.push_chunk(b" n", &mut on_event), tripped);
+    assert_eq!(p.finish(&mut on_event), tripped);
+    assert_eq!(log, ["Word \"ab\" @ 0..2", "Error LimitExceeded @ 14..14"]);
+}
+
+#[test]
+fn depth_limit_trips_at_the_opening_byte() {
+    use brackets::parser::{pushdown::PushdownParser, LimitKind, ParseResult};
+    let mut log = Vec::new();
+    let mut on_event = |e: brackets::parser::StreamEvent<'_>| log.push(brackets::stream_line(e));
+    // document + 2 bracket frames fit; the third `[` goes one deeper.
+    let mut p = PushdownParser::with_limits(usize::MAX, 3);
+    let r = p.push_chunk(b"[[[]]]", &mut on_event);
+    assert_eq!(r, ParseResult::LimitExceeded { kind: LimitKind::Depth, offset: 3 });
+    assert_eq!(p.finish(&mut on_event), r);
+    assert_eq!(log.last().map(String::as_str), Some("Error LimitExceeded @ 3..3"));
+
+    // The same input under a roomy limit parses through.
+    let mut p = PushdownParser::with_limits(usize::MAX, 4);
+    assert_eq!(p.push_chunk(b"[[[]]]", &mut |_| {}), ParseResult::NeedMoreData);
+    assert_eq!(p.finish(&mut |_| {}), ParseResult::Complete);
+}
@@ -0,0 +1,75 @@
+//! `PushdownParser::snapshot` / `restore`: a parse checkpointed mid-stream
+//! and resumed in another parser emits exactly the uninterrupted events.
+
+use descent_tests::markdown::{self, chunked, stream_line, SAMPLES};
+use markdown::parser::pushdown::{ParserSnapshot, PushdownParser};
+use markdown::parser::{LimitKind, ParseResult, StreamEvent};
+
+/// Push `input[..at]`, snapshot, and hand the snapshot to `resume`, which
+/// returns the parser to continue in; push the rest there and finish.
+fn split_run(input: &[u8], at: usize, resume: impl FnOnce(ParserSnapshot) -> PushdownParser) -> Vec<String> {
+    let mut log = Vec::new();
+    let mut on_event = |e: StreamEvent<'_>| log.push(stream_line(e));
+    let mut first = PushdownParser::new();
+    first.push_chunk(&input[..at], &mut on_event);
+    let snapshot = first.snapshot();
+    drop(first);
+    assert_eq!(snapshot.offset(), at);
+    let mut second = resume(snapshot);
+    second.push_chunk(&input[at..], &mut on_event);
+    second.finish(&mut on_event);
+    log
+}
+
+fn restored(snapshot: ParserSnapshot) -> PushdownParser {
+    let mut p = PushdownParser::new();
+    p.restore(snapshot);
+    p
+}
+
+#[test]
+fn restore_resumes_mid_stream() {
+    for (i, input) in SAMPLES.iter().enumerate() {
+        let whole = chunked(input, input.len());
+        for at in (0..=input.len()).step_by(5) {
+            assert_eq!(split_run(input, at, restored), whole, "sample {i} split at {at}");
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn snapshot_round_trips_through_serde() {
+    for (i, input) in SAMPLES.iter().enumerate() {
+        let whole = chunked(input, input.len());
+        for at in (0..=input.len()).step_by(7) {
+            let via_json = split_run(input, at, |s| {
+                let json = serde_json::to_string(&s).unwrap();
+                restored(serde_json::from_str(&json).unwrap())
+            });
+            assert_eq!(via_json, whole, "sample {i} split at {at}");
+        }
+    }
+}
+
+#[test]
+fn restore_keeps_the_receiving_parsers_limits() {
+    // A list item nests a frame per level: `- - - x` is four frames deep
+    // by the time `x` is read.
+    let input = b"- - - - x\n";
+    let whole = chunked(input, input.len());
+
+    let roomy = split_run(input, 3, |s| {
+        let mut p = PushdownParser::with_limits(1 << 10, 64);
+        p.restore(s);
+        p
+    });
+    assert_eq!(roomy, whole);
+
+    let mut first = PushdownParser::new();
+    first.push_chunk(&input[..3], &mut |_| {});
+    let mut tight = PushdownParser::with_limits(1 << 10, 2);
+    tight.restore(first.snapshot());
+    let r = tight.push_chunk(&input[3..], &mut |_| {});
+    assert!(matches!(r, ParseResult::LimitExceeded { kind: LimitKind::Depth, .. }), "{r:?}");
+}
@@ -0,0 +1,43 @@
+//! `StreamingParser` fed in small chunks emits exactly the events of a
+//! one-shot recursive parse.
+
+use descent_tests::markdown::{self, recursive, stream_line, SAMPLES};
+use markdown::parser::{StreamEvent, StreamingParser};
+
+fn streamed(input: &[u8], chunk: usize) -> Vec<String> {
+    let mut log = Vec::new();
+    let mut parser = StreamingParser::new();
+    for piece in input.chunks(chunk.max(1)) {
+        parser.parse(piece, |e: StreamEvent<'_>| log.push(stream_line(e)));
+    }
+    parser.finish(|e: StreamEvent<'_>| log.push(stream_line(e)));
+    log
+}
+
+#[test]
+fn small_chunks_match_a_one_shot_parse() {
+    for (i, input) in SAMPLES.iter().enumerate() {
+        let whole = recursive(input);
+        for chunk in [1, 2, 3] {
+            assert_eq!(streamed(input, chunk), whole, "sample {i}, {chunk}-byte chunks");
+        }
+    }
+}
+
+#[test]
+fn every_seam_matches_a_one_shot_parse() {
+    // A `~~` closer split across chunks (the second `->` used to be pended
+    // and the end span emitted a byte early), and a `~` whose flanking
+    // check reads PREV across a drained seam.
+    for input in [&b"x ~~a~~ b\n"[..], b"\t~~~_\nZ\n"] {
+        let whole = recursive(input);
+        for at in 0..=input.len() {
+            let mut log = Vec::new();
+            let mut parser = StreamingParser::new();
+            parser.parse(&input[..at], |e: StreamEvent<'_>| log.push(stream_line(e)));
+            parser.parse(&input[at..], |e: StreamEvent<'_>| log.push(stream_line(e)));
+            parser.finish(|e: StreamEvent<'_>| log.push(stream_line(e)));
+            assert_eq!(log, whole, "{:?} split at {at}", input.escape_ascii().to_string());
+        }
+    }
+}
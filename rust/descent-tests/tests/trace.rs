@@ -0,0 +1,69 @@
+//! The pushdown trace, minus its `TRACE: PD` machinery lines, matches the
+//! recursive parser's trace line for line (`emit::rust_pushdown` docs).
+//! Traces go to stderr, so each run is a `trace` child process.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+/// Lines compared per run: the `HANGS` samples never terminate (in either
+/// backend), so each trace is cut here and the prefixes compared.
+const MAX_LINES: usize = 20_000;
+
+fn trace(grammar: &str, backend: &str, sample: usize) -> Vec<String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_trace"))
+        .args([grammar, backend, &sample.to_string()])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let lines = BufReader::new(child.stderr.take().unwrap())
+        .lines()
+        .map(Result::unwrap)
+        .filter(|l| !l.starts_with("TRACE: PD"))
+        .take(MAX_LINES)
+        .collect();
+    let _ = child.kill();
+    let _ = child.wait();
+    lines
+}
+
+fn check(grammar: &str, samples: usize) {
+    for i in 0..samples {
+        let recursive = trace(grammar, "recursive", i);
+        let pushdown = trace(grammar, "pushdown", i);
+        assert!(!recursive.is_empty(), "{grammar} sample {i}: no trace");
+        if let Some(at) = recursive.iter().zip(&pushdown).position(|(r, p)| r != p) {
+            panic!("{grammar} sample {i}, line {}:\n  recursive: {}\n  pushdown:  {}", at + 1, recursive[at], pushdown[at]);
+        }
+        assert_eq!(recursive.len(), pushdown.len(), "{grammar} sample {i}: trace lengths");
+    }
+}
+
+#[test]
+fn udon_complete_traces_match() {
+    check("udon_complete", descent_tests::udon_complete::SAMPLES.len());
+}
+
+/// The recorded hangs still spin: the trace runs to the cut, ending in a
+/// short cycle that doesn't move. Fixing the grammar fails this until the
+/// sample leaves `HANGS`.
+#[test]
+fn udon_complete_hangs_are_recorded() {
+    for &i in descent_tests::udon_complete::HANGS {
+        for backend in ["recursive", "pushdown"] {
+            let lines = trace("udon_complete", backend, i);
+            assert_eq!(lines.len(), MAX_LINES, "udon_complete sample {i} ({backend}) terminates");
+            let tail: std::collections::BTreeSet<&String> = lines[MAX_LINES - 100..].iter().collect();
+            assert!(tail.len() <= 4, "udon_complete sample {i} ({backend}): no cycle in {tail:?}");
+        }
+    }
+}
+
+#[test]
+fn markdown_traces_match() {
+    check("markdown", descent_tests::markdown::SAMPLES.len());
+}
+
+#[test]
+fn elements_traces_match() {
+    check("elements", descent_tests::elements::SAMPLES.len());
+}
@@ -0,0 +1,116 @@
+#!/usr/bin/env bash
+# Differential test: C backend vs the recursive Rust backend, by event
+# stream. Per fixture grammar: sample inputs from the grammar
+# (`descent-rs sample`), run each through both generated parsers, and diff
+# the `format_line` renderings (`<prefix>_format_event` on the C side).
+#
+# The Rust side builds offline against the workspace's Cargo.lock, so the
+# parser's crates (memchr, phf, unicode-xid) must already be in the local
+# cargo registry — a workspace build puts them there.
+#
+# Usage: bash rust/tools/diff_c.sh [COUNT]   (from the descent repo root)
+set -u
+cd "$(dirname "$0")/../.." || exit 1
+
+cargo build --quiet --manifest-path rust/Cargo.toml || exit 1
+RS=rust/target/debug/descent-rs
+CC=${CC:-cc}
+COUNT=${1:-50}
+
+work=$(mktemp -d)
+trap 'rm -rf "$work"' EXIT
+
+# Rust driver crate: one parser module swapped in per fixture.
+mkdir -p "$work/rs/src"
+cp rust/Cargo.lock "$work/rs/"
+cat >"$work/rs/Cargo.toml" <<'EOF'
+[package]
+name = "diff-c-driver"
+version = "0.0.0"
+edition = "2021"
+
+[workspace]
+
+[dependencies]
+memchr = "2"
+phf = { version = "0.11", features = ["macros"] }
+unicode-xid = "0.2"
+
+[features]
+serde = []
+EOF
+cat >"$work/rs/src/main.rs" <<'EOF'
+#[allow(dead_code, unused, clippy::all)]
+mod parser;
+
+fn main() {
+    let input = std::fs::read(std::env::args().nth(1).unwrap()).unwrap();
+    parser::Parser::new(&input).parse(|e| println!("{}", e.format_line()));
+}
+EOF
+
+cat >"$work/driver.c" <<'EOF'
+#include "parser.h"
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+static void print(const parser_event *ev, void *user)
+{
+    static char line[1 << 16];
+    (void)user;
+    parser_format_event(ev, line, sizeof line);
+    puts(line);
+}
+
+int main(int argc, char **argv)
+{
+    static uint8_t buf[1 << 20];
+    FILE *f = argc > 1 ? fopen(argv[1], "rb") : NULL;
+    size_t n;
+    if (f == NULL) {
+        return 2;
+    }
+    n = fread(buf, 1, sizeof buf, f);
+    fclose(f);
+    setvbuf(stdout, NULL, _IOLBF, 0);
+    return parser_parse(buf, n, print, NULL) != 0;
+}
+EOF
+
+fail=0
+for desc in rust/tests/fixtures/*.desc; do
+  base=$(basename "$desc" .desc)
+  rm -rf "$work/in" "$work/c" && mkdir -p "$work/in" "$work/c"
+  "$RS" sample "$desc" --count "$COUNT" --seed 1 --out "$work/in" >/dev/null 2>&1 || { echo "FAIL $base (sample error)"; fail=1; continue; }
+  "$RS" generate "$desc" >"$work/rs/src/parser.rs" 2>/dev/null || { echo "FAIL $base (rust generate error)"; fail=1; continue; }
+  "$RS" generate "$desc" --backend c --out "$work/c" >/dev/null || { echo "FAIL $base (c generate error)"; fail=1; continue; }
+  # A grammar the Rust backend can't compile has no reference to diff
+  # against (e.g. a delimited function's Warning without a Warning type).
+  cargo build --quiet --offline --manifest-path "$work/rs/Cargo.toml" 2>"$work/err" || { echo "SKIP $base (Rust parser does not compile: $(grep -m1 '^error' "$work/err"))"; continue; }
+  $CC -std=c99 -Wall -Wextra -Werror -I "$work/c" -o "$work/c/drv" "$work/driver.c" "$work/c/parser.c" 2>"$work/err" || { echo "FAIL $base (cc)"; head -20 "$work/err"; fail=1; continue; }
+  # One run per input, bounded: some fixture grammars never terminate on
+  # some inputs (in both backends), so a run is cut at 1s / 1000 lines and
+  # the prefixes compared. Hanging in both is agreement, and counted.
+  hung=0 bad=0
+  for input in $(find "$work/in" -type f | sort); do
+    timeout 1 "$work/rs/target/debug/diff-c-driver" "$input" 2>&1 | head -n 1000 >"$work/rs.out" &
+    timeout 1 "$work/c/drv" "$input" 2>&1 | head -n 1000 >"$work/c.out"
+    c_rc=${PIPESTATUS[0]}
+    wait
+    [ "$c_rc" = 124 ] && hung=$((hung + 1))
+    if ! d=$(diff "$work/rs.out" "$work/c.out"); then
+      [ $bad = 0 ] && { echo "FAIL $base $(basename "$input")"; echo "$d" | head -20; }
+      bad=$((bad + 1))
+    fi
+  done
+  if [ $bad = 0 ]; then
+    note=""
+    [ $hung -gt 0 ] && note=" ($hung inputs hang in both)"
+    echo "OK   $base$note"
+  else
+    echo "FAIL $base ($bad of $COUNT inputs differ)"
+    fail=1
+  fi
+done
+exit $fail